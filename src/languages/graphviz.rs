@@ -0,0 +1,146 @@
+use std::path::Path;
+
+use crate::{
+    output::*,
+    types::{ArrayType, UserDefinitionLink, ValueType},
+    RuneFileDescription, RuneParserError
+};
+
+/// Indentation of statements inside the graph and its file clusters
+const INDENTATION: &str = "    ";
+
+/// Write a Graphviz graph of all definitions and the types they reference to the output path, such as `output/types.dot`
+pub fn output_type_graph(file_descriptions: &[RuneFileDescription], output_path: &Path) -> Result<(), RuneParserError> {
+    info!("Writing type graph to {0:?}", output_path);
+
+    let graph: String = type_graph_string(file_descriptions);
+
+    if let Some(directory) = output_path.parent() {
+        if let Err(error) = std::fs::create_dir_all(directory) {
+            error!("Could not create output directory {0:?}. Got error {1}", directory, error);
+            return Err(RuneParserError::FileSystemError);
+        }
+    }
+
+    if let Err(error) = std::fs::write(output_path, graph) {
+        error!("Could not write type graph to {0:?}. Got error {1}", output_path, error);
+        return Err(RuneParserError::FileSystemError);
+    }
+
+    Ok(())
+}
+
+/// Generate a Graphviz graph with a node per definition, shaped by its kind, and an edge for every message field and struct member
+/// referencing another definition. Definitions are clustered by the file declaring them
+pub fn type_graph_string(file_descriptions: &[RuneFileDescription]) -> String {
+    let mut output = String::from("digraph rune {\n");
+    output.push_str(&format!("{0}node [fontname=\"Helvetica\"];\n", INDENTATION));
+
+    // Nodes
+    // ——————
+
+    for (file_index, file) in file_descriptions.iter().enumerate() {
+        output.push('\n');
+        output.push_str(&format!("{0}subgraph cluster_{1} {{\n", INDENTATION, file_index));
+        output.push_str(&format!("{0}{0}label = {1};\n", INDENTATION, quoted(&format!("{0}.rune", file.include_path()))));
+
+        let definitions = &file.definitions;
+
+        let nodes = (definitions.bitfields.iter().map(|definition| (&definition.name, "diamond")))
+            .chain(definitions.enums.iter().map(|definition| (&definition.name, "ellipse")))
+            .chain(definitions.messages.iter().map(|definition| (&definition.name, "hexagon")))
+            .chain(definitions.structs.iter().map(|definition| (&definition.name, "box")));
+
+        for (name, shape) in nodes {
+            output.push_str(&format!("{0}{0}{1} [shape={2}];\n", INDENTATION, quoted(name), shape));
+        }
+
+        output.push_str(&format!("{0}}}\n", INDENTATION));
+    }
+
+    // Edges
+    // ——————
+
+    let mut edges: Vec<(&str, &str, &str)> = Vec::new();
+
+    for file in file_descriptions {
+        for struct_definition in &file.definitions.structs {
+            for member in &struct_definition.members {
+                if let Some(target) = referenced_type(&member.data_type) {
+                    edges.push((&struct_definition.name, target, &member.identifier));
+                }
+            }
+        }
+
+        for message_definition in &file.definitions.messages {
+            for field in &message_definition.fields {
+                if let Some(target) = field.data_type.value_type().and_then(referenced_type) {
+                    edges.push((&message_definition.name, target, &field.identifier));
+                }
+            }
+        }
+    }
+
+    if !edges.is_empty() {
+        output.push('\n');
+    }
+
+    for (source, target, identifier) in edges {
+        output.push_str(&format!("{0}{1} -> {2} [label={3}];\n", INDENTATION, quoted(source), quoted(target), quoted(identifier)));
+    }
+
+    output.push_str("}\n");
+    output
+}
+
+/// Name of the definition a value refers to, looking through arrays to the type of their elements
+fn referenced_type(value_type: &ValueType) -> Option<&str> {
+    let (name, link) = match value_type {
+        ValueType::UserDefined(name, link) => (name, link),
+        ValueType::Array(array) => match array.innermost_type() {
+            ArrayType::UserDefined(name, link) => (name, link),
+            _ => return None
+        },
+        ValueType::Primitive(_) => return None
+    };
+
+    // Prefer the name of the linked definition, falling back to the written name for files that were not linked
+    Some(match link {
+        UserDefinitionLink::NoLink => name,
+        UserDefinitionLink::BitfieldLink(definition) => &definition.name,
+        UserDefinitionLink::EnumLink(definition) => &definition.name,
+        UserDefinitionLink::MessageLink(definition) => &definition.name,
+        UserDefinitionLink::StructLink(definition) => &definition.name
+    })
+}
+
+/// Graphviz ID as a double quoted string
+fn quoted(text: &str) -> String {
+    format!("\"{0}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{languages::graphviz::type_graph_string, test_utils::parse_files};
+
+    #[test]
+    fn small_schema_has_nodes_and_edges() {
+        let files = parse_files(&[(
+            "device.rune",
+            "bitfield Flags: u8 {\n    ready: u1 = 0;\n}\n\nenum Mode: u8 {\n    Off = 0;\n}\n\nstruct Point {\n    x: i16 = 0;\n}\n\n\
+             message Status {\n    flags: Flags = 1;\n    mode: Mode = 2;\n    path: [Point; 4] = 3;\n    count: u8 = 4;\n}\n"
+        )])
+        .unwrap();
+        let graph = type_graph_string(&files);
+
+        assert!(graph.starts_with("digraph rune {\n"));
+        assert!(graph.contains("subgraph cluster_0 {\n        label = \"device.rune\";\n"));
+        for node in ["\"Flags\" [shape=diamond];", "\"Mode\" [shape=ellipse];", "\"Status\" [shape=hexagon];", "\"Point\" [shape=box];"] {
+            assert!(graph.contains(node), "Missing node {0}", node);
+        }
+
+        // Only fields referencing another definition have an edge
+        let edges: Vec<&str> = graph.lines().map(str::trim).filter(|line| line.contains("->")).collect();
+        assert_eq!(edges, ["\"Status\" -> \"Flags\" [label=\"flags\"];", "\"Status\" -> \"Mode\" [label=\"mode\"];", "\"Status\" -> \"Point\" [label=\"path\"];"]);
+    }
+}
@@ -0,0 +1,478 @@
+use std::{fmt::Display, path::Path};
+
+use crate::{
+    output::*,
+    scanner::NumericLiteral,
+    types::{Array, ArrayType, BitSize, BitfieldDefinition, DefineValue, Definitions, EnumDefinition, FieldType, MessageDefinition, Primitive, StructDefinition, ValueType},
+    RuneFileDescription, RuneParserError
+};
+
+/// Dialect declared by every generated schema document
+const SCHEMA_DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// Appended to the include path of a rune file to get the name of its schema document
+const FILE_EXTENSION: &str = ".schema.json";
+
+/// Minimal JSON document tree. Objects keep their entries in insertion order, so generated documents follow the order of the rune file
+enum JsonValue {
+    Bool(bool),
+    /// Number already formatted as JSON, so that 64 and 128 bit limits are written exactly
+    Number(String),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>)
+}
+
+impl JsonValue {
+    fn number(value: impl Display) -> JsonValue {
+        JsonValue::Number(value.to_string())
+    }
+
+    fn string(value: &str) -> JsonValue {
+        JsonValue::String(String::from(value))
+    }
+
+    fn object(entries: Vec<(&str, JsonValue)>) -> JsonValue {
+        JsonValue::Object(entries.into_iter().map(|(key, value)| (String::from(key), value)).collect())
+    }
+
+    fn is_scalar(&self) -> bool {
+        !matches!(self, JsonValue::Array(_) | JsonValue::Object(_))
+    }
+
+    fn write(&self, output: &mut String, indentation: usize) {
+        match self {
+            JsonValue::Bool(value) => output.push_str(if *value { "true" } else { "false" }),
+            JsonValue::Number(value) => output.push_str(value),
+            JsonValue::String(value) => write_json_string(output, value),
+
+            JsonValue::Array(elements) if elements.is_empty() => output.push_str("[]"),
+
+            // Keep lists of plain values, such as enum values or required properties, on one line
+            JsonValue::Array(elements) if elements.iter().all(JsonValue::is_scalar) => {
+                output.push('[');
+                for (position, element) in elements.iter().enumerate() {
+                    if position != 0 {
+                        output.push_str(", ");
+                    }
+                    element.write(output, indentation);
+                }
+                output.push(']');
+            },
+
+            JsonValue::Array(elements) => {
+                output.push_str("[\n");
+                for (position, element) in elements.iter().enumerate() {
+                    push_indentation(output, indentation + 1);
+                    element.write(output, indentation + 1);
+                    output.push_str(if position + 1 == elements.len() { "\n" } else { ",\n" });
+                }
+                push_indentation(output, indentation);
+                output.push(']');
+            },
+
+            JsonValue::Object(entries) if entries.is_empty() => output.push_str("{}"),
+
+            JsonValue::Object(entries) => {
+                output.push_str("{\n");
+                for (position, (key, value)) in entries.iter().enumerate() {
+                    push_indentation(output, indentation + 1);
+                    write_json_string(output, key);
+                    output.push_str(": ");
+                    value.write(output, indentation + 1);
+                    output.push_str(if position + 1 == entries.len() { "\n" } else { ",\n" });
+                }
+                push_indentation(output, indentation);
+                output.push('}');
+            }
+        }
+    }
+}
+
+fn push_indentation(output: &mut String, indentation: usize) {
+    for _ in 0..indentation {
+        output.push_str("  ");
+    }
+}
+
+fn write_json_string(output: &mut String, value: &str) {
+    output.push('"');
+    for character in value.chars() {
+        match character {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            character if character.is_control() => output.push_str(&format!("\\u{0:04x}", character as u32)),
+            character => output.push(character)
+        }
+    }
+    output.push('"');
+}
+
+/// Write a JSON Schema document for every file, at the include path of the file under the output path, such as `output/sub/file.schema.json`
+pub fn output_json_schema(file_descriptions: &[RuneFileDescription], output_path: &Path) -> Result<(), RuneParserError> {
+    info!("Writing JSON schemas to {0:?}", output_path);
+
+    for file in file_descriptions {
+        let schema: String = json_schema_string(file, file_descriptions)?;
+        let schema_path = output_path.join(format!("{0}{1}", file.include_path(), FILE_EXTENSION));
+
+        if let Some(directory) = schema_path.parent() {
+            if let Err(error) = std::fs::create_dir_all(directory) {
+                error!("Could not create output directory {0:?}. Got error {1}", directory, error);
+                return Err(RuneParserError::FileSystemError);
+            }
+        }
+
+        if let Err(error) = std::fs::write(&schema_path, schema) {
+            error!("Could not write JSON schema to {0:?}. Got error {1}", schema_path, error);
+            return Err(RuneParserError::FileSystemError);
+        }
+    }
+
+    Ok(())
+}
+
+/// Generate the JSON Schema document of a single file. Every bitfield, enum, struct, and message becomes an entry under `$defs`, and user
+/// defined types are referenced with `$ref`, pointing to the schema document of the declaring file when it is not the file itself
+pub fn json_schema_string(file: &RuneFileDescription, file_descriptions: &[RuneFileDescription]) -> Result<String, RuneParserError> {
+    let schema_generator = SchemaGenerator { file, file_descriptions };
+
+    let mut definitions: Vec<(String, JsonValue)> = Vec::new();
+
+    for bitfield_definition in &file.definitions.bitfields {
+        definitions.push((bitfield_definition.name.clone(), bitfield_schema(bitfield_definition)));
+    }
+
+    for enum_definition in &file.definitions.enums {
+        definitions.push((enum_definition.name.clone(), enum_schema(enum_definition)));
+    }
+
+    for struct_definition in &file.definitions.structs {
+        definitions.push((struct_definition.name.clone(), schema_generator.struct_schema(struct_definition)?));
+    }
+
+    for message_definition in &file.definitions.messages {
+        definitions.push((message_definition.name.clone(), schema_generator.message_schema(message_definition)?));
+    }
+
+    let document = JsonValue::object(vec![
+        ("$schema", JsonValue::string(SCHEMA_DIALECT)),
+        ("$id", JsonValue::String(format!("{0}{1}", file.include_path(), FILE_EXTENSION))),
+        ("title", JsonValue::string(&file.name)),
+        ("$defs", JsonValue::Object(definitions))
+    ]);
+
+    let mut output = String::new();
+    document.write(&mut output, 0);
+    output.push('\n');
+
+    Ok(output)
+}
+
+struct SchemaGenerator<'a> {
+    /// File the schema document is generated for
+    file:              &'a RuneFileDescription,
+    /// All parsed files, used to find the declaring file of types used across files
+    file_descriptions: &'a [RuneFileDescription]
+}
+
+impl SchemaGenerator<'_> {
+    fn struct_schema(&self, struct_definition: &StructDefinition) -> Result<JsonValue, RuneParserError> {
+        let mut schema: Vec<(&str, JsonValue)> = vec![("type", JsonValue::string("object"))];
+
+        if let Some(comment) = &struct_definition.comment {
+            schema.push(("description", JsonValue::string(comment.trim())));
+        }
+
+        // The layout of external structs is not known to Rune
+        if struct_definition.is_external() {
+            return Ok(JsonValue::object(schema));
+        }
+
+        let mut properties: Vec<(String, JsonValue)> = Vec::with_capacity(struct_definition.members.len());
+        for member in &struct_definition.members {
+            properties.push((member.identifier.clone(), self.value_schema(&member.data_type, &member.comment, &member.default)?));
+        }
+
+        // Every struct member is always present
+        let required: Vec<JsonValue> = struct_definition.members.iter().map(|member| JsonValue::string(&member.identifier)).collect();
+
+        schema.push(("properties", JsonValue::Object(properties)));
+        schema.push(("required", JsonValue::Array(required)));
+        schema.push(("additionalProperties", JsonValue::Bool(false)));
+
+        Ok(JsonValue::object(schema))
+    }
+
+    fn message_schema(&self, message_definition: &MessageDefinition) -> Result<JsonValue, RuneParserError> {
+        let mut schema: Vec<(&str, JsonValue)> = vec![("type", JsonValue::string("object"))];
+
+        if let Some(comment) = &message_definition.comment {
+            schema.push(("description", JsonValue::string(comment.trim())));
+        }
+
+        // Message fields are optional, so none of them are required
+        let mut properties: Vec<(String, JsonValue)> = Vec::with_capacity(message_definition.fields.len());
+        for field in &message_definition.fields {
+            let value_type: &ValueType = match &field.data_type {
+                FieldType::Empty => {
+                    error!("Message field definition was empty! This should not happen!");
+                    return Err(RuneParserError::EmptyMessageField);
+                },
+                FieldType::Value(value_type) => value_type,
+                FieldType::Bytes(max_size) => {
+                    properties.push((field.identifier.clone(), bytes_schema(*max_size, &field.comment)));
+                    continue;
+                }
+            };
+
+            properties.push((field.identifier.clone(), self.value_schema(value_type, &field.comment, &field.default)?));
+        }
+
+        schema.push(("properties", JsonValue::Object(properties)));
+        schema.push(("additionalProperties", JsonValue::Bool(false)));
+
+        Ok(JsonValue::object(schema))
+    }
+
+    fn value_schema(&self, value_type: &ValueType, comment: &Option<String>, default: &Option<DefineValue>) -> Result<JsonValue, RuneParserError> {
+        let schema: JsonValue = match value_type {
+            ValueType::Primitive(primitive) => primitive_schema(primitive),
+            ValueType::Array(array) => self.array_schema(array)?,
+            ValueType::UserDefined(name, _) => self.reference(name)?
+        };
+
+        let mut entries: Vec<(String, JsonValue)> = match schema {
+            JsonValue::Object(entries) => entries,
+            schema => return Ok(schema)
+        };
+
+        // Attach the comment and default value of the member or field, if any
+        if let Some(comment) = comment {
+            entries.push((String::from("description"), JsonValue::string(comment.trim())));
+        }
+        match default {
+            Some(DefineValue::NumericLiteral(literal)) => entries.push((String::from("default"), literal_value(literal))),
+            // Char arrays are arrays of single character strings
+            Some(DefineValue::StringLiteral(text)) => {
+                let characters: Vec<JsonValue> = text.chars().map(|character| JsonValue::String(character.to_string())).collect();
+                entries.push((String::from("default"), JsonValue::Array(characters)));
+            },
+            _ => ()
+        }
+
+        Ok(JsonValue::Object(entries))
+    }
+
+    fn array_schema(&self, array: &Array) -> Result<JsonValue, RuneParserError> {
+        let items: JsonValue = match &array.data_type {
+            ArrayType::Primitive(primitive) => primitive_schema(primitive),
+            ArrayType::UserDefined(name, _) => self.reference(name)?,
+            ArrayType::Array(inner_array) => self.array_schema(inner_array)?
+        };
+
+        Ok(JsonValue::object(vec![
+            ("type", JsonValue::string("array")),
+            ("items", items),
+            ("maxItems", JsonValue::number(array.element_count.value()?))
+        ]))
+    }
+
+    /// Reference to the definition of a user defined type, either in this document or in the document of the file declaring it
+    fn reference(&self, name: &str) -> Result<JsonValue, RuneParserError> {
+        if declares_type(&self.file.definitions, name) {
+            return Ok(JsonValue::object(vec![("$ref", JsonValue::String(format!("#/$defs/{0}", name)))]));
+        }
+
+        let declaring_file: &RuneFileDescription = match self.file_descriptions.iter().find(|file| declares_type(&file.definitions, name)) {
+            None => {
+                error!("Found no user definition for identifier '{0}' while generating the JSON schema of {1}!", name, self.file.include_path());
+                return Err(RuneParserError::UndefinedIdentifier);
+            },
+            Some(file) => file
+        };
+
+        // Schema documents mirror the directory structure of the rune files, so step out of the directory of this file first
+        let parent_directories: String = "../".repeat(self.file.relative_path.matches('/').count());
+
+        Ok(JsonValue::object(vec![(
+            "$ref",
+            JsonValue::String(format!("{0}{1}{2}#/$defs/{3}", parent_directories, declaring_file.include_path(), FILE_EXTENSION, name))
+        )]))
+    }
+}
+
+fn declares_type(definitions: &Definitions, name: &str) -> bool {
+    definitions.bitfields.iter().any(|definition| definition.name == name)
+        || definitions.enums.iter().any(|definition| definition.name == name)
+        || definitions.structs.iter().any(|definition| definition.name == name)
+        || definitions.messages.iter().any(|definition| definition.name == name)
+}
+
+fn bitfield_schema(bitfield_definition: &BitfieldDefinition) -> JsonValue {
+    let mut schema: Vec<(&str, JsonValue)> = vec![("type", JsonValue::string("object"))];
+
+    if let Some(comment) = &bitfield_definition.comment {
+        schema.push(("description", JsonValue::string(comment.trim())));
+    }
+
+    let mut properties: Vec<(String, JsonValue)> = Vec::with_capacity(bitfield_definition.members.len());
+    for member in &bitfield_definition.members {
+        let (minimum, maximum): (i128, i128) = match member.size {
+            BitSize::Signed(size) => (-(1 << (size - 1)), (1 << (size - 1)) - 1),
+            BitSize::Unsigned(size) => (0, (1 << size) - 1)
+        };
+
+        properties.push((member.identifier.clone(), integer_schema(minimum, maximum)));
+    }
+
+    let required: Vec<JsonValue> = bitfield_definition.members.iter().map(|member| JsonValue::string(&member.identifier)).collect();
+
+    schema.push(("properties", JsonValue::Object(properties)));
+    schema.push(("required", JsonValue::Array(required)));
+    schema.push(("additionalProperties", JsonValue::Bool(false)));
+
+    JsonValue::object(schema)
+}
+
+fn enum_schema(enum_definition: &EnumDefinition) -> JsonValue {
+    let mut schema: Vec<(&str, JsonValue)> = Vec::with_capacity(2);
+
+    if let Some(comment) = &enum_definition.comment {
+        schema.push(("description", JsonValue::string(comment.trim())));
+    }
+
+    schema.push(("enum", JsonValue::Array(enum_definition.members.iter().map(|member| literal_value(&member.value)).collect())));
+
+    JsonValue::object(schema)
+}
+
+fn literal_value(literal: &NumericLiteral) -> JsonValue {
+    match literal {
+        NumericLiteral::AsciiChar(character) => JsonValue::String(character.to_string()),
+        NumericLiteral::Boolean(value) => JsonValue::Bool(*value),
+        NumericLiteral::PositiveInteger(value, _) => JsonValue::number(value),
+        NumericLiteral::NegativeInteger(value, _) => JsonValue::number(value),
+        NumericLiteral::Float(value) => JsonValue::Number(format!("{0:?}", value))
+    }
+}
+
+fn primitive_schema(primitive: &Primitive) -> JsonValue {
+    match primitive {
+        Primitive::Bool => JsonValue::object(vec![("type", JsonValue::string("boolean"))]),
+        Primitive::Char => JsonValue::object(vec![
+            ("type", JsonValue::string("string")),
+            ("minLength", JsonValue::number(1)),
+            ("maxLength", JsonValue::number(1))
+        ]),
+        Primitive::I8 => integer_schema(*Primitive::I8_RANGE.start(), *Primitive::I8_RANGE.end()),
+        Primitive::U8 => integer_schema(*Primitive::U8_RANGE.start(), *Primitive::U8_RANGE.end()),
+        Primitive::F16 => JsonValue::object(vec![
+            ("type", JsonValue::string("number")),
+            ("minimum", JsonValue::Number(format!("{0:?}", *Primitive::F16_RANGE.start()))),
+            ("maximum", JsonValue::Number(format!("{0:?}", *Primitive::F16_RANGE.end())))
+        ]),
+        Primitive::I16 => integer_schema(*Primitive::I16_RANGE.start(), *Primitive::I16_RANGE.end()),
+        Primitive::U16 => integer_schema(*Primitive::U16_RANGE.start(), *Primitive::U16_RANGE.end()),
+        Primitive::F32 => JsonValue::object(vec![
+            ("type", JsonValue::string("number")),
+            ("minimum", JsonValue::Number(format!("{0:?}", *Primitive::F32_RANGE.start()))),
+            ("maximum", JsonValue::Number(format!("{0:?}", *Primitive::F32_RANGE.end())))
+        ]),
+        Primitive::I32 => integer_schema(*Primitive::I32_RANGE.start(), *Primitive::I32_RANGE.end()),
+        Primitive::U32 => integer_schema(*Primitive::U32_RANGE.start(), *Primitive::U32_RANGE.end()),
+        Primitive::F64 => JsonValue::object(vec![("type", JsonValue::string("number"))]),
+        Primitive::I64 => integer_schema(i64::MIN, i64::MAX),
+        Primitive::U64 => integer_schema(u64::MIN, u64::MAX),
+        Primitive::I128 => integer_schema(*Primitive::I128_RANGE.start(), *Primitive::I128_RANGE.end()),
+        Primitive::U128 => integer_schema(*Primitive::U128_RANGE.start(), *Primitive::U128_RANGE.end())
+    }
+}
+
+/// Bytes are arrays of u8, with as many items as the bound of the field if it has one
+fn bytes_schema(max_size: Option<u64>, comment: &Option<String>) -> JsonValue {
+    let mut schema: Vec<(&str, JsonValue)> = vec![("type", JsonValue::string("array")), ("items", primitive_schema(&Primitive::U8))];
+
+    if let Some(max_size) = max_size {
+        schema.push(("maxItems", JsonValue::number(max_size)));
+    }
+    if let Some(comment) = comment {
+        schema.push(("description", JsonValue::string(comment.trim())));
+    }
+
+    JsonValue::object(schema)
+}
+
+/// The ends of the primitive ranges are the largest values of each type, so both limits are inclusive
+fn integer_schema(minimum: impl Display, maximum: impl Display) -> JsonValue {
+    JsonValue::object(vec![
+        ("type", JsonValue::string("integer")),
+        ("minimum", JsonValue::number(minimum)),
+        ("maximum", JsonValue::number(maximum))
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{languages::json_schema::json_schema_string, test_utils::parse_files};
+
+    #[test]
+    fn small_schema_matches_golden() {
+        let source = "enum Mode: u8 {\n    Off = 0;\n    On = 1;\n}\n\nstruct Point {\n    x: i16 = 0;\n    y: i16 = 1;\n}\n\n\
+                      message Move {\n    mode: Mode = 1;\n    to: Point = 2;\n    path?: [Point; 4] = 3;\n}\n";
+        let files = parse_files(&[("move.rune", source)]).unwrap();
+
+        assert_eq!(json_schema_string(&files[0], &files).unwrap(), GOLDEN);
+    }
+
+    const GOLDEN: &str = r##"{
+  "$schema": "https://json-schema.org/draft/2020-12/schema",
+  "$id": "move.schema.json",
+  "title": "move",
+  "$defs": {
+    "Mode": {
+      "enum": [0, 1]
+    },
+    "Point": {
+      "type": "object",
+      "properties": {
+        "x": {
+          "type": "integer",
+          "minimum": -32768,
+          "maximum": 32767
+        },
+        "y": {
+          "type": "integer",
+          "minimum": -32768,
+          "maximum": 32767
+        }
+      },
+      "required": ["x", "y"],
+      "additionalProperties": false
+    },
+    "Move": {
+      "type": "object",
+      "properties": {
+        "mode": {
+          "$ref": "#/$defs/Mode"
+        },
+        "to": {
+          "$ref": "#/$defs/Point"
+        },
+        "path": {
+          "type": "array",
+          "items": {
+            "$ref": "#/$defs/Point"
+          },
+          "maxItems": 4
+        }
+      },
+      "additionalProperties": false
+    }
+  }
+}
+"##;
+}
@@ -0,0 +1,7 @@
+pub mod graphviz;
+pub mod json_schema;
+pub mod typescript;
+
+pub use graphviz::{output_type_graph, type_graph_string};
+pub use json_schema::{json_schema_string, output_json_schema};
+pub use typescript::{output_typescript, typescript_string};
@@ -0,0 +1,338 @@
+use std::path::Path;
+
+use crate::{
+    output::*,
+    scanner::NumericLiteral,
+    types::{
+        Array, ArrayType, BitSize, BitfieldDefinition, DeclarationRef, EnumDefinition, FieldType, MessageDefinition, Primitive, StandaloneCommentDefinition, StructDefinition,
+        TypeRef, ValueType
+    },
+    RuneFileDescription, RuneParserError
+};
+
+/// Appended to the include path of a rune file to get the name of its TypeScript file
+const FILE_EXTENSION: &str = ".ts";
+
+/// Indentation of members inside declarations
+const INDENTATION: &str = "    ";
+
+/// Write a TypeScript file for every file, at the include path of the file under the output path, such as `output/sub/file.ts`
+pub fn output_typescript(file_descriptions: &[RuneFileDescription], output_path: &Path) -> Result<(), RuneParserError> {
+    info!("Writing TypeScript definitions to {0:?}", output_path);
+
+    for file in file_descriptions {
+        let definitions: String = typescript_string(file, file_descriptions)?;
+        let typescript_path = output_path.join(format!("{0}{1}", file.include_path(), FILE_EXTENSION));
+
+        if let Some(directory) = typescript_path.parent() {
+            if let Err(error) = std::fs::create_dir_all(directory) {
+                error!("Could not create output directory {0:?}. Got error {1}", directory, error);
+                return Err(RuneParserError::FileSystemError);
+            }
+        }
+
+        if let Err(error) = std::fs::write(&typescript_path, definitions) {
+            error!("Could not write TypeScript definitions to {0:?}. Got error {1}", typescript_path, error);
+            return Err(RuneParserError::FileSystemError);
+        }
+    }
+
+    Ok(())
+}
+
+/// Generate the TypeScript definitions of a single file. Enums become numeric enums, while bitfields, structs, and messages become
+/// interfaces. User defined types declared in other files are imported from the TypeScript file generated for the declaring file
+pub fn typescript_string(file: &RuneFileDescription, file_descriptions: &[RuneFileDescription]) -> Result<String, RuneParserError> {
+    let mut output = format!("// Generated from {0}.rune\n", file.include_path());
+
+    // Imports
+    // ————————
+
+    let imports: Vec<(String, Vec<String>)> = collect_imports(file, file_descriptions)?;
+
+    if !imports.is_empty() {
+        output.push('\n');
+    }
+
+    // TypeScript files mirror the directory structure of the rune files, so step out of the directory of this file first
+    let parent_directories: String = match file.relative_path.matches('/').count() {
+        0 => String::from("./"),
+        depth => "../".repeat(depth)
+    };
+
+    for (include_path, names) in &imports {
+        output.push_str(&format!("import type {{ {0} }} from \"{1}{2}\";\n", names.iter().map(|name| type_name(name)).collect::<Vec<String>>().join(", "), parent_directories, include_path));
+    }
+
+    // Declarations
+    // —————————————
+
+    // Keep the order the declarations are written in, so the output reads like the rune file. Defines have no TypeScript counterpart
+    for declaration in file.definitions.declarations() {
+        match declaration {
+            DeclarationRef::Comment(comment_definition) => {
+                output.push('\n');
+                output_standalone_comment(&mut output, comment_definition);
+            },
+            DeclarationRef::Define(_) => (),
+            DeclarationRef::Type(TypeRef::Bitfield(bitfield_definition)) => {
+                output.push('\n');
+                output_bitfield(&mut output, bitfield_definition);
+            },
+            DeclarationRef::Type(TypeRef::Enum(enum_definition)) => {
+                output.push('\n');
+                output_enum(&mut output, enum_definition);
+            },
+            DeclarationRef::Type(TypeRef::Message(message_definition)) => {
+                output.push('\n');
+                output_message(&mut output, message_definition)?;
+            },
+            DeclarationRef::Type(TypeRef::Struct(struct_definition)) => {
+                output.push('\n');
+                output_struct(&mut output, struct_definition);
+            }
+        }
+    }
+
+    Ok(output)
+}
+
+/// Group the user defined types used by a file but declared in another by their declaring file, in order of first use
+fn collect_imports(file: &RuneFileDescription, file_descriptions: &[RuneFileDescription]) -> Result<Vec<(String, Vec<String>)>, RuneParserError> {
+    let mut used_types: Vec<&str> = Vec::new();
+
+    let value_types = file
+        .definitions
+        .structs
+        .iter()
+        .flat_map(|struct_definition| struct_definition.members.iter().map(|member| &member.data_type))
+        .chain(file.definitions.messages.iter().flat_map(|message_definition| message_definition.fields.iter().filter_map(|field| field.data_type.value_type())));
+
+    for value_type in value_types {
+        let name: &str = match value_type {
+            ValueType::UserDefined(name, _) => name,
+            ValueType::Array(array) => match array.innermost_type() {
+                ArrayType::UserDefined(name, _) => name,
+                _ => continue
+            },
+            ValueType::Primitive(_) => continue
+        };
+
+        if !used_types.contains(&name) && file.definitions.find_type(name).is_none() {
+            used_types.push(name);
+        }
+    }
+
+    let mut imports: Vec<(String, Vec<String>)> = Vec::new();
+
+    for name in used_types {
+        let declaring_file: &RuneFileDescription = match file_descriptions.iter().find(|description| description.definitions.find_type(name).is_some()) {
+            None => {
+                error!("Found no user definition for identifier '{0}' while generating the TypeScript definitions of {1}!", name, file.include_path());
+                return Err(RuneParserError::UndefinedIdentifier);
+            },
+            Some(description) => description
+        };
+
+        let include_path: String = declaring_file.include_path();
+
+        match imports.iter_mut().find(|(path, _)| *path == include_path) {
+            Some((_, names)) => names.push(String::from(name)),
+            None => imports.push((include_path, Vec::from([String::from(name)])))
+        }
+    }
+
+    Ok(imports)
+}
+
+/// Write a comment as a documentation comment, indented to the declaration or member it describes
+fn output_comment(output: &mut String, comment: &Option<String>, indentation: &str) {
+    let comment: &str = match comment {
+        Some(comment) if !comment.trim().is_empty() => comment.trim(),
+        _ => return
+    };
+
+    match comment.lines().count() {
+        1 => output.push_str(&format!("{0}/** {1} */\n", indentation, comment)),
+        _ => {
+            output.push_str(&format!("{0}/**\n", indentation));
+            for line in comment.lines() {
+                output.push_str(&format!("{0} * {1}\n", indentation, line.trim()).replace(" * \n", " *\n"));
+            }
+            output.push_str(&format!("{0} */\n", indentation));
+        }
+    }
+}
+
+/// Write a comment standing on its own as line comments
+fn output_standalone_comment(output: &mut String, comment_definition: &StandaloneCommentDefinition) {
+    for line in comment_definition.comment.trim().lines() {
+        output.push_str(&format!("// {0}\n", line.trim()).replace("// \n", "//\n"));
+    }
+}
+
+fn output_bitfield(output: &mut String, bitfield_definition: &BitfieldDefinition) {
+    output_comment(output, &bitfield_definition.comment, "");
+    output.push_str(&format!("export interface {0} {{\n", type_name(&bitfield_definition.name)));
+
+    for member in &bitfield_definition.members {
+        // Like primitives, members wider than 32 bits are written as bigint
+        let member_type: &str = match member.size {
+            BitSize::Signed(size) | BitSize::Unsigned(size) if size > 32 => "bigint",
+            _ => "number"
+        };
+
+        output_comment(output, &member.comment, INDENTATION);
+        output.push_str(&format!("{0}{1}: {2};\n", INDENTATION, member.identifier, member_type));
+    }
+
+    output.push_str("}\n");
+}
+
+fn output_enum(output: &mut String, enum_definition: &EnumDefinition) {
+    output_comment(output, &enum_definition.comment, "");
+    output.push_str(&format!("export enum {0} {{\n", type_name(&enum_definition.name)));
+
+    for member in &enum_definition.members {
+        output_comment(output, &member.comment, INDENTATION);
+        output.push_str(&format!("{0}{1} = {2},\n", INDENTATION, member.identifier, enum_value(&member.value)));
+    }
+
+    output.push_str("}\n");
+}
+
+fn output_struct(output: &mut String, struct_definition: &StructDefinition) {
+    output_comment(output, &struct_definition.comment, "");
+
+    // The layout of external structs is not known to Rune
+    if struct_definition.is_external() {
+        output.push_str(&format!("export type {0} = unknown;\n", type_name(&struct_definition.name)));
+        return;
+    }
+
+    output.push_str(&format!("export interface {0} {{\n", type_name(&struct_definition.name)));
+
+    // Every struct member is always present
+    for member in &struct_definition.members {
+        output_comment(output, &member.comment, INDENTATION);
+        output.push_str(&format!("{0}{1}: {2};\n", INDENTATION, member.identifier, value_type(&member.data_type)));
+    }
+
+    output.push_str("}\n");
+}
+
+fn output_message(output: &mut String, message_definition: &MessageDefinition) -> Result<(), RuneParserError> {
+    output_comment(output, &message_definition.comment, "");
+    output.push_str(&format!("export interface {0} {{\n", type_name(&message_definition.name)));
+
+    // Message fields are optional
+    for field in &message_definition.fields {
+        let field_type: String = match &field.data_type {
+            FieldType::Empty => {
+                error!("Message field definition was empty! This should not happen!");
+                return Err(RuneParserError::EmptyMessageField);
+            },
+            FieldType::Value(field_type) => value_type(field_type),
+            FieldType::Bytes(_) => String::from("Uint8Array")
+        };
+
+        output_comment(output, &field.comment, INDENTATION);
+        output.push_str(&format!("{0}{1}?: {2};\n", INDENTATION, field.identifier, field_type));
+    }
+
+    output.push_str("}\n");
+
+    Ok(())
+}
+
+fn value_type(value_type: &ValueType) -> String {
+    match value_type {
+        ValueType::Primitive(primitive) => String::from(primitive_type(primitive)),
+        ValueType::Array(array) => array_type(array),
+        ValueType::UserDefined(name, _) => type_name(name)
+    }
+}
+
+/// TypeScript name of a definition. Namespaces are joined to the name with underscores, so `Foo::Header` becomes `Foo_Header`
+fn type_name(name: &str) -> String {
+    name.replace("::", "_")
+}
+
+fn array_type(array: &Array) -> String {
+    match &array.data_type {
+        ArrayType::Primitive(primitive) => format!("{0}[]", primitive_type(primitive)),
+        ArrayType::UserDefined(name, _) => format!("{0}[]", type_name(name)),
+        ArrayType::Array(inner_array) => format!("{0}[]", array_type(inner_array))
+    }
+}
+
+/// Integers wider than 32 bits cannot be held exactly by a JavaScript number, so they are written as bigint
+fn primitive_type(primitive: &Primitive) -> &'static str {
+    match primitive {
+        Primitive::Bool => "boolean",
+        Primitive::Char => "string",
+        Primitive::I8 | Primitive::U8 | Primitive::F16 | Primitive::I16 | Primitive::U16 | Primitive::I32 | Primitive::U32 | Primitive::F32 | Primitive::F64 => "number",
+        Primitive::I64 | Primitive::U64 | Primitive::I128 | Primitive::U128 => "bigint"
+    }
+}
+
+/// TypeScript enums hold numbers, so characters are written as their code and booleans as 0 and 1
+fn enum_value(literal: &NumericLiteral) -> String {
+    match literal {
+        NumericLiteral::AsciiChar(character) => (*character as u32).to_string(),
+        NumericLiteral::Boolean(value) => (*value as u8).to_string(),
+        NumericLiteral::PositiveInteger(value, _) => value.to_string(),
+        NumericLiteral::NegativeInteger(value, _) => value.to_string(),
+        NumericLiteral::Float(value) => format!("{0:?}", value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{languages::typescript::typescript_string, test_utils::parse_files};
+
+    #[test]
+    fn message_importing_an_enum_matches_golden() {
+        let files = parse_files(&[
+            ("common/mode.rune", "// Operating mode\nenum Mode: u8 {\n    Off = 0;\n    On = 1;\n}\n"),
+            ("move.rune", "include \"common/mode\";\n\nmessage Move {\n    mode: Mode = 1;\n    speeds?: [u16; 4] = 2;\n    enabled: bool = 3;\n}\n")
+        ])
+        .unwrap();
+        let mode = files.iter().find(|file| file.name == "mode").unwrap();
+        let message = files.iter().find(|file| file.name == "move").unwrap();
+
+        assert_eq!(typescript_string(mode, &files).unwrap(), MODE_GOLDEN);
+        assert_eq!(typescript_string(message, &files).unwrap(), MOVE_GOLDEN);
+    }
+
+    #[test]
+    fn declarations_are_written_in_source_order() {
+        let files = parse_files(&[("order.rune", "struct First {\n    a: u8 = 0;\n}\n\nenum Second: u8 {\n    A = 0;\n}\n\nstruct Third {\n    b: u8 = 0;\n}\n")]).unwrap();
+        let output = typescript_string(&files[0], &files).unwrap();
+
+        let first = output.find("interface First").unwrap();
+        let second = output.find("enum Second").unwrap();
+        let third = output.find("interface Third").unwrap();
+        assert!(first < second && second < third, "Declarations out of order:\n{0}", output);
+    }
+
+    const MODE_GOLDEN: &str = r#"// Generated from common/mode.rune
+
+/** Operating mode */
+export enum Mode {
+    Off = 0,
+    On = 1,
+}
+"#;
+
+    const MOVE_GOLDEN: &str = r#"// Generated from move.rune
+
+import type { Mode } from "./common/mode";
+
+export interface Move {
+    mode?: Mode;
+    speeds?: number[];
+    enabled?: boolean;
+}
+"#;
+}
@@ -1,31 +1,52 @@
 #[macro_use]
 pub mod output;
+pub mod languages;
 pub mod parser;
 pub mod post_processing;
 pub mod scanner;
 pub mod types;
 pub mod validation;
 
-use std::{fs::ReadDir, path::Path};
+#[cfg(test)]
+mod test_utils;
+
+use std::{
+    fmt::{Display, Formatter},
+    fs::ReadDir,
+    path::Path
+};
 
 use output::{enable_silent, is_silent};
-use parser::parse_tokens;
-use post_processing::{link_user_definitions, parse_define_statements, parse_extensions};
-use scanner::Scanner;
+use parser::{parse_token_stream, ParsingError};
+use post_processing::{
+    check_include_cycles, deduplicate_includes, link_user_definitions, parse_define_statements, parse_extensions, resolve_includes, sort_definitions, DEFAULT_MAX_NESTING_DEPTH
+};
+use scanner::{Scanner, ScanningError};
 pub use scanner::{NumeralSystem, NumericLiteral};
-use types::Definitions;
+use types::{Definitions, Primitive, TypeRef};
 pub use types::{ArraySize, ArrayType};
-use validation::validate_parsed_files;
+use validation::{find_unused_definitions, validate_array_lengths, validate_parsed_files};
 
 const ALLOCATION_SIZE: usize = 0x40;
 
+/// Name of the file listing directories to skip when searching an input path for rune files
+pub const IGNORE_FILE_NAME: &str = ".runeignore";
+
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RuneFileDescription {
     pub relative_path: String,
     pub name:          String,
     pub definitions:   Definitions
 }
 
+impl RuneFileDescription {
+    /// Path of the file relative to its input path without the .rune extension, as it would be written in an include statement
+    pub fn include_path(&self) -> String {
+        format!("{0}{1}", self.relative_path, self.name)
+    }
+}
+
 #[derive(Debug)]
 pub enum RuneParserError {
     InvalidInputPath,
@@ -36,6 +57,7 @@ pub enum RuneParserError {
     NameCollision,
     ValueCollision,
     InvalidTotalBitfieldSize,
+    InvalidBitfieldMemberSize,
     InvalidEncodedSize,
     InvalidArrayType,
     InvalidArraySize,
@@ -43,11 +65,133 @@ pub enum RuneParserError {
     UseOfReservedIndex,
     ExtensionMismatch,
     UndefinedIdentifier,
+    UnavailableDefinition,
+    UndefinedInclude,
     MultipleDefinitions,
     MultipleRedefinitions,
     InvalidNumericValue,
     EmptyMessageField,
-    InvalidTypeUse
+    InvalidTypeUse,
+    CircularDefinition,
+    NestingTooDeep,
+    CyclicInclude,
+    RecursiveType,
+    ReservedIdentifier,
+    InvalidToken,
+    InvalidSyntax
+}
+
+/// Non-fatal issue found while parsing rune files. Warnings are printed as they are found, and are also collected for tools embedding the
+/// parser through [`parser_rune_files_with_warnings`]
+#[derive(Debug, Clone)]
+pub enum Warning {
+    /// No rune files were found in any of the input paths
+    NoRuneFiles,
+    /// A path could not be converted to a string, so it was skipped
+    InvalidPathString(String),
+    /// A rune file could not be read, and was skipped
+    UnreadableFile { path: String, error: String },
+    /// A rune file had scanning errors, and was skipped
+    ScanningFailed { path: String, errors: Vec<ScanningError> },
+    /// A rune file could not be parsed, and was skipped
+    ParsingFailed { path: String, error: ParsingError },
+    /// The name or relative path of a rune file could not be determined, so it was skipped
+    UnresolvableFilePath(String),
+    /// A redefinition without a matching define, which is ignored
+    OrphanRedefinition(String),
+    /// A file including the same file more than once. Only the first include is kept
+    DuplicateInclude { file: String, include: String },
+    /// A file including itself, which is dropped
+    SelfInclude(String),
+    /// A bitfield, enum, struct, or define that no message uses, directly or through other definitions
+    UnusedDefinition { file: String, name: String },
+    /// A message field marked obsolete after a version older than the version the file declares
+    ObsoleteField { file: String, message: String, field: String, index: u64, obsolete_after: u64, version: u64 },
+    /// A value or index reserved more than once by the same definition, possibly written in another numeral system, such as 255 and 0xFF
+    RepeatedReservation { file: String, definition: String, value: String, earlier: String },
+    /// A bitfield or message deprecating an unusually long run of consecutive indexes, which may have been meant as a reservation
+    LongDeprecatedRange { file: String, definition: String, first: u64, last: u64 },
+    /// A deprecated index right next to an index in use, which may mean the deprecated range covers the wrong indexes
+    DeprecatedNextToUsed { file: String, definition: String, deprecated: u64, used: u64 },
+    /// A signed bitfield member one bit wide, which can only hold 0 and -1
+    SingleBitSigned { file: String, bitfield: String, member: String }
+}
+
+impl Display for Warning {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Warning::NoRuneFiles => write!(formatter, "Could not parse any rune files from paths. Returning empty list"),
+            Warning::InvalidPathString(path) => write!(formatter, "Could not get string from file path {0}", path),
+            Warning::UnreadableFile { path, error } => write!(formatter, "Could not read file {0}, so it was skipped. Got error {1}", path, error),
+            Warning::ScanningFailed { path, errors } => write!(formatter, "File {0} had {1} scanning error(s), so it was skipped", path, errors.len()),
+            Warning::ParsingFailed { path, .. } => write!(formatter, "File {0} could not be parsed, so it was skipped", path),
+            Warning::UnresolvableFilePath(path) => write!(formatter, "Could not get the name and relative path of file {0}, so it was skipped", path),
+            Warning::OrphanRedefinition(name) => {
+                write!(formatter, "Define statement for redefinition {0} not found, so it will thus be ignored and do nothing.", name)
+            },
+            Warning::DuplicateInclude { file, include } => write!(formatter, "{0}.rune includes {1} more than once. Only the first include is kept", file, include),
+            Warning::SelfInclude(file) => write!(formatter, "{0}.rune includes itself. The include is dropped", file),
+            Warning::UnusedDefinition { file, name } => write!(formatter, "{0} in {1}.rune is not used by any message", name, file),
+            Warning::ObsoleteField {
+                file,
+                message,
+                field,
+                index,
+                obsolete_after,
+                version
+            } => write!(
+                formatter,
+                "Field {0} of message {1} is obsolete after version {2}, but {3}.rune declares version {4}. Consider removing it and reserving index {5}",
+                field, message, obsolete_after, file, version, index
+            ),
+            Warning::RepeatedReservation { file, definition, value, earlier } => match value == earlier {
+                true => write!(formatter, "{0} in {1}.rune reserves {2} more than once", definition, file, value),
+                false => write!(formatter, "{0} in {1}.rune reserves {2}, which is the same value as the already reserved {3}", definition, file, value, earlier)
+            },
+            Warning::LongDeprecatedRange { file, definition, first, last } => write!(
+                formatter,
+                "{0} in {1}.rune deprecates {2} consecutive indexes ({3} to {4}). Check that the range is intended, or use reserve for indexes that never held a field",
+                definition,
+                file,
+                last - first + 1,
+                first,
+                last
+            ),
+            Warning::DeprecatedNextToUsed { file, definition, deprecated, used } => write!(
+                formatter,
+                "{0} in {1}.rune deprecates index {2}, right next to index {3} which is in use. Check that the deprecated range does not cover the wrong indexes",
+                definition, file, deprecated, used
+            ),
+            Warning::SingleBitSigned { file, bitfield, member } => write!(
+                formatter,
+                "Field {0} of bitfield {1} in {2}.rune is a signed single bit, which can only hold 0 and -1. Consider declaring it as u1",
+                member, bitfield, file
+            )
+        }
+    }
+}
+
+/// A user defined type, along with the file it was declared in
+#[derive(Debug, Clone, Copy)]
+pub struct ResolvedType<'a> {
+    /// File declaring the type
+    pub file:       &'a RuneFileDescription,
+    /// Linked definition of the type
+    pub definition: TypeRef<'a>
+}
+
+/// Options of [`parser_rune_files_with_options`]
+#[derive(Debug, Clone, Default)]
+pub struct ParserOptions {
+    /// Append the members and fields of extensions to the definitions they extend
+    pub append_extensions: bool,
+    /// Print nothing while parsing
+    pub silent:            bool,
+    /// Warn about every bitfield, enum, struct, and define that no message uses, once all definitions are linked
+    pub warn_unused:       bool,
+    /// Unsigned type generated code counts array elements with, such as `u8` for a one byte size type. Arrays with more elements than it
+    /// can count are rejected
+    pub array_size_type:   Option<Primitive>
 }
 
 struct RuneFile {
@@ -56,8 +200,30 @@ struct RuneFile {
 }
 
 pub fn parser_rune_files(input_paths: &[&Path], append_extensions: bool, silent: bool) -> Result<Vec<RuneFileDescription>, RuneParserError> {
+    parser_rune_files_with_warnings(input_paths, append_extensions, silent, &mut Vec::new())
+}
+
+/// Same as [`parser_rune_files`], also collecting every non-fatal issue found into the given list of warnings
+pub fn parser_rune_files_with_warnings(
+    input_paths: &[&Path],
+    append_extensions: bool,
+    silent: bool,
+    warnings: &mut Vec<Warning>
+) -> Result<Vec<RuneFileDescription>, RuneParserError> {
+    let options = ParserOptions {
+        append_extensions,
+        silent,
+        warn_unused: false,
+        array_size_type: None
+    };
+
+    parser_rune_files_with_options(input_paths, &options, warnings)
+}
+
+/// Same as [`parser_rune_files_with_warnings`], with opt-in analyses such as reporting unused definitions
+pub fn parser_rune_files_with_options(input_paths: &[&Path], options: &ParserOptions, warnings: &mut Vec<Warning>) -> Result<Vec<RuneFileDescription>, RuneParserError> {
     // Enable silent mode if requested by user
-    if silent {
+    if options.silent {
         enable_silent();
     }
 
@@ -80,6 +246,7 @@ pub fn parser_rune_files(input_paths: &[&Path], append_extensions: bool, silent:
         let input_path_string: String = match input_path.to_str() {
             None => {
                 warning!("Could not get string from file path {0:?}", input_path);
+                warnings.push(Warning::InvalidPathString(input_path.to_string_lossy().into_owned()));
                 continue;
             },
             Some(string) => String::from(string)
@@ -87,7 +254,8 @@ pub fn parser_rune_files(input_paths: &[&Path], append_extensions: bool, silent:
 
         // Get rune files in path
         info!("Searching input path {0:?}", input_path);
-        let file_list: Vec<String> = get_rune_files(input_path)?;
+        let ignore_list: Vec<String> = read_ignore_list(input_path);
+        let file_list: Vec<String> = get_rune_files(input_path, input_path, &ignore_list)?;
 
         // Add found files to list
         for rune_file in file_list {
@@ -99,7 +267,8 @@ pub fn parser_rune_files(input_paths: &[&Path], append_extensions: bool, silent:
     }
 
     if rune_file_list.is_empty() {
-        warning!("Could not parse any rune files from paths. Returning empty list");
+        warning!("{0}", Warning::NoRuneFiles);
+        warnings.push(Warning::NoRuneFiles);
         return Ok(Vec::new());
     }
 
@@ -112,7 +281,7 @@ pub fn parser_rune_files(input_paths: &[&Path], append_extensions: bool, silent:
     // Process rune files
     // ———————————————————
 
-    let mut definitions_list: Vec<RuneFileDescription> = Vec::with_capacity(ALLOCATION_SIZE);
+    let mut definitions_list: Vec<RuneFileDescription> = Vec::with_capacity(rune_file_list.len());
 
     for rune_file in rune_file_list {
         let file_path: &Path = Path::new(&rune_file.name);
@@ -120,24 +289,29 @@ pub fn parser_rune_files(input_paths: &[&Path], append_extensions: bool, silent:
         let file = match std::fs::read_to_string(file_path) {
             Err(error) => {
                 error!("Error in reading file to string. Got error {0}", error);
+                warnings.push(Warning::UnreadableFile {
+                    path:  rune_file.name.clone(),
+                    error: error.to_string()
+                });
                 continue;
             },
             Ok(path) => path
         };
 
-        // Scan file for tokens
-        let tokens = match Scanner::new(file.chars()).scan_all() {
-            Err(error) => {
-                error!("Error while scanning file {0}: {1:#?}", rune_file.name, error);
+        // Parse tokens as they are scanned
+        let definitions: Definitions = match parse_token_stream(Scanner::from_str(&file).into_iter()) {
+            // Scan the whole file again to report every scanning error, not only the one that stopped parsing
+            Err(ParsingError::ScanningError(_)) => {
+                let (_, errors) = Scanner::from_str(&file).scan_all_collecting();
+                for error in &errors {
+                    error!("Error while scanning file {0}: {1}", rune_file.name, error.render_diagnostic(&file));
+                }
+                warnings.push(Warning::ScanningFailed { path: rune_file.name, errors });
                 continue;
             },
-            Ok(tokens) => tokens
-        };
-
-        // Parse all scanned tokens
-        let definitions: Definitions = match parse_tokens(&mut tokens.into_iter().peekable()) {
             Err(error) => {
-                error!("Error while parsing file {0}: {1:#?}", rune_file.name, error);
+                error!("Error while parsing file {0}: {1}", rune_file.name, error.render_diagnostic(&file));
+                warnings.push(Warning::ParsingFailed { path: rune_file.name, error });
                 continue;
             },
             Ok(tokens) => tokens
@@ -147,11 +321,13 @@ pub fn parser_rune_files(input_paths: &[&Path], append_extensions: bool, silent:
         let full_file_name: String = match file_path.file_name() {
             None => {
                 error!("File given at path {0:?} had no name!", file_path);
+                warnings.push(Warning::UnresolvableFilePath(rune_file.name.clone()));
                 continue;
             },
             Some(os_string) => match os_string.to_str() {
                 None => {
                     error!("Could not parse OS string: \"{0:?}\"", os_string);
+                    warnings.push(Warning::UnresolvableFilePath(rune_file.name.clone()));
                     continue;
                 },
                 Some(string) => string.to_string()
@@ -161,6 +337,7 @@ pub fn parser_rune_files(input_paths: &[&Path], append_extensions: bool, silent:
         let name: String = match full_file_name.strip_suffix(".rune") {
             None => {
                 error!("Could not strip '.rune' suffix from file name!");
+                warnings.push(Warning::UnresolvableFilePath(rune_file.name.clone()));
                 continue;
             },
             Some(stripped_name) => stripped_name.to_string()
@@ -170,16 +347,19 @@ pub fn parser_rune_files(input_paths: &[&Path], append_extensions: bool, silent:
         let relative_path = match rune_file.name.strip_prefix(&rune_file.source_path) {
             None => {
                 warning!("Could not get relative path from input path string \"{0}\"", rune_file.source_path);
+                warnings.push(Warning::UnresolvableFilePath(rune_file.name.clone()));
                 continue;
             },
             Some(string) => match string.strip_prefix("/") {
                 None => {
                     warning!("Could not get relative path from input path string \"{0}\"", rune_file.source_path);
+                    warnings.push(Warning::UnresolvableFilePath(rune_file.name.clone()));
                     continue;
                 },
                 Some(stripped_path) => match stripped_path.strip_suffix(&full_file_name) {
                     None => {
                         warning!("Could not get relative path from input path string \"{0}\"", rune_file.source_path);
+                        warnings.push(Warning::UnresolvableFilePath(rune_file.name.clone()));
                         continue;
                     },
                     Some(relative_path) => relative_path.to_string()
@@ -193,19 +373,44 @@ pub fn parser_rune_files(input_paths: &[&Path], append_extensions: bool, silent:
     // Post-processing
     // ————————————————
 
+    // Point every include at the parsed file it refers to
+    resolve_includes(&mut definitions_list)?;
+
     // Parse and resolve define statements
-    parse_define_statements(&mut definitions_list)?;
+    parse_define_statements(&mut definitions_list, warnings)?;
+
+    // Drop repeated includes and files including themselves directly
+    deduplicate_includes(&mut definitions_list, warnings);
+
+    // Check that no file ends up including itself
+    check_include_cycles(&definitions_list)?;
+
+    // Order definitions so that no type is used before it is declared
+    sort_definitions(&mut definitions_list)?;
 
     // Parse and link user defined data types across files
-    link_user_definitions(&mut definitions_list)?;
+    link_user_definitions(&mut definitions_list, DEFAULT_MAX_NESTING_DEPTH)?;
 
     // Parse extensions
-    parse_extensions(&mut definitions_list, append_extensions)?;
+    parse_extensions(&mut definitions_list, options.append_extensions)?;
+
+    // Extended files include the files extending them, which must not close an include cycle either
+    check_include_cycles(&definitions_list)?;
 
     // Validate parsed data structures
     // ————————————————————————————————
 
-    validate_parsed_files(&definitions_list)?;
+    validate_parsed_files(&definitions_list, warnings)?;
+
+    // Check that every array fits the size type generated code counts its elements with
+    if let Some(size_type) = &options.array_size_type {
+        validate_array_lengths(&definitions_list, size_type)?;
+    }
+
+    // Report definitions no message uses, which needs every definition to be linked
+    if options.warn_unused {
+        find_unused_definitions(&definitions_list, warnings);
+    }
 
     // Return list
     // ————————————
@@ -213,7 +418,85 @@ pub fn parser_rune_files(input_paths: &[&Path], append_extensions: bool, silent:
     Ok(definitions_list)
 }
 
-fn get_rune_files(folder_path: &Path) -> Result<Vec<String>, RuneParserError> {
+/// Parse a single rune file from a string, without touching the filesystem. Includes are not resolved, and user defined types are left
+/// unlinked, as there are no other files to process them against. The definitions are still validated, so array sizes must be given by
+/// literals or by defines declared in the string
+pub fn parse_str(source: &str, file_name: &str) -> Result<Definitions, RuneParserError> {
+    let definitions: Definitions = match parse_token_stream(Scanner::from_str(source).into_iter()) {
+        Err(error @ ParsingError::ScanningError(_)) => {
+            error!("Error while scanning {0}: {1}", file_name, error.render_diagnostic(source));
+            return Err(RuneParserError::InvalidToken);
+        },
+        Err(error @ ParsingError::InvalidTypeUse(_)) => {
+            error!("Error while parsing {0}: {1}", file_name, error.render_diagnostic(source));
+            return Err(RuneParserError::InvalidTypeUse);
+        },
+        Err(error) => {
+            error!("Error while parsing {0}: {1}", file_name, error.render_diagnostic(source));
+            return Err(RuneParserError::InvalidSyntax);
+        },
+        Ok(definitions) => definitions
+    };
+
+    let mut file_list: Vec<RuneFileDescription> = Vec::from([RuneFileDescription {
+        relative_path: String::new(),
+        name: String::from(file_name.strip_suffix(".rune").unwrap_or(file_name)),
+        definitions
+    }]);
+
+    // Array sizes can only use the defines declared in the string itself
+    parse_define_statements(&mut file_list, &mut Vec::new())?;
+
+    validate_parsed_files(&file_list, &mut Vec::new())?;
+
+    Ok(file_list.into_iter().next().unwrap().definitions)
+}
+
+/// Find the declaration of a bitfield, enum, message, or struct by name in a list of parsed files
+pub fn resolve_type<'a>(files: &'a [RuneFileDescription], name: &str) -> Option<ResolvedType<'a>> {
+    files.iter().find_map(|file| file.definitions.find_type(name).map(|definition| ResolvedType { file, definition }))
+}
+
+/// Read the directories listed in the .runeignore file of an input path, if there is one. Each line holds either a directory name, which
+/// is skipped wherever it appears, or a path relative to the input path. Empty lines and lines starting with # are ignored
+fn read_ignore_list(input_path: &Path) -> Vec<String> {
+    let ignore_file_path = input_path.join(IGNORE_FILE_NAME);
+
+    if !ignore_file_path.is_file() {
+        return Vec::new();
+    }
+
+    match std::fs::read_to_string(&ignore_file_path) {
+        Err(error) => {
+            warning!("Could not read {0:?}. Got error {1}", ignore_file_path, error);
+            Vec::new()
+        },
+        Ok(contents) => contents
+            .lines()
+            .map(|line| line.trim().trim_matches('/'))
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(String::from)
+            .collect()
+    }
+}
+
+/// Check whether a subdirectory should be skipped when searching for rune files. Hidden directories, such as .git, are always skipped
+fn is_ignored_directory(input_path: &Path, directory_path: &Path, ignore_list: &[String]) -> bool {
+    let directory_name: String = directory_path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+
+    if directory_name.starts_with('.') {
+        return true;
+    }
+
+    let relative_path: String = match directory_path.strip_prefix(input_path) {
+        Ok(path) => path.to_string_lossy().into_owned(),
+        Err(_) => directory_name.clone()
+    };
+
+    ignore_list.iter().any(|entry| *entry == directory_name || *entry == relative_path)
+}
+
+fn get_rune_files(input_path: &Path, folder_path: &Path, ignore_list: &[String]) -> Result<Vec<String>, RuneParserError> {
     let mut rune_file_list: Vec<String> = Vec::with_capacity(ALLOCATION_SIZE);
 
     let folder_iterator: ReadDir = match folder_path.read_dir() {
@@ -259,6 +542,11 @@ fn get_rune_files(folder_path: &Path) -> Result<Vec<String>, RuneParserError> {
             // Subfolder
             // ——————————
 
+            if is_ignored_directory(input_path, &directory_entry.path(), ignore_list) {
+                info!("    Skipping ignored subdirectory named {0:?}", directory_entry.file_name());
+                continue;
+            }
+
             info!("    Found subdirectory named {0:?}", directory_entry.file_name());
 
             let subfolder_string: String = format!(
@@ -282,7 +570,7 @@ fn get_rune_files(folder_path: &Path) -> Result<Vec<String>, RuneParserError> {
             let subfolder_path: &Path = Path::new(&subfolder_string);
 
             // Recursively call function to parse files in subfolder
-            let mut subfolder_list: Vec<String> = get_rune_files(subfolder_path)?;
+            let mut subfolder_list: Vec<String> = get_rune_files(input_path, subfolder_path, ignore_list)?;
 
             rune_file_list.append(&mut subfolder_list);
         } else if entry_type.is_file() {
@@ -317,3 +605,85 @@ fn get_rune_files(folder_path: &Path) -> Result<Vec<String>, RuneParserError> {
 
     Ok(rune_file_list)
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        parse_str, resolve_type,
+        test_utils::parse_files,
+        types::{BitSize, Primitive, TypeRef},
+        RuneParserError, IGNORE_FILE_NAME
+    };
+
+    #[test]
+    fn resolve_type_finds_the_declaring_file() {
+        let files = parse_files(&[
+            ("common/types.rune", "struct Point {\n    x: i32 = 0;\n    y: i32 = 1;\n}\n"),
+            ("shapes.rune", "include \"common/types\";\n\nmessage Line {\n    start: Point = 1;\n    end: Point = 2;\n}\n")
+        ])
+        .unwrap();
+
+        let resolved = resolve_type(&files, "Point").unwrap();
+        assert_eq!(resolved.file.include_path(), "common/types");
+        assert!(matches!(resolved.definition, TypeRef::Struct(definition) if definition.members.len() == 2));
+
+        let resolved = resolve_type(&files, "Line").unwrap();
+        assert_eq!(resolved.file.include_path(), "shapes");
+        assert!(matches!(resolved.definition, TypeRef::Message(_)));
+    }
+
+    #[test]
+    fn resolve_type_returns_nothing_for_unknown_names() {
+        let files = parse_files(&[("shapes.rune", "struct Point {\n    x: i32 = 0;\n}\n")]).unwrap();
+        assert!(resolve_type(&files, "Circle").is_none());
+    }
+
+    #[test]
+    fn hidden_directories_are_skipped() {
+        let files = parse_files(&[("types.rune", "struct Point {\n    x: i32 = 0;\n}\n"), (".hidden/vendored.rune", "struct Vendored {\n    x: i32 = 0;\n}\n")]).unwrap();
+
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].name, "types");
+    }
+
+    #[test]
+    fn ignored_directories_are_skipped() {
+        let files = parse_files(&[
+            (IGNORE_FILE_NAME, "build\nsub/generated\n"),
+            ("types.rune", "struct Point {\n    x: i32 = 0;\n}\n"),
+            ("build/output.rune", "struct Output {\n    x: i32 = 0;\n}\n"),
+            ("sub/generated/copy.rune", "struct Copy {\n    x: i32 = 0;\n}\n"),
+            ("sub/kept.rune", "struct Kept {\n    x: i32 = 0;\n}\n")
+        ])
+        .unwrap();
+
+        let mut paths: Vec<String> = files.iter().map(|file| file.include_path()).collect();
+        paths.sort();
+        assert_eq!(paths, vec!["sub/kept", "types"]);
+    }
+
+    #[test]
+    fn parse_str_parses_an_enum() {
+        let definitions = parse_str("enum Mode: u8 {\n    Off = 0;\n    On = 1;\n}\n", "mode.rune").unwrap();
+        let mode = definitions.find_enum("Mode").unwrap();
+
+        assert_eq!(mode.backing_type, Primitive::U8);
+        assert_eq!(mode.members.len(), 2);
+    }
+
+    #[test]
+    fn parse_str_parses_a_bitfield() {
+        let definitions = parse_str("bitfield Flags: u8 {\n    ready: u1 = 0;\n    level: i3 = 1;\n}\n", "flags.rune").unwrap();
+        let flags = definitions.find_bitfield("Flags").unwrap();
+
+        assert!(matches!(flags.members[1].size, BitSize::Signed(3)));
+    }
+
+    #[test]
+    fn parse_str_validates_the_file() {
+        let result = parse_str("enum Mode: u8 {\n    Off = 0;\n    On = 0;\n}\n", "mode.rune");
+        assert!(matches!(result, Err(RuneParserError::ValueCollision)));
+
+        assert!(matches!(parse_str("enum Mode: u8 {\n", "mode.rune"), Err(RuneParserError::InvalidSyntax)));
+    }
+}
@@ -1,13 +1,13 @@
-static mut SILENT: bool = false;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static SILENT: AtomicBool = AtomicBool::new(false);
 
 pub fn enable_silent() {
-    unsafe {
-        SILENT = true;
-    }
+    SILENT.store(true, Ordering::Relaxed);
 }
 
 pub fn is_silent() -> bool {
-    unsafe { SILENT }
+    SILENT.load(Ordering::Relaxed)
 }
 
 // Reset  - "\u{001B}[0m"
@@ -50,3 +50,32 @@ macro_rules! error {
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{test_utils::parse_files, RuneParserError};
+
+    #[test]
+    fn concurrent_parses_return_their_own_results() {
+        // Every other thread parses a file with a duplicate member, so each thread must get back the result of its own file
+        let threads: Vec<_> = (0..8)
+            .map(|index| {
+                let member: &str = if index % 2 == 0 { "y" } else { "x" };
+                std::thread::spawn(move || parse_files(&[("point.rune", &format!("struct Point{0} {{\n    x: i32 = 0;\n    {1}: i32 = 1;\n}}\n", index, member))]))
+            })
+            .collect();
+
+        for (index, thread) in threads.into_iter().enumerate() {
+            match thread.join().unwrap() {
+                Ok(files) => {
+                    assert_eq!(index % 2, 0);
+                    assert!(files[0].definitions.find_struct(&format!("Point{0}", index)).is_some());
+                },
+                Err(error) => {
+                    assert_eq!(index % 2, 1);
+                    assert!(matches!(error, RuneParserError::IdentifierCollision));
+                }
+            }
+        }
+    }
+}
@@ -1,7 +1,13 @@
-use std::iter::{Iterator, Peekable};
+use std::{
+    iter::{Iterator, Peekable},
+    path::Path
+};
 
 use crate::{output::*, scanner::*, types::*};
 
+/// Number of definitions of each kind to allocate room for when parsing a file
+const DEFINITIONS_CAPACITY: usize = 0x10;
+
 type ItemType = Spanned<Token>;
 
 #[allow(unused)]
@@ -13,8 +19,17 @@ pub enum ParsingError {
     InvalidBitIndex(NumericLiteral),
     InvalidIndex(NumericLiteral),
     InvalidBitfieldBackingType(Primitive),
+    InvalidBitfieldSize(Spanned<String>),
     InvalidEnumBackingType(Primitive),
     InvalidEnumValue(NumericLiteral),
+    InvalidFilePath(Spanned<String>),
+    InvalidAnnotation(Spanned<String>),
+    MultipleVersionDeclarations(ItemType),
+    ReservedKeyword(ItemType),
+    InvalidDefaultValue(ItemType),
+    InvalidArraySize(ItemType),
+    InvalidTypeUse(ItemType),
+    MissingExternalSize(Spanned<String>),
     LogicError
 }
 
@@ -118,9 +133,45 @@ impl From<ScanningError> for ParsingError {
     }
 }
 
+impl ParsingError {
+    /// Describes the error along with the offending line of the source it was parsed from, when the error carries a position
+    pub fn render_diagnostic(&self, source: &str) -> String {
+        let span = match self {
+            ParsingError::ScanningError(error) => return error.render_diagnostic(source),
+            ParsingError::UnexpectedToken(token)
+            | ParsingError::MultipleVersionDeclarations(token)
+            | ParsingError::ReservedKeyword(token)
+            | ParsingError::InvalidDefaultValue(token)
+            | ParsingError::InvalidArraySize(token)
+            | ParsingError::InvalidTypeUse(token) => token.just_span(),
+            ParsingError::InvalidBitfieldSize(text)
+            | ParsingError::InvalidFilePath(text)
+            | ParsingError::InvalidAnnotation(text)
+            | ParsingError::MissingExternalSize(text) => text.just_span(),
+            _ => return format!("{0:?}", self)
+        };
+
+        let description = match self {
+            ParsingError::UnexpectedToken(token) => format!("Unexpected token {0:?}", token.item),
+            ParsingError::MultipleVersionDeclarations(_) => String::from("Multiple version declarations"),
+            ParsingError::ReservedKeyword(token) => format!("Reserved word '{0}' used as a name", token.keyword().unwrap_or_default()),
+            ParsingError::InvalidDefaultValue(token) => format!("Invalid default value {0:?}", token.item),
+            ParsingError::InvalidArraySize(token) => format!("Invalid array size {0:?}", token.item),
+            ParsingError::InvalidTypeUse(token) => format!("Type {0:?} cannot be used here", token.item),
+            ParsingError::InvalidBitfieldSize(text) => format!("Invalid bitfield size \"{0}\"", text.item),
+            ParsingError::InvalidFilePath(text) => format!("Invalid file path \"{0}\"", text.item),
+            ParsingError::InvalidAnnotation(text) => format!("Invalid annotation @{0}", text.item),
+            ParsingError::MissingExternalSize(name) => format!("External struct {0} is missing a non-zero @size", name.item),
+            _ => unreachable!("Only errors carrying a position are rendered with a source line")
+        };
+
+        format!("{0}\n{1}", description, render_source_span(source, span.from, span.to))
+    }
+}
+
 type ParsingResult<T> = Result<T, ParsingError>;
 
-pub trait TokenSource: std::clone::Clone {
+pub trait TokenSource {
     fn next(&mut self) -> Option<ItemType>;
     fn peek(&mut self) -> Option<&ItemType>;
 
@@ -130,14 +181,28 @@ pub trait TokenSource: std::clone::Clone {
         match &token.item {
             // Parse identifier, with char first, then convert rest to decimal number
             Token::Identifier(string) => {
-                let signed: bool = match string.chars().nth(0).unwrap() {
-                    'u' | 'U' => false,
-                    'i' | 'I' => true,
-                    _ => return Err(ParsingError::UnexpectedToken(token))
+                let invalid_size = || {
+                    error!("Invalid bitfield member size '{0}'. Bitfield members must be declared as uN or iN, such as u3 or i12", string);
+                    ParsingError::InvalidBitfieldSize(Spanned::new(string.to_string(), token.from, token.to))
+                };
+
+                let (signed, digits): (bool, &str) = match (string.strip_prefix('u'), string.strip_prefix('i')) {
+                    (Some(digits), _) => (false, digits),
+                    (_, Some(digits)) => (true, digits),
+                    _ => return Err(invalid_size())
                 };
 
-                let size: u64 = match string[1..].parse() {
-                    Err(_) => return Err(ParsingError::UnexpectedToken(token)),
+                // Only plain decimal digits are accepted, as parse() would also take a sign
+                if digits.is_empty() || !digits.chars().all(|character| character.is_ascii_digit()) {
+                    return Err(invalid_size());
+                }
+
+                let size: u64 = match digits.parse() {
+                    Err(_) => return Err(invalid_size()),
+                    Ok(0) => {
+                        error!("Bitfield member size '{0}' must be at least one bit", string);
+                        return Err(ParsingError::InvalidBitfieldSize(Spanned::new(string.to_string(), token.from, token.to)));
+                    },
                     Ok(number) => number
                 };
 
@@ -148,7 +213,10 @@ pub trait TokenSource: std::clone::Clone {
 
                 Ok(Spanned::new(bitfield_size, token.from, token.to))
             },
-            _ => Err(ParsingError::UnexpectedToken(token))
+            _ => {
+                error!("Expected a bitfield member size such as u3 or i12");
+                Err(ParsingError::UnexpectedToken(token))
+            }
         }
     }
 
@@ -183,9 +251,31 @@ pub trait TokenSource: std::clone::Clone {
     fn expect_identifier(&mut self) -> ParsingResult<Spanned<String>> {
         let token = self.expect_next()?;
         match token.item {
-            Token::Identifier(string) => Ok(Spanned::new(string, token.from, token.to)),
-            _ => Err(ParsingError::UnexpectedToken(token))
+            Token::Identifier(string) => Ok(Spanned::new(string.to_string(), token.from, token.to)),
+            _ => match token.item.keyword() {
+                None => Err(ParsingError::UnexpectedToken(token)),
+                Some(keyword) => {
+                    error!(
+                        "Expected a name on line {0}, but found the reserved word '{1}'. Reserved words cannot be used as names",
+                        token.from.line, keyword
+                    );
+                    Err(ParsingError::ReservedKeyword(token))
+                }
+            }
+        }
+    }
+
+    /// Parse the rest of a name qualified by namespaces, such as `Foo::Header`, after its first identifier. Unqualified names are returned
+    /// as they are
+    fn expect_qualified_name(&mut self, first: Spanned<String>) -> ParsingResult<Spanned<String>> {
+        let mut name = first;
+
+        while self.maybe_expect(Token::DoubleColon).is_some() {
+            let part = self.expect_identifier()?;
+            name = Spanned::new(format!("{0}::{1}", name.item, part.item), name.from, part.to);
         }
+
+        Ok(name)
     }
 
     fn expect_next(&mut self) -> ParsingResult<ItemType> {
@@ -198,7 +288,7 @@ pub trait TokenSource: std::clone::Clone {
     fn expect_reserve(&mut self) -> ParsingResult<Spanned<Token>> {
         let token = self.expect_next()?;
         match token.item {
-            Token::Reserve => Ok(token),
+            Token::Reserve | Token::Deprecate => Ok(token),
             _ => Err(ParsingError::UnexpectedToken(token))
         }
     }
@@ -230,22 +320,9 @@ pub trait TokenSource: std::clone::Clone {
         let token = self.expect_next()?;
 
         match &token.item {
-            Token::Identifier(string) => match string.as_str() {
-                "bool" => Ok(Spanned::new(Primitive::Bool, token.from, token.to)),
-                "char" => Ok(Spanned::new(Primitive::Char, token.from, token.to)),
-                "i8" => Ok(Spanned::new(Primitive::I8, token.from, token.to)),
-                "u8" => Ok(Spanned::new(Primitive::U8, token.from, token.to)),
-                "i16" => Ok(Spanned::new(Primitive::I16, token.from, token.to)),
-                "u16" => Ok(Spanned::new(Primitive::U16, token.from, token.to)),
-                "f32" => Ok(Spanned::new(Primitive::F32, token.from, token.to)),
-                "i32" => Ok(Spanned::new(Primitive::I32, token.from, token.to)),
-                "u32" => Ok(Spanned::new(Primitive::U32, token.from, token.to)),
-                "f64" => Ok(Spanned::new(Primitive::F64, token.from, token.to)),
-                "i64" => Ok(Spanned::new(Primitive::I64, token.from, token.to)),
-                "u64" => Ok(Spanned::new(Primitive::U64, token.from, token.to)),
-                "i128" => Ok(Spanned::new(Primitive::I128, token.from, token.to)),
-                "u128" => Ok(Spanned::new(Primitive::U128, token.from, token.to)),
-                _ => Err(ParsingError::UnexpectedToken(token))
+            Token::Identifier(string) => match Primitive::from_str(string) {
+                Some(primitive) => Ok(Spanned::new(primitive, token.from, token.to)),
+                None => Err(ParsingError::UnexpectedToken(token))
             },
             _ => Err(ParsingError::UnexpectedToken(token))
         }
@@ -253,139 +330,118 @@ pub trait TokenSource: std::clone::Clone {
 
     fn expect_array_type(&mut self) -> ParsingResult<Spanned<ArrayType>> {
         let token = self.expect_next()?;
+        check_not_bytes(&token)?;
 
         match &token.item {
-            Token::Identifier(string) => match string.as_str() {
-                "bool" => Ok(Spanned::new(ArrayType::Primitive(Primitive::Bool), token.from, token.to)),
-                "char" => Ok(Spanned::new(ArrayType::Primitive(Primitive::Char), token.from, token.to)),
-                "i8" => Ok(Spanned::new(ArrayType::Primitive(Primitive::I8), token.from, token.to)),
-                "u8" => Ok(Spanned::new(ArrayType::Primitive(Primitive::U8), token.from, token.to)),
-                "i16" => Ok(Spanned::new(ArrayType::Primitive(Primitive::I16), token.from, token.to)),
-                "u16" => Ok(Spanned::new(ArrayType::Primitive(Primitive::U16), token.from, token.to)),
-                "f32" => Ok(Spanned::new(ArrayType::Primitive(Primitive::F32), token.from, token.to)),
-                "i32" => Ok(Spanned::new(ArrayType::Primitive(Primitive::I32), token.from, token.to)),
-                "u32" => Ok(Spanned::new(ArrayType::Primitive(Primitive::U32), token.from, token.to)),
-                "f64" => Ok(Spanned::new(ArrayType::Primitive(Primitive::F64), token.from, token.to)),
-                "i64" => Ok(Spanned::new(ArrayType::Primitive(Primitive::I64), token.from, token.to)),
-                "u64" => Ok(Spanned::new(ArrayType::Primitive(Primitive::U64), token.from, token.to)),
-                "i128" => Ok(Spanned::new(ArrayType::Primitive(Primitive::I128), token.from, token.to)),
-                "u128" => Ok(Spanned::new(ArrayType::Primitive(Primitive::U128), token.from, token.to)),
-                _ => Ok(Spanned::new(ArrayType::UserDefined(string.clone(), UserDefinitionLink::NoLink), token.from, token.to))
+            Token::Identifier(string) => match Primitive::from_str(string) {
+                Some(primitive) => Ok(Spanned::new(ArrayType::Primitive(primitive), token.from, token.to)),
+                None => {
+                    let name = self.expect_qualified_name(Spanned::new(string.to_string(), token.from, token.to))?;
+                    Ok(name.map(|name| ArrayType::UserDefined(name.clone(), UserDefinitionLink::NoLink)))
+                }
+            },
+            Token::LeftBracket => {
+                let array = self.expect_array(token)?;
+                Ok(Spanned::new(ArrayType::Array(Box::new(array.item)), array.from, array.to))
             },
             _ => Err(ParsingError::UnexpectedToken(token))
         }
     }
 
-    fn expect_field_type(&mut self) -> ParsingResult<Spanned<FieldType>> {
+    fn expect_value_type(&mut self) -> ParsingResult<Spanned<ValueType>> {
         let token = self.expect_next()?;
+        check_not_bytes(&token)?;
         match token.item {
-            Token::Identifier(string) => Ok(Spanned::new(
-                match string.as_str() {
-                    "bool" => FieldType::Primitive(Primitive::Bool),
-                    "char" => FieldType::Primitive(Primitive::Char),
-                    "i8" => FieldType::Primitive(Primitive::I8),
-                    "u8" => FieldType::Primitive(Primitive::U8),
-                    "i16" => FieldType::Primitive(Primitive::I16),
-                    "u16" => FieldType::Primitive(Primitive::U16),
-                    "f32" => FieldType::Primitive(Primitive::F32),
-                    "i32" => FieldType::Primitive(Primitive::I32),
-                    "u32" => FieldType::Primitive(Primitive::U32),
-                    "f64" => FieldType::Primitive(Primitive::F64),
-                    "i64" => FieldType::Primitive(Primitive::I64),
-                    "u64" => FieldType::Primitive(Primitive::U64),
-                    "i128" => FieldType::Primitive(Primitive::I128),
-                    "u128" => FieldType::Primitive(Primitive::U128),
-                    _ => FieldType::UserDefined(string, UserDefinitionLink::NoLink)
-                },
-                token.from,
-                token.to
-            )),
+            Token::Identifier(string) => match Primitive::from_str(&string) {
+                Some(primitive) => Ok(Spanned::new(ValueType::Primitive(primitive), token.from, token.to)),
+                None => {
+                    let name = self.expect_qualified_name(Spanned::new(string.to_string(), token.from, token.to))?;
+                    Ok(name.map(|name| ValueType::UserDefined(name.clone(), UserDefinitionLink::NoLink)))
+                }
+            },
 
             Token::LeftBracket => {
-                let data_type = self.expect_array_type()?.item;
-                self.expect_token(Token::SemiColon)?;
-                let count_token = self.expect_next()?;
-
-                let element_count = match &count_token.item {
-                    // Simple integer or hex value will generate a simple number
-                    Token::NumericLiteral(NumericLiteral::PositiveInteger(value, numeral_system)) => ArraySize::Integer(*value, *numeral_system),
-
-                    // String will generate a user definition, which will be populated with a value in post processing
-                    Token::Identifier(string) => ArraySize::UserDefinition(DefineDefinition {
-                        name:         string.clone(),
-                        value:        DefineValue::NoValue,
-                        comment:      None,
-                        redefinition: None
-                    }),
-                    _ => return Err(ParsingError::UnexpectedToken(count_token))
-                };
-
-                let right_bracket = self.expect_token(Token::RightBracket)?;
-
-                Ok(Spanned::new(FieldType::Array(Array { data_type, element_count }), token.from, right_bracket.to))
+                let array = self.expect_array(token)?;
+                Ok(Spanned::new(ValueType::Array(array.item), array.from, array.to))
             },
 
             _ => Err(ParsingError::UnexpectedToken(token))
         }
     }
 
-    fn expect_member_type(&mut self) -> ParsingResult<Spanned<MemberType>> {
-        let token = self.expect_next()?;
-        match token.item {
-            Token::Identifier(string) => Ok(Spanned::new(
-                match string.as_str() {
-                    "bool" => MemberType::Primitive(Primitive::Bool),
-                    "char" => MemberType::Primitive(Primitive::Char),
-                    "i8" => MemberType::Primitive(Primitive::I8),
-                    "u8" => MemberType::Primitive(Primitive::U8),
-                    "i16" => MemberType::Primitive(Primitive::I16),
-                    "u16" => MemberType::Primitive(Primitive::U16),
-                    "f32" => MemberType::Primitive(Primitive::F32),
-                    "i32" => MemberType::Primitive(Primitive::I32),
-                    "u32" => MemberType::Primitive(Primitive::U32),
-                    "f64" => MemberType::Primitive(Primitive::F64),
-                    "i64" => MemberType::Primitive(Primitive::I64),
-                    "u64" => MemberType::Primitive(Primitive::U64),
-                    "i128" => MemberType::Primitive(Primitive::I128),
-                    "u128" => MemberType::Primitive(Primitive::U128),
-                    _ => MemberType::UserDefined(string, UserDefinitionLink::NoLink)
-                },
-                token.from,
-                token.to
-            )),
+    /// Parse the data type of a message field, which is either a value type or bytes, such as `bytes` or `bytes(256)`
+    fn expect_field_type(&mut self) -> ParsingResult<Spanned<FieldType>> {
+        match self.peek() {
+            Some(Spanned { item: Token::Identifier(string), .. }) if &**string == "bytes" => (),
+            _ => {
+                let value_type = self.expect_value_type()?;
+                return Ok(Spanned::new(FieldType::Value(value_type.item), value_type.from, value_type.to));
+            }
+        }
 
-            Token::LeftBracket => {
-                let data_type = self.expect_array_type()?.item;
-                self.expect_token(Token::SemiColon)?;
-                let count_token = self.expect_next()?;
-
-                let element_count = match &count_token.item {
-                    // Simple integer or hex value will generate a simple number
-                    Token::NumericLiteral(NumericLiteral::PositiveInteger(value, numeral_system)) => ArraySize::Integer(*value, *numeral_system),
-
-                    // String will generate a user definition, which will be populated with a value in post processing
-                    Token::Identifier(string) => ArraySize::UserDefinition(DefineDefinition {
-                        name:         string.clone(),
-                        value:        DefineValue::NoValue,
-                        comment:      None,
-                        redefinition: None
-                    }),
-                    _ => return Err(ParsingError::UnexpectedToken(count_token))
-                };
+        let bytes_token = self.expect_next()?;
 
-                let right_bracket = self.expect_token(Token::RightBracket)?;
+        if self.maybe_expect(Token::LeftParenthesis).is_none() {
+            return Ok(Spanned::new(FieldType::Bytes(None), bytes_token.from, bytes_token.to));
+        }
 
-                Ok(Spanned::new(MemberType::Array(Array { data_type, element_count }), token.from, right_bracket.to))
+        let size_token = self.expect_next()?;
+        let max_size: u64 = match &size_token.item {
+            Token::NumericLiteral(NumericLiteral::PositiveInteger(value, _)) if *value > 0 => *value,
+            Token::NumericLiteral(literal) => {
+                error!("Bytes size {0} is not a positive integer! The largest number of bytes must be a positive integer", literal);
+                return Err(ParsingError::InvalidArraySize(size_token));
             },
+            _ => return Err(ParsingError::UnexpectedToken(size_token))
+        };
 
-            _ => Err(ParsingError::UnexpectedToken(token))
-        }
+        let right_parenthesis = self.expect_token(Token::RightParenthesis)?;
+
+        Ok(Spanned::new(FieldType::Bytes(Some(max_size)), bytes_token.from, right_parenthesis.to))
+    }
+
+    /// Parse the rest of an array type, such as `u8; 4]`, after its opening bracket
+    fn expect_array(&mut self, left_bracket: ItemType) -> ParsingResult<Spanned<Array>> {
+        let data_type = self.expect_array_type()?.item;
+        self.expect_token(Token::SemiColon)?;
+        let count_token = self.expect_next()?;
+
+        let element_count = match &count_token.item {
+            // Simple integer or hex value will generate a simple number
+            Token::NumericLiteral(NumericLiteral::PositiveInteger(value, numeral_system)) => ArraySize::Integer(*value, *numeral_system),
+
+            // String will generate a user definition, which will be populated with a value in post processing
+            Token::Identifier(string) => ArraySize::UserDefinition(DefineDefinition {
+                name:         self.expect_qualified_name(Spanned::new(string.to_string(), count_token.from, count_token.to))?.item,
+                value:        DefineValue::NoValue,
+                comment:      None,
+                redefinition: None
+            }),
+            // Give numeric values that cannot be element counts a clearer message than an unexpected token
+            Token::NumericLiteral(NumericLiteral::NegativeInteger(value, _)) => {
+                error!("Array size {0} is negative! Array sizes must be positive integers", value);
+                return Err(ParsingError::InvalidArraySize(count_token));
+            },
+            Token::NumericLiteral(NumericLiteral::Float(value)) => {
+                error!("Array size {0} is a floating point value! Array sizes must be positive integers", value);
+                return Err(ParsingError::InvalidArraySize(count_token));
+            },
+            Token::NumericLiteral(NumericLiteral::Boolean(value)) => {
+                error!("Array size {0} is a boolean value! Array sizes must be positive integers", value);
+                return Err(ParsingError::InvalidArraySize(count_token));
+            },
+            _ => return Err(ParsingError::UnexpectedToken(count_token))
+        };
+
+        let right_bracket = self.expect_token(Token::RightBracket)?;
+
+        Ok(Spanned::new(Array { data_type, element_count }, left_bracket.from, right_bracket.to))
     }
 }
 
 impl<T> TokenSource for Peekable<T>
 where
-    T: Iterator<Item = ItemType> + Clone
+    T: Iterator<Item = ItemType>
 {
     fn next(&mut self) -> Option<ItemType> {
         std::iter::Iterator::next(self)
@@ -396,6 +452,197 @@ where
     }
 }
 
+/// Token source pulling tokens from a scanner only as the parser needs them, so the tokens of a file are never all held in memory at once.
+/// The stream ends at the first scanning error, which is kept to be reported once parsing stops
+pub struct TokenStream<T: Iterator<Item = Result<ItemType, ScanningError>>> {
+    tokens: T,
+    peeked: Option<ItemType>,
+    error:  Option<ScanningError>
+}
+
+impl<T: Iterator<Item = Result<ItemType, ScanningError>>> TokenStream<T> {
+    pub fn new(tokens: T) -> TokenStream<T> {
+        TokenStream {
+            tokens,
+            peeked: None,
+            error: None
+        }
+    }
+
+    /// Get the scanning error that ended the stream, if any
+    pub fn take_error(&mut self) -> Option<ScanningError> {
+        self.error.take()
+    }
+
+    fn fill_peeked(&mut self) {
+        if self.peeked.is_some() || self.error.is_some() {
+            return;
+        }
+
+        match self.tokens.next() {
+            None => (),
+            Some(Ok(token)) => self.peeked = Some(token),
+            Some(Err(error)) => self.error = Some(error)
+        }
+    }
+}
+
+impl<T: Iterator<Item = Result<ItemType, ScanningError>>> TokenSource for TokenStream<T> {
+    fn next(&mut self) -> Option<ItemType> {
+        self.fill_peeked();
+        self.peeked.take()
+    }
+
+    fn peek(&mut self) -> Option<&ItemType> {
+        self.fill_peeked();
+        self.peeked.as_ref()
+    }
+}
+
+/// Annotation preceding a declaration, such as `@obsolete_after(4)`
+pub struct Annotation {
+    pub name:      Spanned<String>,
+    pub arguments: Vec<ItemType>
+}
+
+impl Annotation {
+    /// Check that the annotation is a plain flag, and get its name
+    fn expect_no_arguments(&self) -> ParsingResult<Spanned<String>> {
+        match self.arguments.first() {
+            None => Ok(self.name.clone()),
+            Some(argument) => {
+                error!("Annotation @{0} does not take any arguments", self.name.item);
+                Err(ParsingError::UnexpectedToken(argument.clone()))
+            }
+        }
+    }
+
+    /// Get the single positive integer argument of the annotation
+    fn expect_integer_argument(&self) -> ParsingResult<u64> {
+        match self.arguments.as_slice() {
+            [argument] => match &argument.item {
+                Token::NumericLiteral(NumericLiteral::PositiveInteger(value, _)) => Ok(*value),
+                _ => {
+                    error!("Annotation @{0} expects a positive integer argument", self.name.item);
+                    Err(ParsingError::UnexpectedToken(argument.clone()))
+                }
+            },
+            _ => {
+                error!("Annotation @{0} expects exactly one argument, but got {1}", self.name.item, self.arguments.len());
+                Err(ParsingError::InvalidAnnotation(self.name.clone()))
+            }
+        }
+    }
+
+    /// Get the single string literal argument of the annotation
+    fn expect_string_argument(&self) -> ParsingResult<String> {
+        match self.arguments.as_slice() {
+            [argument] => match &argument.item {
+                Token::StringLiteral(string) => Ok(string.clone()),
+                _ => {
+                    error!("Annotation @{0} expects a string argument", self.name.item);
+                    Err(ParsingError::UnexpectedToken(argument.clone()))
+                }
+            },
+            _ => {
+                error!("Annotation @{0} expects exactly one argument, but got {1}", self.name.item, self.arguments.len());
+                Err(ParsingError::InvalidAnnotation(self.name.clone()))
+            }
+        }
+    }
+
+    /// Get the single string literal argument of the annotation, which cannot be blank
+    fn expect_non_empty_string_argument(&self) -> ParsingResult<String> {
+        let string = self.expect_string_argument()?;
+
+        if string.trim().is_empty() {
+            error!("Annotation @{0} expects a non-empty string argument", self.name.item);
+            return Err(ParsingError::InvalidAnnotation(self.name.clone()));
+        }
+
+        Ok(string)
+    }
+
+    /// Get the single primitive type argument of the annotation
+    fn expect_primitive_argument(&self) -> ParsingResult<Primitive> {
+        match self.arguments.len() {
+            1 => Ok(self.arguments.clone().into_iter().peekable().expect_primitive()?.item),
+            _ => {
+                error!("Annotation @{0} expects exactly one primitive type argument, but got {1} arguments", self.name.item, self.arguments.len());
+                Err(ParsingError::InvalidAnnotation(self.name.clone()))
+            }
+        }
+    }
+}
+
+/// Bytes have no fixed size, so they can only be the type of a message field, and never of a struct member or an array element
+fn check_not_bytes(token: &ItemType) -> ParsingResult<()> {
+    if matches!(&token.item, Token::Identifier(string) if &**string == "bytes") {
+        error!("bytes is only allowed as the type of a message field, and cannot be used for struct members or array elements");
+        return Err(ParsingError::InvalidTypeUse(token.clone()));
+    }
+
+    Ok(())
+}
+
+fn parse_annotations(tokens: &mut impl TokenSource) -> ParsingResult<Vec<Annotation>> {
+    let mut annotations: Vec<Annotation> = Vec::new();
+
+    while let Some(Spanned { item: Token::Annotation(_), .. }) = tokens.peek() {
+        let token = tokens.expect_next()?;
+        let Token::Annotation(name) = token.item else { unreachable!() };
+
+        let mut arguments: Vec<ItemType> = Vec::new();
+
+        // Arguments are optional, and separated by exactly one comma each
+        if tokens.maybe_expect(Token::LeftParenthesis).is_some() && tokens.maybe_expect(Token::RightParenthesis).is_none() {
+            loop {
+                let argument = tokens.expect_next()?;
+
+                if matches!(argument.item, Token::Comma | Token::RightParenthesis) {
+                    error!("Expected an argument to annotation @{0}, but got {1:?}", name, argument.item);
+                    return Err(ParsingError::UnexpectedToken(argument));
+                }
+
+                arguments.push(argument);
+
+                let separator = tokens.expect_next()?;
+
+                match separator.item {
+                    Token::RightParenthesis => break,
+                    Token::Comma => continue,
+                    _ => {
+                        error!("Arguments to annotation @{0} must be separated by commas", name);
+                        return Err(ParsingError::UnexpectedToken(separator));
+                    }
+                }
+            }
+        }
+
+        add_annotation(
+            &mut annotations,
+            Annotation {
+                name: Spanned::new(name, token.from, token.to),
+                arguments
+            }
+        )?;
+    }
+
+    Ok(annotations)
+}
+
+/// Add an annotation to those given to the same declaration. Compiler attributes can be given several times, while every other annotation
+/// sets a single property, so giving it twice is an error
+fn add_annotation(annotations: &mut Vec<Annotation>, annotation: Annotation) -> ParsingResult<()> {
+    if annotation.name.item != "c_attribute" && annotations.iter().any(|earlier| earlier.name.item == annotation.name.item) {
+        error!("Annotation @{0} is given more than once", annotation.name.item);
+        return Err(ParsingError::InvalidAnnotation(annotation.name));
+    }
+
+    annotations.push(annotation);
+    Ok(())
+}
+
 fn check_for_orphan_comment(tokens: &mut impl TokenSource, index: usize, comment: &Option<Spanned<String>>) -> Option<StandaloneCommentDefinition> {
     // Peek next token
     let peeked_token = match tokens.peek() {
@@ -420,10 +667,26 @@ fn check_for_orphan_comment(tokens: &mut impl TokenSource, index: usize, comment
     }
 }
 
-fn parse_bitfield(tokens: &mut impl TokenSource, last_comment: &mut Option<String>) -> Result<BitfieldDefinition, ParsingError> {
+fn parse_bitfield(tokens: &mut impl TokenSource, last_comment: &mut Option<String>, annotations: Vec<Annotation>) -> Result<BitfieldDefinition, ParsingError> {
     // Get comment if any
     let comment = last_comment.take();
 
+    let mut min_version: Option<u64> = None;
+    let mut group: Option<String> = None;
+    let mut c_attributes: Vec<String> = Vec::new();
+
+    for annotation in annotations {
+        match annotation.name.as_str() {
+            "min_version" => min_version = Some(annotation.expect_integer_argument()?),
+            "group" => group = Some(annotation.expect_string_argument()?),
+            "c_attribute" => c_attributes.push(annotation.expect_non_empty_string_argument()?),
+            _ => {
+                error!("Unknown annotation @{0} on bitfield declaration", annotation.name.item);
+                return Err(ParsingError::InvalidAnnotation(annotation.name));
+            }
+        }
+    }
+
     // Type and identifier
     tokens.expect_token(Token::Bitfield)?;
     let name = tokens.expect_identifier()?.item;
@@ -443,6 +706,7 @@ fn parse_bitfield(tokens: &mut impl TokenSource, last_comment: &mut Option<Strin
     let mut members = Vec::new();
     let mut orphan_comments: Vec<StandaloneCommentDefinition> = Vec::new();
     let mut reserved_indexes: Vec<u64> = Vec::new();
+    let mut deprecated_indexes: Vec<u64> = Vec::new();
 
     loop {
         // Get comment if any
@@ -471,26 +735,31 @@ fn parse_bitfield(tokens: &mut impl TokenSource, last_comment: &mut Option<Strin
             continue;
         }
 
-        // Check for reserved values
-        if peeked_token.item == Token::Reserve {
+        // Check for reserved and deprecated values
+        if matches!(peeked_token.item, Token::Reserve | Token::Deprecate) {
+            let indexes = match peeked_token.item {
+                Token::Deprecate => &mut deprecated_indexes,
+                _ => &mut reserved_indexes
+            };
+
             // Push field index to reservation list if valid, throw error if not
             for item in parse_reserved(tokens, false)? {
                 let index = item.to_bit_index()?;
                 match backing_type.validate_bit_index(&index) {
-                    true => reserved_indexes.push(item.to_bit_index()?),
+                    true => indexes.push(index),
                     false => {
                         error!("Reserved index {0} in bitfield {1} is not valid within backing type {2:?}", index, name, backing_type);
                         return Err(ParsingError::InvalidBitIndex(NumericLiteral::PositiveInteger(index, NumeralSystem::Decimal)));
                     }
                 }
+            }
 
-                // If the next token is a right brace, then the definition has ended, so break and return
-                if tokens.maybe_expect(Token::RightBrace).is_some() {
-                    break;
-                }
-
-                continue;
+            // If the next token is a right brace, then the definition has ended, so break and return
+            if tokens.maybe_expect(Token::RightBrace).is_some() {
+                break;
             }
+
+            continue;
         }
 
         // Parser bitfield member
@@ -539,8 +808,12 @@ fn parse_bitfield(tokens: &mut impl TokenSource, last_comment: &mut Option<Strin
         backing_type,
         members,
         reserved_indexes,
+        deprecated_indexes,
         comment,
-        orphan_comments
+        orphan_comments,
+        min_version,
+        group,
+        c_attributes
     })
 }
 
@@ -557,6 +830,8 @@ fn parse_define(tokens: &mut impl TokenSource, last_comment: &mut Option<String>
     let value_token = tokens.expect_next()?;
     let value: DefineValue = match value_token.item {
         Token::NumericLiteral(value) => DefineValue::NumericLiteral(value),
+        Token::Identifier(name) => DefineValue::Alias(tokens.expect_qualified_name(Spanned::new(name.to_string(), value_token.from, value_token.to))?.item),
+        Token::LeftParenthesis => DefineValue::Expression(parse_define_expression(tokens)?),
         _ => return Err(ParsingError::UnexpectedToken(value_token))
     };
 
@@ -581,10 +856,89 @@ fn parse_define(tokens: &mut impl TokenSource, last_comment: &mut Option<String>
     })
 }
 
-fn parse_enum(tokens: &mut impl TokenSource, last_comment: &mut Option<String>) -> Result<EnumDefinition, ParsingError> {
+/// Parse an arithmetic define expression after its opening parenthesis, up to and including the closing parenthesis. Multiplication and
+/// division bind tighter than addition and subtraction, and operators of the same precedence apply from left to right
+fn parse_define_expression(tokens: &mut impl TokenSource) -> Result<DefineExpression, ParsingError> {
+    let expression = parse_define_sum(tokens)?;
+    tokens.expect_token(Token::RightParenthesis)?;
+
+    Ok(expression)
+}
+
+fn parse_define_sum(tokens: &mut impl TokenSource) -> Result<DefineExpression, ParsingError> {
+    let mut expression = parse_define_product(tokens, None)?;
+
+    loop {
+        let (operator, first_operand) = match tokens.peek().map(|token| &token.item) {
+            Some(Token::Plus) => (DefineOperator::Add, None),
+            Some(Token::Minus) => (DefineOperator::Subtract, None),
+
+            // A minus directly followed by a digit is scanned as a negative literal, so (SIZE -1) is a subtraction as well
+            Some(Token::NumericLiteral(NumericLiteral::NegativeInteger(value, numeral_system))) => (
+                DefineOperator::Subtract,
+                Some(DefineExpression::Literal(NumericLiteral::PositiveInteger(value.unsigned_abs(), *numeral_system)))
+            ),
+
+            _ => return Ok(expression)
+        };
+        tokens.next();
+
+        let right = parse_define_product(tokens, first_operand)?;
+        expression = DefineExpression::Binary(Box::new(expression), operator, Box::new(right));
+    }
+}
+
+/// Parse a chain of multiplications and divisions. The first operand may already have been consumed by the caller
+fn parse_define_product(tokens: &mut impl TokenSource, first_operand: Option<DefineExpression>) -> Result<DefineExpression, ParsingError> {
+    let mut expression = match first_operand {
+        None => parse_define_operand(tokens)?,
+        Some(operand) => operand
+    };
+
+    loop {
+        let operator = match tokens.peek().map(|token| &token.item) {
+            Some(Token::Asterisk) => DefineOperator::Multiply,
+            Some(Token::Slash) => DefineOperator::Divide,
+            _ => return Ok(expression)
+        };
+        tokens.next();
+
+        let right = parse_define_operand(tokens)?;
+        expression = DefineExpression::Binary(Box::new(expression), operator, Box::new(right));
+    }
+}
+
+fn parse_define_operand(tokens: &mut impl TokenSource) -> Result<DefineExpression, ParsingError> {
+    let token = tokens.expect_next()?;
+
+    match token.item {
+        Token::NumericLiteral(value) => Ok(DefineExpression::Literal(value)),
+        Token::Identifier(name) => Ok(DefineExpression::Identifier(tokens.expect_qualified_name(Spanned::new(name.to_string(), token.from, token.to))?.item)),
+        Token::LeftParenthesis => parse_define_expression(tokens),
+        _ => Err(ParsingError::UnexpectedToken(token))
+    }
+}
+
+fn parse_enum(tokens: &mut impl TokenSource, last_comment: &mut Option<String>, annotations: Vec<Annotation>) -> Result<EnumDefinition, ParsingError> {
     // Get comment if any
     let comment = last_comment.take();
 
+    let mut encoded_type: Option<Primitive> = None;
+    let mut min_version: Option<u64> = None;
+    let mut group: Option<String> = None;
+
+    for annotation in annotations {
+        match annotation.name.as_str() {
+            "encode_as" => encoded_type = Some(annotation.expect_primitive_argument()?),
+            "min_version" => min_version = Some(annotation.expect_integer_argument()?),
+            "group" => group = Some(annotation.expect_string_argument()?),
+            _ => {
+                error!("Unknown annotation @{0} on enum declaration", annotation.name.item);
+                return Err(ParsingError::InvalidAnnotation(annotation.name));
+            }
+        }
+    }
+
     // Get enum token
     tokens.expect_token(Token::Enum)?;
 
@@ -601,11 +955,20 @@ fn parse_enum(tokens: &mut impl TokenSource, last_comment: &mut Option<String>)
         return Err(ParsingError::InvalidEnumBackingType(backing_type));
     }
 
+    // Validate encoded type. Whether the values fit within it is checked once all extensions have been appended
+    if let Some(encoded_type) = &encoded_type {
+        if !encoded_type.can_back_enum() || encoded_type.encoded_max_data_size() > backing_type.encoded_max_data_size() {
+            error!("{0:?} is not a valid encoding type for enum {1} with backing type {2:?}!", encoded_type, name, backing_type);
+            return Err(ParsingError::InvalidEnumBackingType(encoded_type.clone()));
+        }
+    }
+
     tokens.expect_token(Token::LeftBrace)?;
 
     let mut members: Vec<EnumMember> = Vec::new();
     let mut orphan_comments: Vec<StandaloneCommentDefinition> = Vec::new();
     let mut reserved_values: Vec<NumericLiteral> = Vec::new();
+    let mut deprecated_values: Vec<NumericLiteral> = Vec::new();
 
     loop {
         let comment = tokens.maybe_expect_comment();
@@ -633,12 +996,17 @@ fn parse_enum(tokens: &mut impl TokenSource, last_comment: &mut Option<String>)
             continue;
         }
 
-        // Check for reserved values
-        if peeked_token.item == Token::Reserve {
+        // Check for reserved and deprecated values
+        if matches!(peeked_token.item, Token::Reserve | Token::Deprecate) {
+            let values = match peeked_token.item {
+                Token::Deprecate => &mut deprecated_values,
+                _ => &mut reserved_values
+            };
+
             // Push field index to reservation list if valid, throw error if not
             for item in parse_reserved(tokens, true)? {
                 match backing_type.validate_value(&item) {
-                    true => reserved_values.push(item),
+                    true => values.push(item),
                     false => {
                         error!("Reserved enum value {0} in enum {1} does not conform within backing type {2:?}", item.to_string(), name, backing_type);
                         return Err(ParsingError::InvalidEnumValue(item));
@@ -659,14 +1027,32 @@ fn parse_enum(tokens: &mut impl TokenSource, last_comment: &mut Option<String>)
 
         let identifier = tokens.expect_identifier()?.item;
 
-        tokens.expect_token(Token::Equals)?;
-
-        let value_token = tokens.expect_next()?;
-        let value = match value_token.item {
-            Token::NumericLiteral(value) => value,
-            _ => return Err(ParsingError::UnexpectedToken(value_token))
+        // Members without an explicit value take the value following the previous member, starting at zero
+        let value = match tokens.maybe_expect(Token::Equals) {
+            Some(_) => {
+                let value_token = tokens.expect_next()?;
+                match value_token.item {
+                    Token::NumericLiteral(value) => value,
+                    _ => return Err(ParsingError::UnexpectedToken(value_token))
+                }
+            },
+            None => match next_enum_value(members.last().map(|member| &member.value), &backing_type) {
+                Some(value) => value,
+                None => {
+                    error!("Member {0} in enum {1} follows the last possible value of backing type {2:?}, and must be given a value", identifier, name, backing_type);
+                    return Err(ParsingError::InvalidEnumValue(members.last().unwrap().value.clone()));
+                }
+            }
         };
 
+        // Infinite values and NaN cannot be compared reliably, and are thus not valid enum values
+        if let NumericLiteral::Float(float) = &value {
+            if !float.is_finite() {
+                error!("Value {0} of member {1} in enum {2} is not a finite number", value.to_string(), identifier, name);
+                return Err(ParsingError::InvalidEnumValue(value));
+            }
+        }
+
         // Validate value against backing type
         if !backing_type.validate_value(&value) {
             error!("Value {0} in enum {1} does not conform within backing type {2:?}", value.to_string(), name, backing_type);
@@ -691,13 +1077,35 @@ fn parse_enum(tokens: &mut impl TokenSource, last_comment: &mut Option<String>)
     Ok(EnumDefinition {
         name,
         backing_type,
+        encoded_type,
         orphan_comments,
         members,
         reserved_values,
-        comment
+        deprecated_values,
+        comment,
+        min_version,
+        group
     })
 }
 
+/// Value of an enum member declared without one, being the value following the previous member, or zero for the first member
+fn next_enum_value(previous: Option<&NumericLiteral>, backing_type: &Primitive) -> Option<NumericLiteral> {
+    match previous {
+        None => Some(match backing_type {
+            Primitive::Bool => NumericLiteral::Boolean(false),
+            Primitive::F16 | Primitive::F32 | Primitive::F64 => NumericLiteral::Float(0.0),
+            _ => NumericLiteral::PositiveInteger(0, NumeralSystem::Decimal)
+        }),
+        Some(NumericLiteral::AsciiChar(character)) => char::from_u32(*character as u32 + 1).map(NumericLiteral::AsciiChar),
+        Some(NumericLiteral::Boolean(false)) => Some(NumericLiteral::Boolean(true)),
+        Some(NumericLiteral::Boolean(true)) => None,
+        Some(NumericLiteral::PositiveInteger(value, numeral_system)) => value.checked_add(1).map(|value| NumericLiteral::PositiveInteger(value, *numeral_system)),
+        Some(NumericLiteral::NegativeInteger(-1, numeral_system)) => Some(NumericLiteral::PositiveInteger(0, *numeral_system)),
+        Some(NumericLiteral::NegativeInteger(value, numeral_system)) => Some(NumericLiteral::NegativeInteger(value + 1, *numeral_system)),
+        Some(NumericLiteral::Float(float)) => Some(NumericLiteral::Float(float + 1.0))
+    }
+}
+
 fn parse_extension(tokens: &mut impl TokenSource, last_comment: &mut Option<String>) -> Result<ExtensionDefinition, ParsingError> {
     // Get extend token
     tokens.expect_token(Token::Extend)?;
@@ -709,19 +1117,19 @@ fn parse_extension(tokens: &mut impl TokenSource, last_comment: &mut Option<Stri
     };
 
     match &next_token.item {
-        Token::Bitfield => match parse_bitfield(tokens, last_comment) {
+        Token::Bitfield => match parse_bitfield(tokens, last_comment, Vec::new()) {
             Ok(definition) => Ok(ExtensionDefinition::Bitfield(definition)),
             Err(error) => Err(error)
         },
-        Token::Enum => match parse_enum(tokens, last_comment) {
+        Token::Enum => match parse_enum(tokens, last_comment, Vec::new()) {
             Ok(definition) => Ok(ExtensionDefinition::Enum(definition)),
             Err(error) => Err(error)
         },
-        Token::Message => match parse_message(tokens, last_comment) {
+        Token::Message => match parse_message(tokens, last_comment, Vec::new()) {
             Ok(definition) => Ok(ExtensionDefinition::Message(definition)),
             Err(error) => Err(error)
         },
-        Token::Struct => match parse_struct(tokens, last_comment) {
+        Token::Struct => match parse_struct(tokens, last_comment, Vec::new()) {
             Ok(definition) => Ok(ExtensionDefinition::Struct(definition)),
             Err(error) => Err(error)
         },
@@ -732,7 +1140,24 @@ fn parse_extension(tokens: &mut impl TokenSource, last_comment: &mut Option<Stri
 fn parse_include(tokens: &mut impl TokenSource, _: &mut Option<String>) -> Result<IncludeDefinition, ParsingError> {
     tokens.expect_next()?;
 
-    let string: String = tokens.expect_string_literal()?.item.strip_suffix(".rune").expect("File included was now a .rune file").to_string();
+    let path_token: Spanned<String> = tokens.expect_string_literal()?;
+
+    // The .rune extension is optional, but any other extension is not a valid include
+    let string: String = match path_token.strip_suffix(".rune") {
+        Some(stripped_path) => stripped_path.to_string(),
+        None => match Path::new(&path_token.item).extension() {
+            None => path_token.item.clone(),
+            Some(extension) => {
+                error!("Included file \"{0}\" has extension {1:?}, but only .rune files can be included", path_token.item, extension);
+                return Err(ParsingError::InvalidFilePath(path_token));
+            }
+        }
+    };
+
+    if string.is_empty() {
+        error!("Included file \"{0}\" has no name", path_token.item);
+        return Err(ParsingError::InvalidFilePath(path_token));
+    }
 
     tokens.expect_token(Token::SemiColon)?;
 
@@ -746,12 +1171,15 @@ fn parse_redefine(tokens: &mut impl TokenSource, last_comment: &mut Option<Strin
     // Get redefine token
     tokens.expect_next()?;
 
-    // Get definition name
-    let name = tokens.expect_identifier()?.item;
+    // Get definition name, which may be that of a define inside a namespace
+    let first = tokens.expect_identifier()?;
+    let name = tokens.expect_qualified_name(first)?.item;
 
     let value_token = tokens.expect_next()?;
     let value: DefineValue = match value_token.item {
         Token::NumericLiteral(value) => DefineValue::NumericLiteral(value),
+        Token::Identifier(name) => DefineValue::Alias(tokens.expect_qualified_name(Spanned::new(name.to_string(), value_token.from, value_token.to))?.item),
+        Token::LeftParenthesis => DefineValue::Expression(parse_define_expression(tokens)?),
         _ => return Err(ParsingError::UnexpectedToken(value_token))
     };
 
@@ -812,6 +1240,11 @@ fn parse_reserved(tokens: &mut impl TokenSource, allow_negative: bool) -> Result
                         };
                         let end = match end_value {
                             NumericLiteral::NegativeInteger(value, _) => *value,
+                            // Range crossing zero, such as -2..3
+                            NumericLiteral::PositiveInteger(value, _) => match i64::try_from(*value) {
+                                Ok(value) => value,
+                                Err(_) => return Err(ParsingError::UnexpectedToken(token))
+                            },
                             _ => return Err(ParsingError::UnexpectedToken(token))
                         };
 
@@ -871,10 +1304,24 @@ fn parse_reserved(tokens: &mut impl TokenSource, allow_negative: bool) -> Result
     Ok(reserved_values)
 }
 
-fn parse_message(tokens: &mut impl TokenSource, last_comment: &mut Option<String>) -> Result<MessageDefinition, ParsingError> {
+fn parse_message(tokens: &mut impl TokenSource, last_comment: &mut Option<String>, annotations: Vec<Annotation>) -> Result<MessageDefinition, ParsingError> {
     // Get comment if any
     let comment = last_comment.take();
 
+    let mut min_version: Option<u64> = None;
+    let mut group: Option<String> = None;
+
+    for annotation in annotations {
+        match annotation.name.as_str() {
+            "min_version" => min_version = Some(annotation.expect_integer_argument()?),
+            "group" => group = Some(annotation.expect_string_argument()?),
+            _ => {
+                error!("Unknown annotation @{0} on message declaration", annotation.name.item);
+                return Err(ParsingError::InvalidAnnotation(annotation.name));
+            }
+        }
+    }
+
     // Get struct token
     tokens.expect_token(Token::Message)?;
 
@@ -886,6 +1333,7 @@ fn parse_message(tokens: &mut impl TokenSource, last_comment: &mut Option<String
     let mut fields: Vec<MessageField> = Vec::new();
     let mut orphan_comments: Vec<StandaloneCommentDefinition> = Vec::new();
     let mut reserved_indexes: Vec<FieldIndex> = Vec::new();
+    let mut deprecated_indexes: Vec<FieldIndex> = Vec::new();
 
     loop {
         let comment = tokens.maybe_expect_comment();
@@ -913,11 +1361,16 @@ fn parse_message(tokens: &mut impl TokenSource, last_comment: &mut Option<String
             continue;
         }
 
-        // Check for reserved values
-        if peeked_token.item == Token::Reserve {
+        // Check for reserved and deprecated values
+        if matches!(peeked_token.item, Token::Reserve | Token::Deprecate) {
+            let indexes = match peeked_token.item {
+                Token::Deprecate => &mut deprecated_indexes,
+                _ => &mut reserved_indexes
+            };
+
             // Push field index to reservation list if valid, throw error if not
             for item in parse_reserved(tokens, false)? {
-                reserved_indexes.push(FieldIndex::Numeric(item.to_field_index()?));
+                indexes.push(FieldIndex::Numeric(item.to_field_index()?));
             }
 
             // If the next token is a right brace, then the definition has ended, so break and return
@@ -931,10 +1384,34 @@ fn parse_message(tokens: &mut impl TokenSource, last_comment: &mut Option<String
         // Parse message field
         // ————————————————————
 
+        let mut obsolete_after: Option<u64> = None;
+        let mut cstring: Option<Spanned<String>> = None;
+
+        for annotation in parse_annotations(tokens)? {
+            match annotation.name.as_str() {
+                "obsolete_after" => obsolete_after = Some(annotation.expect_integer_argument()?),
+                "cstring" => cstring = Some(annotation.expect_no_arguments()?),
+                _ => {
+                    error!("Unknown annotation @{0} on message field in message {1}", annotation.name.item, name);
+                    return Err(ParsingError::InvalidAnnotation(annotation.name));
+                }
+            }
+        }
+
         let field_ident = tokens.expect_identifier()?;
 
+        // A question mark after the name marks the field as optional
+        let optional: bool = tokens.maybe_expect(Token::QuestionMark).is_some();
+
         tokens.expect_token(Token::Colon)?;
-        let data_type = tokens.expect_field_type()?.item;
+        let data_type: FieldType = tokens.expect_field_type()?.item;
+
+        if let Some(annotation_name) = &cstring {
+            if !data_type.value_type().is_some_and(|value_type| value_type.is_char_array()) {
+                error!("Annotation @cstring can only be applied to char arrays, but field {0} of message {1} is {2:?}", field_ident.item, name, data_type);
+                return Err(ParsingError::InvalidAnnotation(annotation_name.clone()));
+            }
+        }
 
         tokens.expect_token(Token::Equals)?;
 
@@ -948,11 +1425,20 @@ fn parse_message(tokens: &mut impl TokenSource, last_comment: &mut Option<String
             _ => return Err(ParsingError::UnexpectedToken(index_token))
         };
 
+        let default = match data_type.value_type() {
+            Some(value_type) => parse_default_value(tokens, value_type, &field_ident.item, &name)?,
+            None => None
+        };
+
         fields.push(MessageField {
             identifier: field_ident.item.clone(),
             data_type,
             index,
-            comment: comment.map(|s| s.item)
+            optional,
+            comment: comment.map(|s| s.item),
+            obsolete_after,
+            cstring: cstring.is_some(),
+            default
         });
 
         if tokens.maybe_expect(Token::SemiColon).is_none() {
@@ -968,34 +1454,117 @@ fn parse_message(tokens: &mut impl TokenSource, last_comment: &mut Option<String
         name,
         fields,
         reserved_indexes,
+        deprecated_indexes,
         orphan_comments,
-        comment
+        comment,
+        min_version,
+        group
     })
 }
 
-pub fn parse_struct(tokens: &mut impl TokenSource, last_comment: &mut Option<String>) -> Result<StructDefinition, ParsingError> {
-    // Get comment if any
-    let comment = last_comment.take();
+/// Parses the optional `default <literal>` clause following the index of a struct member or message field. `default` is only a keyword
+/// here, so it can still be used as a name everywhere else
+fn parse_default_value(tokens: &mut impl TokenSource, data_type: &ValueType, identifier: &str, parent: &str) -> ParsingResult<Option<DefineValue>> {
+    match TokenSource::peek(tokens) {
+        Some(Spanned { item: Token::Identifier(word), .. }) if word.eq_ignore_ascii_case("default") => tokens.expect_next()?,
+        _ => return Ok(None)
+    };
 
-    // Get struct token
-    tokens.expect_token(Token::Struct)?;
+    let value_token = tokens.expect_next()?;
+    let value = match &value_token.item {
+        Token::NumericLiteral(value) => value.clone(),
+
+        // Char arrays can be initialized from text. Whether the text fits is checked in validation, as the array size may be a define
+        Token::StringLiteral(text) if data_type.is_char_array() => {
+            if !text.is_ascii() {
+                error!("Default value \"{0}\" of {1} in {2} contains characters outside the ASCII range", text, identifier, parent);
+                return Err(ParsingError::InvalidDefaultValue(value_token));
+            }
 
-    // Get message name
-    let name = tokens.expect_identifier()?.item;
+            return Ok(Some(DefineValue::StringLiteral(text.clone())));
+        },
 
-    tokens.expect_token(Token::LeftBrace)?;
+        _ => return Err(ParsingError::UnexpectedToken(value_token))
+    };
 
-    let mut members: Vec<StructMember> = Vec::new();
-    let mut orphan_comments: Vec<StandaloneCommentDefinition> = Vec::new();
+    let primitive = match data_type {
+        ValueType::Primitive(primitive) => primitive,
+        _ => {
+            error!("{0} in {1} has a numeric default value, but only primitive types can have numeric default values, not {2:?}", identifier, parent, data_type);
+            return Err(ParsingError::InvalidDefaultValue(value_token));
+        }
+    };
 
-    loop {
-        let comment = tokens.maybe_expect_comment();
+    if !primitive.validate_value(&value) {
+        error!("Default value {0} of {1} in {2} does not conform within type {3:?}", value.to_string(), identifier, parent, primitive);
+        return Err(ParsingError::InvalidDefaultValue(value_token));
+    }
 
-        // Peek next token
-        let peeked_token = match tokens.peek() {
-            Some(token) => token.clone(),
-            None => {
-                error!("Sudden end of file in the middle of a struct!");
+    Ok(Some(DefineValue::NumericLiteral(value)))
+}
+
+fn parse_version(tokens: &mut impl TokenSource) -> Result<u64, ParsingError> {
+    // Get version identifier
+    tokens.expect_next()?;
+
+    let value_token = tokens.expect_next()?;
+    let version: u64 = match value_token.item {
+        Token::NumericLiteral(NumericLiteral::PositiveInteger(value, _)) => value,
+        _ => {
+            error!("Schema version must be a positive integer");
+            return Err(ParsingError::UnexpectedToken(value_token));
+        }
+    };
+
+    tokens.expect_token(Token::SemiColon)?;
+
+    Ok(version)
+}
+
+pub fn parse_struct(tokens: &mut impl TokenSource, last_comment: &mut Option<String>, annotations: Vec<Annotation>) -> Result<StructDefinition, ParsingError> {
+    // Get comment if any
+    let comment = last_comment.take();
+
+    let mut min_version: Option<u64> = None;
+    let mut group: Option<String> = None;
+    let mut c_attributes: Vec<String> = Vec::new();
+    let mut no_sort: bool = false;
+
+    for annotation in annotations {
+        match annotation.name.as_str() {
+            "min_version" => min_version = Some(annotation.expect_integer_argument()?),
+            "group" => group = Some(annotation.expect_string_argument()?),
+            "c_attribute" => c_attributes.push(annotation.expect_non_empty_string_argument()?),
+            "no_sort" => {
+                annotation.expect_no_arguments()?;
+                no_sort = true;
+            },
+            _ => {
+                error!("Unknown annotation @{0} on struct declaration", annotation.name.item);
+                return Err(ParsingError::InvalidAnnotation(annotation.name));
+            }
+        }
+    }
+
+    // Get struct token
+    tokens.expect_token(Token::Struct)?;
+
+    // Get message name
+    let name = tokens.expect_identifier()?.item;
+
+    tokens.expect_token(Token::LeftBrace)?;
+
+    let mut members: Vec<StructMember> = Vec::new();
+    let mut orphan_comments: Vec<StandaloneCommentDefinition> = Vec::new();
+
+    loop {
+        let comment = tokens.maybe_expect_comment();
+
+        // Peek next token
+        let peeked_token = match tokens.peek() {
+            Some(token) => token.clone(),
+            None => {
+                error!("Sudden end of file in the middle of a struct!");
                 return Err(ParsingError::UnexpectedEndOfInput);
             }
         };
@@ -1015,7 +1584,7 @@ pub fn parse_struct(tokens: &mut impl TokenSource, last_comment: &mut Option<Str
         }
 
         // Check for reservations, which are not valid
-        if peeked_token.item == Token::Reserve {
+        if matches!(peeked_token.item, Token::Reserve | Token::Deprecate) {
             error!("Struct indexes cannot be reserved. They are merely for data ordering when flattening the struct.");
             return Err(ParsingError::UnexpectedToken(peeked_token));
         }
@@ -1023,10 +1592,29 @@ pub fn parse_struct(tokens: &mut impl TokenSource, last_comment: &mut Option<Str
         // Parse struct member
         // ————————————————————
 
+        let mut cstring: Option<Spanned<String>> = None;
+
+        for annotation in parse_annotations(tokens)? {
+            match annotation.name.as_str() {
+                "cstring" => cstring = Some(annotation.expect_no_arguments()?),
+                _ => {
+                    error!("Unknown annotation @{0} on struct member in struct {1}", annotation.name.item, name);
+                    return Err(ParsingError::InvalidAnnotation(annotation.name));
+                }
+            }
+        }
+
         let field_ident = tokens.expect_identifier()?;
 
         tokens.expect_token(Token::Colon)?;
-        let data_type = tokens.expect_member_type()?.item;
+        let data_type: MemberType = tokens.expect_value_type()?.item;
+
+        if let Some(annotation_name) = &cstring {
+            if !data_type.is_char_array() {
+                error!("Annotation @cstring can only be applied to char arrays, but member {0} of struct {1} is {2:?}", field_ident.item, name, data_type);
+                return Err(ParsingError::InvalidAnnotation(annotation_name.clone()));
+            }
+        }
 
         tokens.expect_token(Token::Equals)?;
 
@@ -1039,11 +1627,15 @@ pub fn parse_struct(tokens: &mut impl TokenSource, last_comment: &mut Option<Str
             _ => return Err(ParsingError::UnexpectedToken(index_token))
         };
 
+        let default = parse_default_value(tokens, &data_type, &field_ident.item, &name)?;
+
         members.push(StructMember {
             identifier: field_ident.item.clone(),
             data_type,
             index,
-            comment: comment.map(|s| s.item)
+            comment: comment.map(|s| s.item),
+            cstring: cstring.is_some(),
+            default
         });
 
         if tokens.maybe_expect(Token::SemiColon).is_none() {
@@ -1059,36 +1651,244 @@ pub fn parse_struct(tokens: &mut impl TokenSource, last_comment: &mut Option<Str
         name,
         members,
         orphan_comments,
-        comment
+        comment,
+        min_version,
+        group,
+        c_attributes,
+        no_sort,
+        external_size: None
+    })
+}
+
+/// Parse a declaration of a struct defined outside of Rune, such as `@size(8) external struct PlatformTime;`
+fn parse_external(tokens: &mut impl TokenSource, last_comment: &mut Option<String>, annotations: Vec<Annotation>) -> Result<StructDefinition, ParsingError> {
+    // Get comment if any
+    let comment = last_comment.take();
+
+    // Skip external identifier
+    tokens.expect_next()?;
+
+    let mut size: Option<u64> = None;
+    let mut min_version: Option<u64> = None;
+    let mut group: Option<String> = None;
+
+    for annotation in annotations {
+        match annotation.name.as_str() {
+            "size" => size = Some(annotation.expect_integer_argument()?),
+            "min_version" => min_version = Some(annotation.expect_integer_argument()?),
+            "group" => group = Some(annotation.expect_string_argument()?),
+            _ => {
+                error!("Unknown annotation @{0} on external declaration", annotation.name.item);
+                return Err(ParsingError::InvalidAnnotation(annotation.name));
+            }
+        }
+    }
+
+    // Only structs can be declared as external
+    tokens.expect_token(Token::Struct)?;
+
+    let name = tokens.expect_identifier()?;
+
+    tokens.expect_token(Token::SemiColon)?;
+
+    let external_size: u64 = match size {
+        Some(size) if size > 0 => size,
+        _ => {
+            error!("External struct {0} must declare its size in bytes with @size(N)", name.item);
+            return Err(ParsingError::MissingExternalSize(name));
+        }
+    };
+
+    Ok(StructDefinition {
+        name: name.item,
+        members: Vec::new(),
+        orphan_comments: Vec::new(),
+        comment,
+        min_version,
+        group,
+        c_attributes: Vec::new(),
+        no_sort: false,
+        external_size: Some(external_size)
     })
 }
 
+/// Prefix the names of the definitions declared inside a namespace block with the namespace, such as `Foo::Header`, along with every
+/// reference to them from inside the block. References to definitions outside of the block are left as written
+fn qualify_namespace(definitions: &mut Definitions, namespace: &str, start: usize) {
+    let declared: Vec<String> = definitions.declaration_order[start..]
+        .iter()
+        .filter_map(|declaration| match declaration {
+            Declaration::Comment(_) => None,
+            Declaration::Bitfield(name) | Declaration::Define(name) | Declaration::Enum(name) | Declaration::Message(name) | Declaration::Struct(name) => Some(name.clone())
+        })
+        .collect();
+
+    let qualify = |name: &mut String| {
+        if declared.contains(name) {
+            *name = format!("{0}::{1}", namespace, name);
+        }
+    };
+
+    // Definitions are pushed in the order they are declared, so those of the block are the last ones of each kind
+    let count = |kind: fn(&Declaration) -> bool| definitions.declaration_order[start..].iter().filter(|declaration| kind(declaration)).count();
+    let bitfields: usize = count(|declaration| matches!(declaration, Declaration::Bitfield(_)));
+    let defines: usize = count(|declaration| matches!(declaration, Declaration::Define(_)));
+    let enums: usize = count(|declaration| matches!(declaration, Declaration::Enum(_)));
+    let messages: usize = count(|declaration| matches!(declaration, Declaration::Message(_)));
+    let structs: usize = count(|declaration| matches!(declaration, Declaration::Struct(_)));
+
+    let bitfields_start: usize = definitions.bitfields.len() - bitfields;
+    for definition in &mut definitions.bitfields[bitfields_start..] {
+        qualify(&mut definition.name);
+    }
+
+    let defines_start: usize = definitions.defines.len() - defines;
+    for definition in &mut definitions.defines[defines_start..] {
+        qualify(&mut definition.name);
+
+        match &mut definition.value {
+            DefineValue::Alias(name) => qualify(name),
+            DefineValue::Expression(expression) => qualify_expression(expression, &qualify),
+            _ => ()
+        }
+    }
+
+    let enums_start: usize = definitions.enums.len() - enums;
+    for definition in &mut definitions.enums[enums_start..] {
+        qualify(&mut definition.name);
+    }
+
+    let messages_start: usize = definitions.messages.len() - messages;
+    for definition in &mut definitions.messages[messages_start..] {
+        qualify(&mut definition.name);
+
+        for field in &mut definition.fields {
+            if let FieldType::Value(value_type) = &mut field.data_type {
+                qualify_value_type(value_type, &qualify);
+            }
+        }
+    }
+
+    let structs_start: usize = definitions.structs.len() - structs;
+    for definition in &mut definitions.structs[structs_start..] {
+        qualify(&mut definition.name);
+
+        for member in &mut definition.members {
+            qualify_value_type(&mut member.data_type, &qualify);
+        }
+    }
+
+    for declaration in &mut definitions.declaration_order[start..] {
+        match declaration {
+            Declaration::Comment(_) => (),
+            Declaration::Bitfield(name) | Declaration::Define(name) | Declaration::Enum(name) | Declaration::Message(name) | Declaration::Struct(name) => qualify(name)
+        }
+    }
+}
+
+fn qualify_value_type(value_type: &mut ValueType, qualify: &impl Fn(&mut String)) {
+    match value_type {
+        ValueType::Primitive(_) => (),
+        ValueType::UserDefined(name, _) => qualify(name),
+        ValueType::Array(array) => qualify_array(array, qualify)
+    }
+}
+
+fn qualify_array(array: &mut Array, qualify: &impl Fn(&mut String)) {
+    match &mut array.data_type {
+        ArrayType::Primitive(_) => (),
+        ArrayType::UserDefined(name, _) => qualify(name),
+        ArrayType::Array(inner_array) => qualify_array(inner_array, qualify)
+    }
+
+    if let ArraySize::UserDefinition(definition) = &mut array.element_count {
+        qualify(&mut definition.name);
+    }
+}
+
+fn qualify_expression(expression: &mut DefineExpression, qualify: &impl Fn(&mut String)) {
+    match expression {
+        DefineExpression::Literal(_) => (),
+        DefineExpression::Identifier(name) => qualify(name),
+        DefineExpression::Binary(left, _, right) => {
+            qualify_expression(left, qualify);
+            qualify_expression(right, qualify);
+        }
+    }
+}
+
+/// Parse tokens as they are scanned, such as straight from a `Scanner`. A scanning error is reported in place of any parsing error it caused
+pub fn parse_token_stream(tokens: impl Iterator<Item = Result<ItemType, ScanningError>>) -> ParsingResult<Definitions> {
+    let mut stream = TokenStream::new(tokens);
+    let result = parse_tokens(&mut stream);
+
+    match stream.take_error() {
+        Some(error) => Err(ParsingError::ScanningError(error)),
+        None => result
+    }
+}
+
 pub fn parse_tokens(tokens: &mut impl TokenSource) -> ParsingResult<Definitions> {
-    let mut definitions = Definitions::default();
+    let mut definitions = Definitions::with_capacity(DEFINITIONS_CAPACITY);
     let mut last_comment: Option<String> = None;
+    let mut last_annotations: Vec<Annotation> = Vec::new();
 
     let mut last_was_comment: bool = false;
 
+    // Names of the namespace blocks currently open, each with the number of declarations made before it was opened
+    let mut namespaces: Vec<(String, usize)> = Vec::new();
+
     'parsing: loop {
         let token = match tokens.peek() {
             None => break 'parsing,
             Some(token) => token
         };
 
+        // Namespaces only hold definitions, so statements about the file as a whole must be written outside of them
+        if let Some((namespace, _)) = namespaces.last() {
+            let is_version: bool = matches!(&token.item, Token::Identifier(string) if string.eq_ignore_ascii_case("version"));
+
+            if is_version || matches!(token.item, Token::Extend | Token::Include | Token::Redefine) {
+                error!("{0:?} cannot be used inside namespace {1}", token.item, namespace);
+                return Err(ParsingError::UnexpectedToken(token.clone()));
+            }
+        }
+
         match &token.item {
             Token::Comment(_) => (),
             _ => last_was_comment = false
         };
 
+        // Check that annotations are only followed by declarations that support them
+        if let Some(annotation) = last_annotations.first() {
+            let is_external: bool = matches!(&token.item, Token::Identifier(string) if string.eq_ignore_ascii_case("external"));
+
+            if !is_external && !matches!(token.item, Token::Annotation(_) | Token::Comment(_) | Token::Bitfield | Token::Enum | Token::Message | Token::Struct) {
+                error!("Annotation @{0} cannot be applied to {1:?}", annotation.name.item, token.item);
+                return Err(ParsingError::InvalidAnnotation(annotation.name.clone()));
+            }
+        }
+
         match &token.item {
-            Token::Bitfield => match parse_bitfield(tokens, &mut last_comment) {
-                Ok(definition) => definitions.bitfields.push(definition),
+            // Annotations separated by comments still belong to the same declaration
+            Token::Annotation(_) => {
+                for annotation in parse_annotations(tokens)? {
+                    add_annotation(&mut last_annotations, annotation)?;
+                }
+            },
+
+            Token::Bitfield => match parse_bitfield(tokens, &mut last_comment, std::mem::take(&mut last_annotations)) {
+                Ok(definition) => {
+                    definitions.declaration_order.push(Declaration::Bitfield(definition.name.clone()));
+                    definitions.bitfields.push(definition)
+                },
                 Err(error) => return Err(error)
             },
 
             Token::Comment(s) => {
                 if last_was_comment {
                     // Turn the last comment into a standalone comment
+                    definitions.declaration_order.push(Declaration::Comment(definitions.standalone_comments.len()));
                     definitions.standalone_comments.push(StandaloneCommentDefinition {
                         comment: match last_comment {
                             None => {
@@ -1097,8 +1897,8 @@ pub fn parse_tokens(tokens: &mut impl TokenSource) -> ParsingResult<Definitions>
                             },
                             Some(string) => string
                         },
-                        // Use index 0 for stray comments in Rune files for now
-                        index:   0
+                        // Stray comments in Rune files are indexed by their place among the top level declarations
+                        index:   definitions.declaration_order.len() - 1
                     });
                 }
 
@@ -1110,12 +1910,18 @@ pub fn parse_tokens(tokens: &mut impl TokenSource) -> ParsingResult<Definitions>
             },
 
             Token::Define => match parse_define(tokens, &mut last_comment) {
-                Ok(definition) => definitions.defines.push(definition),
+                Ok(definition) => {
+                    definitions.declaration_order.push(Declaration::Define(definition.name.clone()));
+                    definitions.defines.push(definition)
+                },
                 Err(error) => return Err(error)
             },
 
-            Token::Enum => match parse_enum(tokens, &mut last_comment) {
-                Ok(definition) => definitions.enums.push(definition),
+            Token::Enum => match parse_enum(tokens, &mut last_comment, std::mem::take(&mut last_annotations)) {
+                Ok(definition) => {
+                    definitions.declaration_order.push(Declaration::Enum(definition.name.clone()));
+                    definitions.enums.push(definition)
+                },
                 Err(error) => return Err(error)
             },
 
@@ -1134,19 +1940,487 @@ pub fn parse_tokens(tokens: &mut impl TokenSource) -> ParsingResult<Definitions>
                 Err(error) => return Err(error)
             },
 
-            Token::Message => match parse_message(tokens, &mut last_comment) {
-                Ok(definition) => definitions.messages.push(definition),
+            // Namespace is not a keyword, so it can still be used as an identifier inside declarations
+            Token::Identifier(string) if string.eq_ignore_ascii_case("namespace") => {
+                tokens.expect_next()?;
+                let name = tokens.expect_identifier()?.item;
+                tokens.expect_token(Token::LeftBrace)?;
+
+                namespaces.push((name, definitions.declaration_order.len()));
+            },
+
+            Token::RightBrace if !namespaces.is_empty() => {
+                tokens.expect_next()?;
+
+                if let Some((namespace, start)) = namespaces.pop() {
+                    qualify_namespace(&mut definitions, &namespace, start);
+                }
+            },
+
+            Token::Message => match parse_message(tokens, &mut last_comment, std::mem::take(&mut last_annotations)) {
+                Ok(definition) => {
+                    definitions.declaration_order.push(Declaration::Message(definition.name.clone()));
+                    definitions.messages.push(definition)
+                },
+                Err(error) => return Err(error)
+            },
+
+            Token::Struct => match parse_struct(tokens, &mut last_comment, std::mem::take(&mut last_annotations)) {
+                Ok(definition) => {
+                    definitions.declaration_order.push(Declaration::Struct(definition.name.clone()));
+                    definitions.structs.push(definition)
+                },
                 Err(error) => return Err(error)
             },
 
-            Token::Struct => match parse_struct(tokens, &mut last_comment) {
-                Ok(definition) => definitions.structs.push(definition),
+            // External is not a keyword, so it can still be used as an identifier inside declarations
+            Token::Identifier(string) if string.eq_ignore_ascii_case("external") => match parse_external(tokens, &mut last_comment, std::mem::take(&mut last_annotations)) {
+                Ok(definition) => {
+                    definitions.declaration_order.push(Declaration::Struct(definition.name.clone()));
+                    definitions.structs.push(definition)
+                },
                 Err(error) => return Err(error)
             },
 
+            // Version is not a keyword, so it can still be used as an identifier inside declarations
+            Token::Identifier(string) if string.eq_ignore_ascii_case("version") => {
+                let version_token = token.clone();
+
+                let version = parse_version(tokens)?;
+
+                if definitions.version.is_some() {
+                    error!("Schema version can only be declared once per file");
+                    return Err(ParsingError::MultipleVersionDeclarations(version_token));
+                }
+
+                definitions.version = Some(version);
+            },
+
             _ => return Err(ParsingError::UnexpectedToken(token.clone()))
         }
     }
 
+    if let Some(annotation) = last_annotations.into_iter().next() {
+        error!("Annotation @{0} is not followed by a declaration", annotation.name.item);
+        return Err(ParsingError::InvalidAnnotation(annotation.name));
+    }
+
+    if let Some((namespace, _)) = namespaces.last() {
+        error!("Namespace {0} is never closed", namespace);
+        return Err(ParsingError::UnexpectedEndOfInput);
+    }
+
+    // Leave out definitions introduced after the version declared by the file
+    if let Some(version) = definitions.version {
+        let mut gated_definitions: Vec<(String, u64)> = Vec::new();
+
+        let mut is_available = |name: &String, min_version: &Option<u64>| match min_version {
+            Some(min_version) if *min_version > version => {
+                info!("    Leaving out {0}, which requires version {1} while the file declares version {2}", name, min_version, version);
+                gated_definitions.push((name.clone(), *min_version));
+                false
+            },
+            _ => true
+        };
+
+        definitions.bitfields.retain(|definition| is_available(&definition.name, &definition.min_version));
+        definitions.enums.retain(|definition| is_available(&definition.name, &definition.min_version));
+        definitions.messages.retain(|definition| is_available(&definition.name, &definition.min_version));
+        definitions.structs.retain(|definition| is_available(&definition.name, &definition.min_version));
+
+        // Drop their declarations too, and move the standalone comments to their new place among the remaining declarations
+        definitions.declaration_order.retain(|declaration| match declaration {
+            Declaration::Bitfield(name) | Declaration::Enum(name) | Declaration::Message(name) | Declaration::Struct(name) => {
+                !gated_definitions.iter().any(|(gated_name, _)| gated_name == name)
+            },
+            Declaration::Comment(_) | Declaration::Define(_) => true
+        });
+
+        for (position, declaration) in definitions.declaration_order.iter().enumerate() {
+            if let Declaration::Comment(index) = declaration {
+                definitions.standalone_comments[*index].index = position;
+            }
+        }
+
+        definitions.gated_definitions = gated_definitions;
+    }
+
     Ok(definitions)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_str, test_utils::parse_files, RuneParserError};
+
+    fn parse(source: &str) -> ParsingResult<Definitions> {
+        parse_token_stream(Scanner::from_str(source).into_iter())
+    }
+
+    #[test]
+    fn include_without_extension_is_accepted() {
+        let definitions = parse("include \"common/types\";\n").unwrap();
+        assert_eq!(definitions.includes[0].file, "common/types");
+    }
+
+    #[test]
+    fn include_extension_is_stripped() {
+        let definitions = parse("include \"common/types.rune\";\n").unwrap();
+        assert_eq!(definitions.includes[0].file, "common/types");
+    }
+
+    #[test]
+    fn include_with_wrong_extension_is_rejected() {
+        match parse("include \"types.rune.bak\";\n") {
+            Err(ParsingError::InvalidFilePath(path)) => assert_eq!(path.item, "types.rune.bak"),
+            result => panic!("Expected an invalid file path, got {0:?}", result)
+        }
+    }
+
+    #[test]
+    fn include_without_name_is_rejected() {
+        assert!(matches!(parse("include \".rune\";\n"), Err(ParsingError::InvalidFilePath(_))));
+    }
+
+    #[test]
+    fn message_extension_is_parsed() {
+        let definitions = parse("extend message Packet {\n    b: u8 = 2;\n}\n").unwrap();
+
+        assert!(!definitions.extensions.is_empty());
+        assert_eq!(definitions.extensions.messages[0].name, "Packet");
+        assert_eq!(definitions.extensions.messages[0].fields.len(), 1);
+    }
+
+    #[test]
+    fn group_annotation_is_parsed() {
+        let definitions = parse("@group(\"commands\")\nmessage Start {\n    a: u8 = 1;\n}\n\nstruct Loose {\n    a: u8 = 0;\n}\n").unwrap();
+
+        assert_eq!(definitions.find_message("Start").unwrap().group.as_deref(), Some("commands"));
+        assert_eq!(definitions.find_struct("Loose").unwrap().group, None);
+    }
+
+    #[test]
+    fn group_annotation_requires_a_string() {
+        assert!(matches!(parse("@group(3)\nstruct Loose {\n    a: u8 = 0;\n}\n"), Err(ParsingError::UnexpectedToken(_))));
+    }
+
+    #[test]
+    fn annotation_at_end_of_file_is_rejected() {
+        assert!(matches!(parse("struct A {\n    a: u8 = 0;\n}\n\n@group(\"x\")\n"), Err(ParsingError::InvalidAnnotation(_))));
+    }
+
+    #[test]
+    fn annotation_arguments_need_single_commas() {
+        for arguments in ["(,\"x\")", "(\"x\",)", "(\"x\",,\"y\")", "(,,\"x\",)", "(\"x\" \"y\")"] {
+            let result = parse(&format!("@group{0}\nstruct Loose {{\n    a: u8 = 0;\n}}\n", arguments));
+            assert!(matches!(result, Err(ParsingError::UnexpectedToken(_))), "{0} should be rejected", arguments);
+        }
+    }
+
+    #[test]
+    fn repeated_annotation_is_rejected() {
+        assert!(matches!(parse("@group(\"a\") @group(\"b\")\nstruct Loose {\n    a: u8 = 0;\n}\n"), Err(ParsingError::InvalidAnnotation(_))));
+        assert!(matches!(parse("@group(\"a\")\n// Comment\n@group(\"b\")\nstruct Loose {\n    a: u8 = 0;\n}\n"), Err(ParsingError::InvalidAnnotation(_))));
+        assert!(matches!(parse("struct Device {\n    @cstring @cstring\n    name: [char; 32] = 0;\n}\n"), Err(ParsingError::InvalidAnnotation(_))));
+    }
+
+    #[test]
+    fn cstring_annotation_marks_char_arrays() {
+        let definitions = parse("struct Device {\n    @cstring\n    name: [char; 32] = 0;\n    raw: [char; 4] = 1;\n}\n\nmessage Hello {\n    @cstring\n    name: [char; 16] = 1;\n}\n").unwrap();
+
+        let device = definitions.find_struct("Device").unwrap();
+        assert!(device.members[0].cstring);
+        assert!(!device.members[1].cstring);
+        assert!(definitions.find_message("Hello").unwrap().fields[0].cstring);
+    }
+
+    #[test]
+    fn cstring_annotation_is_rejected_on_other_types() {
+        assert!(matches!(parse("struct Device {\n    @cstring\n    id: u32 = 0;\n}\n"), Err(ParsingError::InvalidAnnotation(_))));
+        assert!(matches!(parse("message Hello {\n    @cstring\n    ids: [u8; 4] = 1;\n}\n"), Err(ParsingError::InvalidAnnotation(_))));
+    }
+
+    fn bitfield_with_member(member: &str) -> ParsingResult<Definitions> {
+        parse(&format!("bitfield Flags: u16 {{\n    {0} = 0;\n}}\n", member))
+    }
+
+    #[test]
+    fn bitfield_member_sizes_are_parsed() {
+        let size = |member: &str| bitfield_with_member(member).unwrap().find_bitfield("Flags").unwrap().members[0].size.clone();
+
+        assert!(matches!(size("mode: u3"), BitSize::Unsigned(3)));
+        assert!(matches!(size("offset: i12"), BitSize::Signed(12)));
+    }
+
+    #[test]
+    fn float_bitfield_member_is_rejected() {
+        match bitfield_with_member("x: f32") {
+            Err(ParsingError::InvalidBitfieldSize(size)) => assert_eq!(size.item, "f32"),
+            result => panic!("Expected an invalid bitfield size, got {0:?}", result)
+        }
+    }
+
+    #[test]
+    fn malformed_bitfield_member_sizes_are_rejected() {
+        for member in ["x: u0", "x: u", "x: U3", "x: u3x"] {
+            assert!(matches!(bitfield_with_member(member), Err(ParsingError::InvalidBitfieldSize(_))), "{0} was accepted", member);
+        }
+    }
+
+    #[test]
+    fn external_struct_is_parsed_with_its_size() {
+        let definitions = parse("@size(8)\nexternal struct PlatformTime;\n\nstruct Event {\n    time: PlatformTime = 0;\n}\n").unwrap();
+        assert_eq!(definitions.find_struct("PlatformTime").unwrap().external_size, Some(8));
+    }
+
+    #[test]
+    fn external_ignores_case() {
+        let definitions = parse("@size(4)\nEXTERNAL struct PlatformTime;\n").unwrap();
+        assert_eq!(definitions.find_struct("PlatformTime").unwrap().external_size, Some(4));
+    }
+
+    #[test]
+    fn external_struct_without_size_is_rejected() {
+        for source in ["external struct PlatformTime;\n", "@size(0)\nexternal struct PlatformTime;\n"] {
+            match parse(source) {
+                Err(ParsingError::MissingExternalSize(name)) => assert_eq!(name.item, "PlatformTime"),
+                result => panic!("Expected a missing external size, got {0:?}", result)
+            }
+        }
+    }
+
+    #[test]
+    fn c_attribute_is_kept_verbatim() {
+        let definitions = parse("@c_attribute(\"section(\\\".fast\\\")\")\n@c_attribute(\"aligned(8)\")\nstruct Fast {\n    a: u8 = 0;\n}\n").unwrap();
+        assert_eq!(definitions.find_struct("Fast").unwrap().c_attributes, vec![String::from("section(\".fast\")"), String::from("aligned(8)")]);
+    }
+
+    #[test]
+    fn empty_c_attribute_is_rejected() {
+        assert!(matches!(parse("@c_attribute(\" \")\nbitfield Flags: u8 {\n    a: u1 = 0;\n}\n"), Err(ParsingError::InvalidAnnotation(_))));
+    }
+
+    #[test]
+    fn keyword_as_field_name_is_reported_with_its_position() {
+        let source = "struct Device {\n    struct: u8 = 0;\n}\n";
+
+        let error = parse(source).unwrap_err();
+
+        assert!(matches!(&error, ParsingError::ReservedKeyword(token) if token.from.line == 2));
+        assert!(error.render_diagnostic(source).starts_with("Reserved word 'struct' used as a name"));
+    }
+
+    #[test]
+    fn diagnostic_marks_the_offending_token() {
+        let source = "struct Device {\n    id: u8 = 0\n    name: u8 = 1;\n}\n";
+        let rendered = parse(source).unwrap_err().render_diagnostic(source);
+
+        // The missing semicolon is found at the next member name
+        assert_eq!(rendered, "Unexpected token Identifier(\"name\")\n --> line 3, column 5\n  |\n3 |     name: u8 = 1;\n  |     ^^^^");
+    }
+
+    #[test]
+    fn scanning_diagnostic_marks_the_offending_character() {
+        let source = "struct Device {\n    id: u8 = 0; $\n}\n";
+        let error = Scanner::from_str(source).scan_all().unwrap_err();
+
+        assert!(error.render_diagnostic(source).ends_with("2 |     id: u8 = 0; $\n  |                 ^"));
+    }
+
+    #[test]
+    fn default_value_follows_the_index() {
+        let definitions = parse("message Config {\n    retries: u8 = 1 default 3;\n    timeout: u16 = 2 DEFAULT 500;\n}\n").unwrap();
+        let fields = &definitions.find_message("Config").unwrap().fields;
+
+        assert!(matches!(&fields[0].default, Some(DefineValue::NumericLiteral(NumericLiteral::PositiveInteger(3, _)))));
+        assert!(matches!(&fields[1].default, Some(DefineValue::NumericLiteral(NumericLiteral::PositiveInteger(500, _)))));
+    }
+
+    #[test]
+    fn out_of_range_default_value_is_rejected() {
+        assert!(matches!(parse("struct Config {\n    retries: u8 = 0 default 256;\n}\n"), Err(ParsingError::InvalidDefaultValue(_))));
+    }
+
+    #[test]
+    fn default_can_be_used_as_a_name() {
+        let source = "enum Mode: u8 {\n    Default = 0;\n}\n\nstruct Default {\n    default: u8 = 0;\n    DEFAULT: u8 = 1 default 2;\n}\n";
+        let definitions = parse(source).unwrap();
+
+        assert_eq!(definitions.find_enum("Mode").unwrap().members[0].identifier, "Default");
+
+        let members = &definitions.find_struct("Default").unwrap().members;
+        assert_eq!(members[0].identifier, "default");
+        assert_eq!(members[1].identifier, "DEFAULT");
+        assert!(matches!(&members[1].default, Some(DefineValue::NumericLiteral(NumericLiteral::PositiveInteger(2, _)))));
+    }
+
+    #[test]
+    fn reserved_range_crossing_zero_is_expanded_by_sign() {
+        let definitions = parse("enum Offset: i8 {\n    Far = 5;\n    reserve -2..3;\n}\n").unwrap();
+        let reserved: Vec<String> = definitions.find_enum("Offset").unwrap().reserved_values.iter().map(NumericLiteral::to_string).collect();
+
+        assert_eq!(reserved, ["-2", "-1", "0", "1", "2"]);
+        assert!(matches!(definitions.find_enum("Offset").unwrap().reserved_values[2], NumericLiteral::PositiveInteger(0, _)));
+    }
+
+    #[test]
+    fn reserved_range_crossing_zero_is_rejected_in_structs() {
+        assert!(matches!(parse("struct Offsets {\n    a: u8 = 5;\n    reserve -2..3;\n}\n"), Err(ParsingError::UnexpectedToken(_))));
+    }
+
+    #[test]
+    fn deprecate_and_reserve_populate_different_lists() {
+        let deprecated = parse("message Packet {\n    a: u8 = 1;\n    deprecate 3;\n}\n").unwrap();
+        let reserved = parse("message Packet {\n    a: u8 = 1;\n    reserve 3;\n}\n").unwrap();
+
+        let deprecated = deprecated.find_message("Packet").unwrap();
+        assert_eq!(deprecated.deprecated_indexes, [FieldIndex::Numeric(3)]);
+        assert!(deprecated.reserved_indexes.is_empty());
+
+        let reserved = reserved.find_message("Packet").unwrap();
+        assert_eq!(reserved.reserved_indexes, [FieldIndex::Numeric(3)]);
+        assert!(reserved.deprecated_indexes.is_empty());
+    }
+
+    #[test]
+    fn deprecate_and_reserve_both_block_the_index() {
+        for statement in ["deprecate 3;", "reserve 3;"] {
+            let result = parse_str(&format!("message Packet {{\n    a: u8 = 3;\n    {0}\n}}\n", statement), "packet.rune");
+            assert!(matches!(result, Err(RuneParserError::UseOfReservedIndex)), "{0} did not block index 3", statement);
+        }
+    }
+
+    #[test]
+    fn tokens_are_scanned_as_they_are_requested() {
+        let source = "struct Point {\n    x: i16 = 0;\n}\n";
+        let read = std::cell::Cell::new(0);
+        let mut tokens = Scanner::new(source.chars().inspect(|_| read.set(read.get() + 1))).into_iter();
+
+        assert_eq!(tokens.next().unwrap().unwrap().item, Token::Struct);
+        // Only the keyword and the character ending it have been read
+        assert_eq!(read.get(), "struct ".len());
+    }
+
+    #[test]
+    fn streamed_tokens_parse_like_buffered_ones() {
+        let source = "enum Mode: u8 {\n    Off = 0;\n}\n\nstruct Point {\n    x: i16 = 0;\n    mode: Mode = 1;\n}\n";
+
+        let streamed = parse_token_stream(Scanner::from_str(source).into_iter()).unwrap();
+        let buffered = parse_token_stream(Scanner::from_str(source).scan_all().unwrap().into_iter().map(Ok)).unwrap();
+
+        assert_eq!(streamed.all_type_names(), buffered.all_type_names());
+        assert_eq!(streamed.find_struct("Point").unwrap().members.len(), 2);
+    }
+
+    #[test]
+    fn scanning_error_in_the_stream_stops_parsing() {
+        assert!(matches!(parse("struct Point {\n    x: i16 = 0; $\n}\n"), Err(ParsingError::ScanningError(ScanningError::UnexpectedCharacter(_)))));
+    }
+
+    #[test]
+    fn array_size_must_be_a_positive_integer() {
+        for size in ["-1", "2.5", "true"] {
+            let result = parse(&format!("struct Buffer {{\n    data: [u8; {0}] = 0;\n}}\n", size));
+            assert!(matches!(result, Err(ParsingError::InvalidArraySize(_))), "[u8; {0}] gave {1:?}", size, result);
+        }
+    }
+
+    const NAMESPACES: &str = "namespace Sensor {\n    struct Header {\n        id: u8 = 0;\n    }\n\n    message Reading {\n        header: Header = 1;\n    }\n}\n\n\
+                              namespace Motor {\n    struct Header {\n        id: u16 = 0;\n    }\n}\n\nmessage Status {\n    motor: Motor::Header = 1;\n}\n";
+
+    #[test]
+    fn namespaces_qualify_their_definitions_and_references() {
+        let definitions = parse(NAMESPACES).unwrap();
+
+        assert!(definitions.find_struct("Sensor::Header").is_some());
+        assert!(definitions.find_struct("Motor::Header").is_some());
+        assert!(definitions.find_struct("Header").is_none());
+
+        // References inside a namespace point at its own definitions, while those outside are written qualified
+        let reading = definitions.find_message("Sensor::Reading").unwrap();
+        assert!(matches!(reading.fields[0].data_type.value_type(), Some(ValueType::UserDefined(name, _)) if name == "Sensor::Header"));
+
+        let status = definitions.find_message("Status").unwrap();
+        assert!(matches!(status.fields[0].data_type.value_type(), Some(ValueType::UserDefined(name, _)) if name == "Motor::Header"));
+    }
+
+    #[test]
+    fn same_name_in_different_namespaces_does_not_collide() {
+        assert!(parse_str(NAMESPACES, "namespaces.rune").is_ok());
+    }
+
+    #[test]
+    fn same_name_in_one_namespace_collides() {
+        let source = "namespace Sensor {\n    struct Header {\n        id: u8 = 0;\n    }\n\n    enum Header: u8 {\n        A = 0;\n    }\n}\n";
+        assert!(matches!(parse_str(source, "namespaces.rune"), Err(RuneParserError::NameCollision)));
+    }
+
+    #[test]
+    fn qualified_reference_links_across_files() {
+        let files = parse_files(&[("namespaces.rune", NAMESPACES), ("user.rune", "include \"namespaces\";\n\nstruct Pair {\n    sensor: Sensor::Header = 0;\n}\n")]).unwrap();
+        let user = files.iter().find(|file| file.name == "user").unwrap();
+
+        match &user.definitions.find_struct("Pair").unwrap().members[0].data_type {
+            ValueType::UserDefined(name, UserDefinitionLink::StructLink(definition)) => {
+                assert_eq!(name, "Sensor::Header");
+                assert_eq!(definition.name, "Sensor::Header");
+            },
+            data_type => panic!("Sensor::Header was not linked, got {0:?}", data_type)
+        }
+    }
+
+    #[test]
+    fn namespace_ignores_case() {
+        let definitions = parse("NAMESPACE Foo {\n    struct A {\n        a: u8 = 0;\n    }\n}\n").unwrap();
+        assert!(definitions.find_struct("Foo::A").is_some());
+    }
+
+    #[test]
+    fn namespace_can_be_used_as_a_name() {
+        let definitions = parse("struct A {\n    namespace: u8 = 0;\n}\n\nenum Kind: u8 {\n    namespace = 0;\n}\n").unwrap();
+
+        assert_eq!(definitions.find_struct("A").unwrap().members[0].identifier, "namespace");
+        assert_eq!(definitions.find_enum("Kind").unwrap().members[0].identifier, "namespace");
+    }
+
+    const GATED: &str = "// Comment about the new message\n\n@min_version(3)\nmessage NewThing { a: u8 = 1; }\n\n// Comment about the old struct\n\nstruct Old { a: u8 = 0; }\n";
+
+    #[test]
+    fn definition_is_excluded_before_its_min_version() {
+        let definitions = parse(&format!("version 2;\n{0}", GATED)).unwrap();
+
+        assert!(definitions.find_message("NewThing").is_none());
+        assert_eq!(definitions.gated_definitions, vec![(String::from("NewThing"), 3)]);
+        assert!(!definitions.declaration_order.contains(&Declaration::Message(String::from("NewThing"))));
+
+        // Standalone comments keep pointing at their place among the remaining declarations
+        for comment in &definitions.standalone_comments {
+            assert!(matches!(definitions.declaration_order[comment.index], Declaration::Comment(_)));
+        }
+    }
+
+    #[test]
+    fn definition_is_included_from_its_min_version() {
+        let definitions = parse(&format!("version 3;\n{0}", GATED)).unwrap();
+
+        assert!(definitions.find_message("NewThing").is_some());
+        assert!(definitions.gated_definitions.is_empty());
+        assert!(definitions.declaration_order.contains(&Declaration::Message(String::from("NewThing"))));
+    }
+
+    #[test]
+    fn version_ignores_case() {
+        assert_eq!(parse("VERSION 5;\n").unwrap().version, Some(5));
+        assert_eq!(parse("Version 5;\n").unwrap().version, Some(5));
+    }
+
+    #[test]
+    fn bytes_are_only_a_message_field_type() {
+        assert!(parse("message Frame {\n    data: bytes(16) = 1;\n}\n").is_ok());
+
+        for source in ["struct Frame {\n    data: bytes = 0;\n}\n", "message Frame {\n    data: [bytes; 4] = 1;\n}\n"] {
+            assert!(matches!(parse(source), Err(ParsingError::InvalidTypeUse(_))), "{0} was accepted", source);
+            assert!(matches!(parse_str(source, "frame.rune"), Err(RuneParserError::InvalidTypeUse)));
+        }
+    }
+}
@@ -1,7 +1,11 @@
 pub mod process_defines;
 pub mod process_extensions;
+pub mod process_includes;
 pub mod process_user_definitions;
+pub mod sort_definitions;
 
 pub use process_defines::parse_define_statements;
 pub use process_extensions::parse_extensions;
-pub use process_user_definitions::link_user_definitions;
+pub use process_includes::{check_include_cycles, deduplicate_includes, include_scopes, resolve_includes};
+pub use process_user_definitions::{link_user_definitions, DEFAULT_MAX_NESTING_DEPTH};
+pub use sort_definitions::sort_definitions;
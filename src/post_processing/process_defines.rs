@@ -1,118 +1,363 @@
 use crate::{
     output::*,
-    scanner::NumericLiteral,
-    types::{DefineDefinition, DefineValue, FieldType, RedefineDefinition},
-    ArraySize, RuneFileDescription, RuneParserError
+    post_processing::include_scopes,
+    scanner::{NumeralSystem, NumericLiteral},
+    types::{Array, DefineDefinition, DefineExpression, DefineOperator, DefineValue, FieldType, RedefineDefinition, ValueType},
+    ArraySize, ArrayType, RuneFileDescription, RuneParserError, Warning
 };
 
-const VEC_SIZE: usize = 0x40;
-
-pub fn parse_define_statements(definitions: &mut Vec<RuneFileDescription>) -> Result<(), RuneParserError> {
+pub fn parse_define_statements(definitions: &mut [RuneFileDescription], warnings: &mut Vec<Warning>) -> Result<(), RuneParserError> {
     info!("Parsing define statements");
 
-    let mut defines_list: Vec<DefineDefinition> = Vec::with_capacity(VEC_SIZE);
-    let mut redefines_list: Vec<RedefineDefinition> = Vec::with_capacity(VEC_SIZE);
-
-    // Create a list of all user defines found across all files
-    for file in definitions.clone() {
-        for definition in &file.definitions.defines {
-            defines_list.push(definition.clone());
-        }
-
-        for redefinition in &file.definitions.redefines {
-            redefines_list.push(redefinition.clone());
-        }
-    }
+    // A file only sees its own defines, and those of the files it includes directly or through other files
+    let scopes: Vec<Vec<usize>> = include_scopes(definitions);
 
     // Check for duplicates
     // —————————————————————
 
-    // Check for multiple definitions of the same define. Only necessary if more than one item in the list
-    if defines_list.len() > 1 {
-        for i in 0..(defines_list.len() - 1) {
-            for definition in &defines_list[(i + 1)..] {
-                if defines_list[i].name == definition.name {
-                    error!("Found duplicate definition of {0}. Aborting parsing.", defines_list[i].name);
-                    return Err(RuneParserError::MultipleDefinitions);
+    // Check for multiple definitions of the same define among the defines visible from a single file
+    for (index, scope) in scopes.iter().enumerate() {
+        let mut visible_defines: Vec<(&str, usize)> = Vec::new();
+
+        for &file_index in scope {
+            for definition in &definitions[file_index].definitions.defines {
+                match visible_defines.iter().find(|(name, _)| *name == definition.name) {
+                    None => visible_defines.push((&definition.name, file_index)),
+                    Some(&(_, other_index)) if other_index == file_index => {
+                        error!("Found duplicate definition of {0} in {1}.rune. Aborting parsing.", definition.name, definitions[file_index].include_path());
+                        return Err(RuneParserError::MultipleDefinitions);
+                    },
+                    Some(&(_, other_index)) => {
+                        error!(
+                            "Found duplicate definition of {0} in {1}.rune and {2}.rune, which are both visible from {3}.rune. Aborting parsing.",
+                            definition.name,
+                            definitions[other_index].include_path(),
+                            definitions[file_index].include_path(),
+                            definitions[index].include_path()
+                        );
+                        return Err(RuneParserError::MultipleDefinitions);
+                    }
                 }
             }
         }
     }
 
-    // Check for multiple definitions of the same redefine. Only necessary if more than one item in the list
-    if redefines_list.len() > 1 {
-        for i in 0..(redefines_list.len() - 1) {
-            for redefinition in &redefines_list[(i + 1)..] {
-                if redefines_list[i].name == redefinition.name {
-                    error!("Multiple redefinitions of {0}! Only a single redefinition of a define is supported.", redefines_list[i].name);
+    // Process files
+    // ——————————————
+
+    // A redefinition overwrites the define of the same name visible from the file declaring it. Find the one redefinition of every define
+    let mut attachments: Vec<(usize, usize, RedefineDefinition)> = Vec::new();
+    let mut attached: Vec<Vec<bool>> = definitions.iter().map(|file| vec![false; file.definitions.redefines.len()]).collect();
+
+    for (file_index, file) in definitions.iter().enumerate() {
+        for (define_index, define_definition) in file.definitions.defines.iter().enumerate() {
+            let mut redefinitions = scopes
+                .iter()
+                .enumerate()
+                .filter(|(_, scope)| scope.contains(&file_index))
+                .flat_map(|(redefining_index, _)| definitions[redefining_index].definitions.redefines.iter().enumerate().map(move |(i, redefinition)| (redefining_index, i, redefinition)))
+                .filter(|(_, _, redefinition)| redefinition.name == define_definition.name);
+
+            if let Some((redefining_index, i, redefinition)) = redefinitions.next() {
+                if redefinitions.next().is_some() {
+                    error!("Multiple redefinitions of {0}! Only a single redefinition of a define is supported.", define_definition.name);
                     return Err(RuneParserError::MultipleRedefinitions);
                 }
+
+                attached[redefining_index][i] = true;
+                attachments.push((file_index, define_index, redefinition.clone()));
             }
         }
     }
 
-    // Process files
-    // ——————————————
+    // Attach every redefinition to the define it overwrites
+    for (file_index, define_index, redefinition) in attachments {
+        definitions[file_index].definitions.defines[define_index].redefinition = Some(redefinition);
+    }
 
-    for file in definitions {
-        // Find all definitions in the file, and check to see if there is any redefinition for it
-        for define_definition in &mut file.definitions.defines {
-            for i in 0..redefines_list.len() {
-                // Check to see if names match
-                if define_definition.name == redefines_list[i].name {
-                    // Add redefinition to the define
-                    define_definition.redefinition = Some(redefines_list[i].clone());
-
-                    // Remove from redefines_list so we can check for orphan redefinitions after processing al files
-                    redefines_list.swap_remove(i);
-                }
-            }
+    for (file, attached) in definitions.iter().zip(&attached) {
+        for (redefinition, _) in file.definitions.redefines.iter().zip(attached).filter(|(_, &attached)| !attached) {
+            let warning = Warning::OrphanRedefinition(redefinition.name.clone());
+            warning!("{0}", warning);
+            warnings.push(warning);
         }
+    }
+
+    // Collect the defines visible from every file, now that their redefinitions are attached
+    let scoped_defines: Vec<Vec<DefineDefinition>> = scopes
+        .iter()
+        .map(|scope| scope.iter().flat_map(|&file_index| definitions[file_index].definitions.defines.iter().cloned()).collect())
+        .collect();
+
+    // Check that every alias and expression evaluates to a value, even for defines not used inside Rune
+    for (file, defines_list) in definitions.iter().zip(&scoped_defines) {
+        for define_definition in &file.definitions.defines {
+            resolve_define_value(define_definition, defines_list)?;
+        }
+    }
+
+    for (file, defines_list) in definitions.iter_mut().zip(&scoped_defines) {
+        let include_path: String = file.include_path();
 
         // So far, array sizes are the only valid place to use define values inside Rune itself
-        // Check all message fields for array members, and check if their size is defined by a UserDefinition
-        for message_definition in &mut file.definitions.messages {
-            // Check all message members
+        // Check all message fields and struct members for array members, and check if their size is defined by a UserDefinition. Extensions
+        // are merged after defines are resolved, so their bodies are resolved with the defines visible from the file declaring them
+        for message_definition in file.definitions.messages.iter_mut().chain(&mut file.definitions.extensions.messages) {
             for field in &mut message_definition.fields {
-                // Check if field type is array
-                if let FieldType::Array(array) = &mut field.data_type {
-                    // Check to see if the array size is a user defined value
-                    if let ArraySize::UserDefinition(definition) = &mut array.element_count {
-                        // Find define value
-                        for user_define in &defines_list {
-                            // Match with identifier string
-                            if user_define.name == definition.name {
-                                // Check for redefinition
-                                let define_value: &DefineValue = match &user_define.redefinition {
-                                    None => &user_define.value,
-                                    Some(redefine) => &redefine.value
-                                };
-
-                                // Parse the value. Only integer values are valid
-                                match define_value {
-                                    DefineValue::NumericLiteral(value) => match value {
-                                        NumericLiteral::PositiveInteger(_, _) => definition.value = DefineValue::NumericLiteral(value.clone()),
-                                        _ => {
-                                            error!("Could not parse {0} into a valid positive integer value!", definition.name);
-                                            return Err(RuneParserError::InvalidNumericValue);
-                                        }
-                                    },
-                                    _ => {
-                                        error!("Could not parse {0} into a valid positive integer value!", definition.name);
-                                        return Err(RuneParserError::InvalidNumericValue);
-                                    }
-                                }
-                            }
-                        }
-                    }
+                if let FieldType::Value(value_type) = &mut field.data_type {
+                    resolve_array_size(value_type, defines_list, (&message_definition.name, &field.identifier, &include_path))?;
                 }
             }
         }
+
+        for struct_definition in file.definitions.structs.iter_mut().chain(&mut file.definitions.extensions.structs) {
+            for member in &mut struct_definition.members {
+                resolve_array_size(&mut member.data_type, defines_list, (&struct_definition.name, &member.identifier, &include_path))?;
+            }
+        }
     }
 
-    for orphan_redefinition in redefines_list {
-        warning!("Define statement for redefinition {0} not found, so it will thus be ignored and do nothing.", orphan_redefinition.name);
+    Ok(())
+}
+
+/// Get the numeric value of a define, following aliases and evaluating expressions. References to unknown defines, or chains of references
+/// leading back to the define itself, are errors
+fn resolve_define_value(definition: &DefineDefinition, defines_list: &[DefineDefinition]) -> Result<NumericLiteral, RuneParserError> {
+    evaluate_define(definition, defines_list, &mut Vec::new())
+}
+
+/// Evaluate a define, with the chain holding the names of the defines currently being evaluated
+fn evaluate_define<'a>(definition: &'a DefineDefinition, defines_list: &'a [DefineDefinition], chain: &mut Vec<&'a str>) -> Result<NumericLiteral, RuneParserError> {
+    if let Some(start) = chain.iter().position(|&name| name == definition.name) {
+        error!("Define {0} refers back to itself through {1} -> {0}", definition.name, chain[start..].join(" -> "));
+        return Err(RuneParserError::CircularDefinition);
+    }
+
+    chain.push(definition.name.as_str());
+
+    let value: NumericLiteral = match definition.effective_value() {
+        DefineValue::NoValue => {
+            error!("Define {0} has no value!", definition.name);
+            return Err(RuneParserError::InvalidNumericValue);
+        },
+        DefineValue::NumericLiteral(value) => value.clone(),
+        DefineValue::Alias(alias) => evaluate_define(find_define(&definition.name, alias, defines_list)?, defines_list, chain)?,
+        DefineValue::Expression(expression) => evaluate_expression(&definition.name, expression, defines_list, chain)?,
+        DefineValue::StringLiteral(_) => {
+            error!("Define {0} has a text value! Only numeric values are supported", definition.name);
+            return Err(RuneParserError::InvalidNumericValue);
+        }
+    };
+
+    chain.pop();
+
+    Ok(value)
+}
+
+fn evaluate_expression<'a>(
+    define_name: &str,
+    expression: &'a DefineExpression,
+    defines_list: &'a [DefineDefinition],
+    chain: &mut Vec<&'a str>
+) -> Result<NumericLiteral, RuneParserError> {
+    let (left, operator, right) = match expression {
+        DefineExpression::Literal(value) => return Ok(value.clone()),
+        DefineExpression::Identifier(name) => return evaluate_define(find_define(define_name, name, defines_list)?, defines_list, chain),
+        DefineExpression::Binary(left, operator, right) => (left, operator, right)
+    };
+
+    // Arithmetic is done on integers only, wide enough to hold both positive and negative 64 bit values
+    let mut operands: [i128; 2] = [0; 2];
+    for (operand, side) in operands.iter_mut().zip([left, right]) {
+        *operand = match evaluate_expression(define_name, side, defines_list, chain)? {
+            NumericLiteral::PositiveInteger(value, _) => value as i128,
+            NumericLiteral::NegativeInteger(value, _) => value as i128,
+            value => {
+                error!("Define {0} uses the non integer value {1} in an arithmetic expression! Only integers are supported", define_name, value);
+                return Err(RuneParserError::InvalidNumericValue);
+            }
+        };
+    }
+
+    let [left, right] = operands;
+
+    let result: Option<i128> = match operator {
+        DefineOperator::Add => left.checked_add(right),
+        DefineOperator::Subtract => left.checked_sub(right),
+        DefineOperator::Multiply => left.checked_mul(right),
+        DefineOperator::Divide => {
+            if right == 0 {
+                error!("Define {0} divides by zero!", define_name);
+                return Err(RuneParserError::InvalidNumericValue);
+            }
+            left.checked_div(right)
+        }
+    };
+
+    // Results are written in decimal, as the operands may have been written in different numeral systems
+    match result {
+        Some(value) if value >= 0 && value <= u64::MAX as i128 => Ok(NumericLiteral::PositiveInteger(value as u64, NumeralSystem::Decimal)),
+        Some(value) if value < 0 && value >= i64::MIN as i128 => Ok(NumericLiteral::NegativeInteger(value as i64, NumeralSystem::Decimal)),
+        _ => {
+            error!("Value of define {0} overflows a 64 bit integer!", define_name);
+            Err(RuneParserError::InvalidNumericValue)
+        }
+    }
+}
+
+/// Find a define referenced by another define
+fn find_define<'a>(referencing_name: &str, name: &str, defines_list: &'a [DefineDefinition]) -> Result<&'a DefineDefinition, RuneParserError> {
+    match defines_list.iter().find(|user_define| user_define.name == name) {
+        None => {
+            error!("Define {0} refers to {1}, which is not defined!", referencing_name, name);
+            Err(RuneParserError::UndefinedIdentifier)
+        },
+        Some(user_define) => Ok(user_define)
+    }
+}
+
+/// Parent definition, identifier, and include path of the field or member an array belongs to, used to point at it in error messages
+type ArrayLocation<'a> = (&'a str, &'a str, &'a str);
+
+/// Populate the value of an array size if it is defined by a UserDefinition
+fn resolve_array_size(value_type: &mut ValueType, defines_list: &[DefineDefinition], location: ArrayLocation) -> Result<(), RuneParserError> {
+    // Check if data type is array
+    if let ValueType::Array(array) = value_type {
+        resolve_array_dimensions(array, defines_list, location)?;
     }
 
     Ok(())
 }
+
+/// Populate the sizes of an array and of every array nested inside it
+fn resolve_array_dimensions(array: &mut Array, defines_list: &[DefineDefinition], location: ArrayLocation) -> Result<(), RuneParserError> {
+    if let ArrayType::Array(inner_array) = &mut array.data_type {
+        resolve_array_dimensions(inner_array, defines_list, location)?;
+    }
+
+    // Check to see if the array size is a user defined value
+    if let ArraySize::UserDefinition(definition) = &mut array.element_count {
+        // Find define value
+        for user_define in defines_list {
+            // Match with identifier string
+            if user_define.name == definition.name {
+                // Follow redefinitions, aliases, and expressions to the final value. Only positive integer values are valid
+                match resolve_define_value(user_define, defines_list)? {
+                    value @ NumericLiteral::PositiveInteger(_, _) => definition.value = DefineValue::NumericLiteral(value),
+                    value => {
+                        let (parent, identifier, include_path) = location;
+                        error!(
+                            "Array size {0} of {1}.{2} in {3}.rune resolves to {4}, but array sizes must be positive integers!",
+                            definition.name, parent, identifier, include_path, value
+                        );
+                        return Err(RuneParserError::InvalidNumericValue);
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        test_utils::{parse_files, parse_files_with_warnings},
+        types::ValueType,
+        RuneParserError,
+        Warning
+    };
+
+    /// Size of an array of bytes whose element count is the define SIZE, declared along with the given defines
+    fn size_of(defines: &str) -> Result<u64, RuneParserError> {
+        let files = parse_files(&[("sizes.rune", &format!("{0}\n\nstruct Buffer {{\n    data: [u8; SIZE] = 0;\n}}\n", defines))])?;
+        files[0].definitions.find_struct("Buffer").unwrap().flat_size()
+    }
+
+    #[test]
+    fn two_level_alias_resolves_to_its_value() {
+        assert_eq!(size_of("define BASE 12;\ndefine MIDDLE BASE;\ndefine SIZE MIDDLE;").unwrap(), 12);
+    }
+
+    #[test]
+    fn circular_alias_is_rejected() {
+        assert!(matches!(size_of("define SIZE OTHER;\ndefine OTHER SIZE;"), Err(RuneParserError::CircularDefinition)));
+    }
+
+    #[test]
+    fn multiplication_binds_tighter_than_addition() {
+        assert_eq!(size_of("define SIZE (2 + 3 * 4);").unwrap(), 14);
+        assert_eq!(size_of("define SIZE (20 - 8 / 2 - 1);").unwrap(), 15);
+    }
+
+    #[test]
+    fn parentheses_group_expressions() {
+        assert_eq!(size_of("define SIZE ((2 + 3) * 4);").unwrap(), 20);
+        assert_eq!(size_of("define HEADER 4;\ndefine PAYLOAD 12;\ndefine SIZE ((HEADER + PAYLOAD) / 2);").unwrap(), 8);
+    }
+
+    #[test]
+    fn subtraction_right_after_a_name_is_parsed() {
+        // The scanner reads -1 as a negative literal, which the parser turns back into a subtraction
+        assert_eq!(size_of("define COUNT 9;\ndefine SIZE (COUNT-1);").unwrap(), 8);
+        assert_eq!(size_of("define COUNT 9;\ndefine SIZE (COUNT - 1);").unwrap(), 8);
+    }
+
+    #[test]
+    fn division_by_zero_is_rejected() {
+        assert!(matches!(size_of("define ZERO 0;\ndefine SIZE (8 / ZERO);"), Err(RuneParserError::InvalidNumericValue)));
+    }
+
+    #[test]
+    fn orphan_redefinition_is_returned_as_a_warning() {
+        let mut warnings: Vec<Warning> = Vec::new();
+        parse_files_with_warnings(&[("sizes.rune", "define SIZE 4;\n\nredefine SIZE 8;\nredefine MISSING 2;\n")], &mut warnings).unwrap();
+
+        let orphans: Vec<&Warning> = warnings.iter().filter(|warning| matches!(warning, Warning::OrphanRedefinition(_))).collect();
+        assert_eq!(orphans.len(), 1);
+        assert!(matches!(orphans[0], Warning::OrphanRedefinition(name) if name == "MISSING"));
+    }
+
+    #[test]
+    fn unrelated_files_can_define_the_same_name() {
+        let files = parse_files(&[
+            ("a.rune", "define MAX 4;\n\nstruct A {\n    data: [u8; MAX] = 0;\n}\n"),
+            ("b.rune", "define MAX 8;\n\nstruct B {\n    data: [u8; MAX] = 0;\n}\n")
+        ])
+        .unwrap();
+
+        let size = |name: &str| files.iter().find_map(|file| file.definitions.find_struct(name)).unwrap().flat_size().unwrap();
+        assert_eq!(size("A"), 4);
+        assert_eq!(size("B"), 8);
+    }
+
+    #[test]
+    fn included_files_cannot_define_the_same_name() {
+        let result = parse_files(&[("a.rune", "include \"b\";\n\ndefine MAX 4;\n"), ("b.rune", "define MAX 8;\n")]);
+        assert!(matches!(result, Err(RuneParserError::MultipleDefinitions)));
+    }
+
+    #[test]
+    fn negative_define_cannot_size_an_array() {
+        assert!(matches!(size_of("define SIZE -4;"), Err(RuneParserError::InvalidNumericValue)));
+        assert!(matches!(size_of("define BASE 2;\ndefine SIZE (BASE - 6);"), Err(RuneParserError::InvalidNumericValue)));
+    }
+
+    #[test]
+    fn define_sizes_arrays_in_extensions() {
+        let files = parse_files(&[
+            ("base.rune", "define N 4;\n\nmessage M {\n    a: u8 = 1;\n}\n\nstruct S {\n    a: u8 = 0;\n}\n"),
+            ("extra.rune", "include \"base\";\n\nextend message M {\n    b: [u8; N] = 2;\n}\n\nextend struct S {\n    b: [u16; N] = 1;\n}\n")
+        ])
+        .unwrap();
+
+        let base = files.iter().find(|file| file.name == "base").unwrap();
+        let message = base.definitions.find_message("M").unwrap();
+
+        // A header and a byte for a, then a header and four bytes for b
+        assert!(matches!(message.fields[1].data_type.value_type(), Some(ValueType::Array(array)) if array.element_count.value().unwrap() == 4));
+        assert_eq!(message.optimal_full_encoded_size().unwrap(), 7);
+        assert_eq!(base.definitions.find_struct("S").unwrap().flat_size().unwrap(), 9);
+    }
+}
@@ -1,71 +1,33 @@
 use crate::{
     output::*,
-    types::{BitfieldDefinition, EnumDefinition, IncludeDefinition, MessageDefinition, StructDefinition},
+    post_processing::include_scopes,
+    types::{BitfieldDefinition, EnumDefinition, ExtensionRef, IncludeDefinition, MessageDefinition, StructDefinition},
     RuneFileDescription, RuneParserError
 };
 
-const VEC_SIZE: usize = 0x40;
-
 pub fn parse_extensions(definitions: &mut Vec<RuneFileDescription>, append_definitions: bool) -> Result<(), RuneParserError> {
     info!("Parsing extensions");
 
     // Create a list of all extensions found across all files
     // ———————————————————————————————————————————————————————
 
-    let mut bitfield_extensions: Vec<BitfieldExtension> = Vec::with_capacity(VEC_SIZE);
-    let mut enum_extensions: Vec<EnumExtension> = Vec::with_capacity(VEC_SIZE);
-    let mut message_extensions: Vec<MessageExtension> = Vec::with_capacity(VEC_SIZE);
-    let mut struct_extensions: Vec<StructExtension> = Vec::with_capacity(VEC_SIZE);
-
-    for file in &mut *definitions {
-        // Check if there is any definitions in this file
-        if !file.definitions.extensions.is_empty() {
-            // Add all bitfield extensions, as well as which file they came from
-            for bitfield_extension in &file.definitions.extensions.bitfields {
-                info!("    Found extension to {0} in file {1}.rune", bitfield_extension.name, file.name);
-
-                let extension: BitfieldExtension = BitfieldExtension {
-                    files:      Vec::from([file.name.clone()]),
-                    definition: bitfield_extension.clone()
-                };
-
-                bitfield_extensions.push(extension);
-            }
-
-            // Add all enum extensions, as well as which file they came from
-            for enum_extension in &file.definitions.extensions.enums {
-                info!("    Found extension to {0} in {1}.rune", enum_extension.name, file.name);
-
-                let extension: EnumExtension = EnumExtension {
-                    files:      Vec::from([file.name.clone()]),
-                    definition: enum_extension.clone()
-                };
-
-                enum_extensions.push(extension);
-            }
+    let mut bitfield_extensions: Vec<BitfieldExtension> = Vec::with_capacity(definitions.iter().map(|file| file.definitions.extensions.bitfields.len()).sum());
+    let mut enum_extensions: Vec<EnumExtension> = Vec::with_capacity(definitions.iter().map(|file| file.definitions.extensions.enums.len()).sum());
+    let mut message_extensions: Vec<MessageExtension> = Vec::with_capacity(definitions.iter().map(|file| file.definitions.extensions.messages.len()).sum());
+    let mut struct_extensions: Vec<StructExtension> = Vec::with_capacity(definitions.iter().map(|file| file.definitions.extensions.structs.len()).sum());
 
-            // Add all message extensions, as well as which file they came from
-            for message_extension in &file.definitions.extensions.messages {
-                info!("    Found extension to {0} in file {1}.rune", message_extension.name, file.name);
+    for file in definitions.iter() {
+        // Add all extensions, as well as which file they came from
+        for extension in file.definitions.extensions.iter() {
+            info!("    Found extension to {0} in file {1}.rune", extension.name(), file.name);
 
-                let extension: MessageExtension = MessageExtension {
-                    files:      Vec::from([file.name.clone()]),
-                    definition: message_extension.clone()
-                };
+            let files: Vec<String> = Vec::from([file.include_path()]);
 
-                message_extensions.push(extension);
-            }
-
-            // Add all struct extensions, as well as which file they came from
-            for struct_extension in &file.definitions.extensions.structs {
-                info!("    Found extension to {0} in file {1}.rune", struct_extension.name, file.name);
-
-                let extension: StructExtension = StructExtension {
-                    files:      Vec::from([file.name.clone()]),
-                    definition: struct_extension.clone()
-                };
-
-                struct_extensions.push(extension);
+            match extension {
+                ExtensionRef::Bitfield(definition) => bitfield_extensions.push(BitfieldExtension { files, definition: definition.clone() }),
+                ExtensionRef::Enum(definition) => enum_extensions.push(EnumExtension { files, definition: definition.clone() }),
+                ExtensionRef::Message(definition) => message_extensions.push(MessageExtension { files, definition: definition.clone() }),
+                ExtensionRef::Struct(definition) => struct_extensions.push(StructExtension { files, definition: definition.clone() })
             }
         }
     }
@@ -188,19 +150,30 @@ pub fn parse_extensions(definitions: &mut Vec<RuneFileDescription>, append_defin
                                 error!("Collision between two {0} extensions at index {1}", message_extensions[i].definition.name, z_field.identifier);
                                 return Err(RuneParserError::IndexCollision);
                             }
+
+                            if z_field.index.value() == i_field.index.value() {
+                                error!(
+                                    "Collision between two {0} extensions at field index {1}, used by both {2} and {3}",
+                                    message_extensions[i].definition.name,
+                                    z_field.index.value(),
+                                    i_field.identifier,
+                                    z_field.identifier
+                                );
+                                return Err(RuneParserError::IndexCollision);
+                            }
                         }
                     }
 
                     // Copy all origin files of 'z' to 'i'
-                    let mut z_files_copy = struct_extensions[z].files.clone();
-                    struct_extensions[i].files.append(&mut z_files_copy);
+                    let mut z_files_copy = message_extensions[z].files.clone();
+                    message_extensions[i].files.append(&mut z_files_copy);
 
                     // Copy all fields of 'z' to 'i'
-                    let mut z_field_list_copy = struct_extensions[z].definition.members.clone();
-                    struct_extensions[i].definition.members.append(&mut z_field_list_copy);
+                    let mut z_field_list_copy = message_extensions[z].definition.fields.clone();
+                    message_extensions[i].definition.fields.append(&mut z_field_list_copy);
 
                     // Remove index 'z' from list
-                    struct_extensions.swap_remove(z);
+                    message_extensions.swap_remove(z);
 
                     list_size -= 1;
                 } else {
@@ -225,22 +198,22 @@ pub fn parse_extensions(definitions: &mut Vec<RuneFileDescription>, append_defin
                     for z_member in &struct_extensions[z].definition.members {
                         for i_member in &struct_extensions[i].definition.members {
                             if z_member.identifier == i_member.identifier {
-                                error!("Collision between two {0} extensions at index {1}", message_extensions[i].definition.name, z_member.identifier);
+                                error!("Collision between two {0} extensions at index {1}", struct_extensions[i].definition.name, z_member.identifier);
                                 return Err(RuneParserError::IndexCollision);
                             }
                         }
                     }
 
                     // Copy all origin files of 'z' to 'i'
-                    let mut z_files_copy = message_extensions[z].files.clone();
-                    message_extensions[i].files.append(&mut z_files_copy);
+                    let mut z_files_copy = struct_extensions[z].files.clone();
+                    struct_extensions[i].files.append(&mut z_files_copy);
 
                     // Copy all members of 'z' to 'i'
-                    let mut z_member_list_copy = message_extensions[z].definition.fields.clone();
-                    message_extensions[i].definition.fields.append(&mut z_member_list_copy);
+                    let mut z_member_list_copy = struct_extensions[z].definition.members.clone();
+                    struct_extensions[i].definition.members.append(&mut z_member_list_copy);
 
                     // Remove index 'z' from list
-                    message_extensions.swap_remove(z);
+                    struct_extensions.swap_remove(z);
 
                     list_size -= 1;
                 } else {
@@ -254,11 +227,21 @@ pub fn parse_extensions(definitions: &mut Vec<RuneFileDescription>, append_defin
     // Check the extensions with the original definition, and append them if there are no collisions
     // ——————————————————————————————————————————————————————————————————————————————————————————————
 
+    // Files visible from each file before any extension includes are added, as files extending a definition they already see must not be
+    // included back by the file declaring it
+    let scopes: Vec<(String, Vec<String>)> = include_scopes(definitions)
+        .into_iter()
+        .enumerate()
+        .map(|(index, scope)| (definitions[index].include_path(), scope.into_iter().map(|visible| definitions[visible].include_path()).collect()))
+        .collect();
+
     if append_definitions {
         // Append Bitfields
         for extension in bitfield_extensions {
             // Find original definition
             for file in &mut *definitions {
+                let own_path: String = file.include_path();
+
                 for bitfield_definition in &mut file.definitions.bitfields {
                     if bitfield_definition.name == extension.definition.name {
                         // Check that backing types match
@@ -287,9 +270,7 @@ pub fn parse_extensions(definitions: &mut Vec<RuneFileDescription>, append_defin
                         bitfield_definition.members.append(&mut extension.definition.members.clone());
 
                         // Add files as inclusions
-                        for include_file in &extension.files {
-                            file.definitions.includes.push(IncludeDefinition { file: include_file.clone() });
-                        }
+                        add_extension_includes(&mut file.definitions.includes, &own_path, &extension.files, &scopes);
                     }
                 }
             }
@@ -299,6 +280,8 @@ pub fn parse_extensions(definitions: &mut Vec<RuneFileDescription>, append_defin
         for extension in enum_extensions {
             // Find original definition
             for file in &mut *definitions {
+                let own_path: String = file.include_path();
+
                 for enum_definition in &mut file.definitions.enums {
                     if enum_definition.name == extension.definition.name {
                         // Check that backing types match
@@ -327,9 +310,7 @@ pub fn parse_extensions(definitions: &mut Vec<RuneFileDescription>, append_defin
                         enum_definition.members.append(&mut extension.definition.members.clone());
 
                         // Add files as inclusions
-                        for include_file in &extension.files {
-                            file.definitions.includes.push(IncludeDefinition { file: include_file.clone() });
-                        }
+                        add_extension_includes(&mut file.definitions.includes, &own_path, &extension.files, &scopes);
                     }
                 }
             }
@@ -339,6 +320,8 @@ pub fn parse_extensions(definitions: &mut Vec<RuneFileDescription>, append_defin
         for extension in message_extensions {
             // Find original definition
             for file in &mut *definitions {
+                let own_path: String = file.include_path();
+
                 for message_definition in &mut file.definitions.messages {
                     if message_definition.name == extension.definition.name {
                         // Check for collisions
@@ -351,6 +334,17 @@ pub fn parse_extensions(definitions: &mut Vec<RuneFileDescription>, append_defin
                                     );
                                     return Err(RuneParserError::IndexCollision);
                                 }
+
+                                if extension_field.index.value() == definition_field.index.value() {
+                                    error!(
+                                        "Collision between original {0} definition and extension at field index {1}, used by both {2} and {3}",
+                                        message_definition.name,
+                                        definition_field.index.value(),
+                                        definition_field.identifier,
+                                        extension_field.identifier
+                                    );
+                                    return Err(RuneParserError::IndexCollision);
+                                }
                             }
                         }
 
@@ -358,9 +352,7 @@ pub fn parse_extensions(definitions: &mut Vec<RuneFileDescription>, append_defin
                         message_definition.fields.append(&mut extension.definition.fields.clone());
 
                         // Add files as inclusions
-                        for include_file in &extension.files {
-                            file.definitions.includes.push(IncludeDefinition { file: include_file.clone() });
-                        }
+                        add_extension_includes(&mut file.definitions.includes, &own_path, &extension.files, &scopes);
                     }
                 }
             }
@@ -370,8 +362,16 @@ pub fn parse_extensions(definitions: &mut Vec<RuneFileDescription>, append_defin
         for extension in struct_extensions {
             // Find original definition
             for file in &mut *definitions {
+                let own_path: String = file.include_path();
+
                 for struct_definition in &mut file.definitions.structs {
                     if struct_definition.name == extension.definition.name {
+                        // The members of external structs are not known, so they cannot be extended
+                        if struct_definition.is_external() {
+                            error!("Cannot extend external struct {0}", struct_definition.name);
+                            return Err(RuneParserError::InvalidTypeUse);
+                        }
+
                         // Check for collisions
                         for extension_field in &extension.definition.members {
                             for definition_field in &struct_definition.members {
@@ -389,9 +389,7 @@ pub fn parse_extensions(definitions: &mut Vec<RuneFileDescription>, append_defin
                         struct_definition.members.append(&mut extension.definition.members.clone());
 
                         // Add files as inclusions
-                        for include_file in &extension.files {
-                            file.definitions.includes.push(IncludeDefinition { file: include_file.clone() });
-                        }
+                        add_extension_includes(&mut file.definitions.includes, &own_path, &extension.files, &scopes);
                     }
                 }
             }
@@ -404,6 +402,18 @@ pub fn parse_extensions(definitions: &mut Vec<RuneFileDescription>, append_defin
 // Utility Structs
 // ————————————————
 
+/// Include the files declaring an extension from the file whose definition is extended, skipping files already included, the file itself,
+/// and files that already include it, as including those back would create an include cycle
+fn add_extension_includes(includes: &mut Vec<IncludeDefinition>, own_path: &str, extension_files: &[String], scopes: &[(String, Vec<String>)]) {
+    for include_file in extension_files {
+        let sees_own_file: bool = scopes.iter().any(|(path, scope)| path == include_file && scope.iter().any(|visible| visible == own_path));
+
+        if include_file != own_path && !sees_own_file && !includes.iter().any(|include| include.file == *include_file) {
+            includes.push(IncludeDefinition { file: include_file.clone() });
+        }
+    }
+}
+
 struct BitfieldExtension {
     files:      Vec<String>,
     definition: BitfieldDefinition
@@ -423,3 +433,79 @@ struct StructExtension {
     files:      Vec<String>,
     definition: StructDefinition
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{test_utils::parse_files, RuneParserError};
+
+    #[test]
+    fn extension_in_subdirectory_is_included_with_its_path() {
+        let files = parse_files(&[
+            ("base.rune", "message Packet {\n    a: u8 = 1;\n}\n"),
+            ("common/extra.rune", "extend message Packet {\n    b: u8 = 2;\n}\n")
+        ])
+        .unwrap();
+
+        let base = files.iter().find(|file| file.name == "base").unwrap();
+        let includes: Vec<&str> = base.definitions.includes.iter().map(|include| include.file.as_str()).collect();
+
+        assert_eq!(includes, vec!["common/extra"]);
+        assert_eq!(base.definitions.find_message("Packet").unwrap().fields.len(), 2);
+    }
+
+    #[test]
+    fn repeated_extensions_merge_into_their_own_definitions() {
+        let files = parse_files(&[
+            ("base.rune", "message Packet {\n    a: u8 = 1;\n}\n\nstruct Point {\n    x: u8 = 0;\n}\n"),
+            ("first.rune", "extend message Packet {\n    b: u8 = 2;\n}\n\nextend struct Point {\n    y: u8 = 1;\n}\n"),
+            ("second.rune", "extend message Packet {\n    c: u8 = 3;\n}\n\nextend struct Point {\n    z: u8 = 2;\n}\n")
+        ])
+        .unwrap();
+
+        let base = files.iter().find(|file| file.name == "base").unwrap();
+        let fields: Vec<&str> = base.definitions.find_message("Packet").unwrap().fields.iter().map(|field| field.identifier.as_str()).collect();
+        let members: Vec<&str> = base.definitions.find_struct("Point").unwrap().members.iter().map(|member| member.identifier.as_str()).collect();
+
+        assert_eq!(fields, ["a", "b", "c"]);
+        assert_eq!(members, ["x", "y", "z"]);
+    }
+
+    #[test]
+    fn extension_reusing_a_base_field_index_is_rejected() {
+        let result = parse_files(&[("base.rune", "message Packet {\n    a: u8 = 1;\n}\n"), ("extra.rune", "extend message Packet {\n    b: u8 = 1;\n}\n")]);
+        assert!(matches!(result, Err(RuneParserError::IndexCollision)));
+    }
+
+    #[test]
+    fn extensions_colliding_on_an_index_are_rejected() {
+        let result = parse_files(&[
+            ("base.rune", "message Packet {\n    a: u8 = 1;\n}\n"),
+            ("first.rune", "extend message Packet {\n    b: u8 = 7;\n}\n"),
+            ("second.rune", "extend message Packet {\n    c: u8 = 7;\n}\n")
+        ]);
+        assert!(matches!(result, Err(RuneParserError::IndexCollision)));
+    }
+
+    #[test]
+    fn extending_an_included_file_does_not_include_it_back() {
+        let files = parse_files(&[
+            ("a.rune", "message Packet {\n    a: u8 = 1;\n}\n"),
+            ("b.rune", "include \"a\";\n\nextend message Packet {\n    b: u8 = 2;\n}\n")
+        ])
+        .unwrap();
+
+        let a = files.iter().find(|file| file.name == "a").unwrap();
+
+        assert!(a.definitions.includes.is_empty());
+        assert_eq!(a.definitions.find_message("Packet").unwrap().fields.len(), 2);
+    }
+
+    #[test]
+    fn extensions_including_each_other_back_are_rejected() {
+        let result = parse_files(&[
+            ("a.rune", "message Packet {\n    a: u8 = 1;\n}\n\nextend struct Point {\n    y: u8 = 1;\n}\n"),
+            ("b.rune", "struct Point {\n    x: u8 = 0;\n}\n\nextend message Packet {\n    b: u8 = 2;\n}\n")
+        ]);
+        assert!(matches!(result, Err(RuneParserError::CyclicInclude)));
+    }
+}
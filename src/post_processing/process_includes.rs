@@ -0,0 +1,225 @@
+use crate::{output::*, types::IncludeDefinition, RuneFileDescription, RuneParserError, Warning};
+
+/// Visiting state of a file during the depth first search
+#[derive(Clone, Copy, PartialEq)]
+enum VisitState {
+    Unvisited,
+    Visiting,
+    Visited
+}
+
+/// Resolve every include to the include path of a parsed file. An include is first looked up relative to the directory of the including
+/// file, such as `sub/foo` for `include "foo";` in `sub/bar.rune`, and then relative to the input path. Includes matching no parsed file
+/// are errors
+pub fn resolve_includes(definitions: &mut [RuneFileDescription]) -> Result<(), RuneParserError> {
+    info!("Resolving includes");
+
+    let paths: Vec<String> = definitions.iter().map(|file| file.include_path()).collect();
+
+    for file in definitions.iter_mut() {
+        for include in &mut file.definitions.includes {
+            let candidates = [normalize_include(&format!("{0}{1}", file.relative_path, include.file)), normalize_include(&include.file)];
+
+            match candidates.into_iter().flatten().find(|candidate| paths.contains(candidate)) {
+                Some(resolved) => include.file = resolved,
+                None => {
+                    error!("{0}{1}.rune includes \"{2}\", which matches no parsed rune file", file.relative_path, file.name, include.file);
+                    return Err(RuneParserError::UndefinedInclude);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Collapse `.` and `..` components of an include path. Paths stepping out of the input path cannot match any parsed file
+fn normalize_include(path: &str) -> Option<String> {
+    let mut components: Vec<&str> = Vec::new();
+
+    for component in path.split('/') {
+        match component {
+            "" | "." => (),
+            ".." => {
+                components.pop()?;
+            },
+            _ => components.push(component)
+        }
+    }
+
+    Some(components.join("/"))
+}
+
+/// Remove repeated includes of the same file, keeping the first, as well as files including themselves directly
+pub fn deduplicate_includes(definitions: &mut [RuneFileDescription], warnings: &mut Vec<Warning>) {
+    for file in definitions {
+        let own_path: String = file.include_path();
+        let mut includes: Vec<IncludeDefinition> = Vec::with_capacity(file.definitions.includes.len());
+
+        for include in file.definitions.includes.drain(..) {
+            let warning = if include.file == own_path {
+                Warning::SelfInclude(own_path.clone())
+            } else if includes.iter().any(|kept| kept.file == include.file) {
+                Warning::DuplicateInclude { file: own_path.clone(), include: include.file }
+            } else {
+                includes.push(include);
+                continue;
+            };
+
+            warning!("{0}", warning);
+            warnings.push(warning);
+        }
+
+        file.definitions.includes = includes;
+    }
+}
+
+/// Check that no file includes itself through other files, such as a including b, which includes a again. Runs once includes are resolved
+/// to parsed files, and once direct self includes are dropped by [`deduplicate_includes`]
+pub fn check_include_cycles(definitions: &[RuneFileDescription]) -> Result<(), RuneParserError> {
+    info!("Checking includes");
+
+    let paths: Vec<String> = definitions.iter().map(|file| file.include_path()).collect();
+    let includes: Vec<Vec<usize>> = include_graph(definitions, &paths);
+
+    let mut states: Vec<VisitState> = vec![VisitState::Unvisited; paths.len()];
+    let mut path: Vec<usize> = Vec::with_capacity(paths.len());
+
+    for index in 0..paths.len() {
+        visit(index, &paths, &includes, &mut states, &mut path)?;
+    }
+
+    Ok(())
+}
+
+fn visit(index: usize, paths: &[String], includes: &[Vec<usize>], states: &mut [VisitState], path: &mut Vec<usize>) -> Result<(), RuneParserError> {
+    match states[index] {
+        VisitState::Visited => return Ok(()),
+        VisitState::Visiting => {
+            // The file is already on the include path, so it ends up including itself
+            let start: usize = path.iter().position(|&entry| entry == index).unwrap_or_default();
+            let cycle: Vec<String> = path[start..].iter().chain([&index]).map(|&entry| format!("{0}.rune", paths[entry])).collect();
+
+            error!("{0}.rune includes itself through {1}", paths[index], cycle.join(" -> "));
+            return Err(RuneParserError::CyclicInclude);
+        },
+        VisitState::Unvisited => ()
+    }
+
+    states[index] = VisitState::Visiting;
+    path.push(index);
+
+    for &include in &includes[index] {
+        visit(include, paths, includes, states, path)?;
+    }
+
+    path.pop();
+    states[index] = VisitState::Visited;
+
+    Ok(())
+}
+
+/// Indexes of the files visible from each file, which are the file itself followed by every file it includes, directly or through other
+/// files. Includes of files that were not parsed are ignored, and cycles are walked only once
+pub fn include_scopes(definitions: &[RuneFileDescription]) -> Vec<Vec<usize>> {
+    let paths: Vec<String> = definitions.iter().map(|file| file.include_path()).collect();
+    let includes: Vec<Vec<usize>> = include_graph(definitions, &paths);
+
+    (0..definitions.len())
+        .map(|index| {
+            let mut scope: Vec<usize> = Vec::from([index]);
+            let mut next: usize = 0;
+
+            while next < scope.len() {
+                for &include in &includes[scope[next]] {
+                    if !scope.contains(&include) {
+                        scope.push(include);
+                    }
+                }
+                next += 1;
+            }
+
+            scope
+        })
+        .collect()
+}
+
+/// Indexes of the files directly included by each file
+fn include_graph(definitions: &[RuneFileDescription], paths: &[String]) -> Vec<Vec<usize>> {
+    definitions
+        .iter()
+        .map(|file| {
+            file.definitions
+                .includes
+                .iter()
+                .filter_map(|include| paths.iter().position(|path| *path == include.file))
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        test_utils::{parse_files, parse_files_with_warnings},
+        RuneParserError, Warning
+    };
+
+    #[test]
+    fn two_file_cycle_is_rejected() {
+        let result = parse_files(&[("a.rune", "include \"b\";\n\nstruct A {\n    a: u8 = 0;\n}\n"), ("b.rune", "include \"a\";\n\nstruct B {\n    b: u8 = 0;\n}\n")]);
+        assert!(matches!(result, Err(RuneParserError::CyclicInclude)));
+    }
+
+    #[test]
+    fn self_include_is_dropped() {
+        let mut warnings: Vec<Warning> = Vec::new();
+        let files = parse_files_with_warnings(&[("a.rune", "include \"a\";\n\nstruct A {\n    a: u8 = 0;\n}\n")], &mut warnings).unwrap();
+
+        assert!(files[0].definitions.includes.is_empty());
+        assert!(matches!(warnings.as_slice(), [Warning::SelfInclude(file)] if file == "a"));
+    }
+
+    #[test]
+    fn duplicate_and_self_includes_leave_a_clean_list() {
+        let mut warnings: Vec<Warning> = Vec::new();
+        let files = parse_files_with_warnings(
+            &[
+                ("a.rune", "include \"b\";\ninclude \"b.rune\";\ninclude \"a\";\ninclude \"c\";\n\nstruct A {\n    a: u8 = 0;\n}\n"),
+                ("b.rune", "struct B {\n    b: u8 = 0;\n}\n"),
+                ("c.rune", "struct C {\n    c: u8 = 0;\n}\n")
+            ],
+            &mut warnings
+        )
+        .unwrap();
+
+        let a = files.iter().find(|file| file.name == "a").unwrap();
+        let includes: Vec<&str> = a.definitions.includes.iter().map(|include| include.file.as_str()).collect();
+
+        assert_eq!(includes, ["b", "c"]);
+        assert!(warnings.iter().any(|warning| matches!(warning, Warning::DuplicateInclude { file, include } if file == "a" && include == "b")));
+        assert!(warnings.iter().any(|warning| matches!(warning, Warning::SelfInclude(file) if file == "a")));
+    }
+
+    #[test]
+    fn includes_resolve_relative_to_the_including_file() {
+        let files = parse_files(&[
+            ("net/proto/packet.rune", "include \"header\";\ninclude \"../common\";\ninclude \"types/base\";\n\nmessage Packet {\n    header: Header = 1;\n    id: Id = 2;\n    base: Base = 3;\n}\n"),
+            ("net/proto/header.rune", "struct Header {\n    a: u8 = 0;\n}\n"),
+            ("net/common.rune", "struct Id {\n    a: u8 = 0;\n}\n"),
+            ("types/base.rune", "struct Base {\n    a: u8 = 0;\n}\n")
+        ])
+        .unwrap();
+
+        let packet = files.iter().find(|file| file.name == "packet").unwrap();
+        let includes: Vec<&str> = packet.definitions.includes.iter().map(|include| include.file.as_str()).collect();
+
+        assert_eq!(includes, ["net/proto/header", "net/common", "types/base"]);
+    }
+
+    #[test]
+    fn include_matching_no_file_is_rejected() {
+        let result = parse_files(&[("net/packet.rune", "include \"missing\";\n"), ("net/present.rune", "struct Present {\n    a: u8 = 0;\n}\n")]);
+        assert!(matches!(result, Err(RuneParserError::UndefinedInclude)));
+    }
+}
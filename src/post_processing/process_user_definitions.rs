@@ -1,13 +1,77 @@
+use std::collections::HashMap;
+
 use crate::{
     output::*,
-    types::{FieldType, MemberType, UserDefinitionLink},
+    types::{BitfieldDefinition, EnumDefinition, FieldType, MessageDefinition, StructDefinition, UserDefinitionLink, ValueType},
     ArrayType, RuneFileDescription, RuneParserError
 };
 
-pub fn link_user_definitions(definitions: &mut Vec<RuneFileDescription>) -> Result<(), RuneParserError> {
+/// Default limit on how deeply user defined types may be nested inside each other
+pub const DEFAULT_MAX_NESTING_DEPTH: usize = 64;
+
+/// Definition found under a name in the definition index
+enum IndexedDefinition<'a> {
+    Bitfield(&'a BitfieldDefinition),
+    Enum(&'a EnumDefinition),
+    /// Messages are only indexed here to report their use as data types
+    Message,
+    Struct(&'a StructDefinition)
+}
+
+/// Name lookup over every definition in the parsed files, built once before linking
+struct DefinitionIndex<'a> {
+    /// Bitfields, enums, structs and messages, as found when looking up the type of a struct member or an array
+    data:     HashMap<&'a str, IndexedDefinition<'a>>,
+    /// Messages only, which take precedence when looking up the type of a message field
+    messages: HashMap<&'a str, &'a MessageDefinition>,
+    /// Definitions left out by the version their file declares, with the file, the version they require, and the declared version
+    gated:    HashMap<&'a str, (&'a str, u64, u64)>
+}
+
+impl<'a> DefinitionIndex<'a> {
+    /// When a name is defined more than once, the first definition in file order wins, preferring bitfields, then enums, structs and messages
+    /// within a file. Duplicate names are reported later on by validation
+    fn new(definitions: &'a [RuneFileDescription]) -> DefinitionIndex<'a> {
+        let mut index = DefinitionIndex {
+            data:     HashMap::new(),
+            messages: HashMap::new(),
+            gated:    HashMap::new()
+        };
+
+        for file in definitions {
+            for bitfield_definition in &file.definitions.bitfields {
+                index.data.entry(bitfield_definition.name.as_str()).or_insert(IndexedDefinition::Bitfield(bitfield_definition));
+            }
+
+            for enum_definition in &file.definitions.enums {
+                index.data.entry(enum_definition.name.as_str()).or_insert(IndexedDefinition::Enum(enum_definition));
+            }
+
+            for struct_definition in &file.definitions.structs {
+                index.data.entry(struct_definition.name.as_str()).or_insert(IndexedDefinition::Struct(struct_definition));
+            }
+
+            for message_definition in &file.definitions.messages {
+                index.data.entry(message_definition.name.as_str()).or_insert(IndexedDefinition::Message);
+                index.messages.entry(message_definition.name.as_str()).or_insert(message_definition);
+            }
+
+            if let Some(version) = file.definitions.version {
+                for (name, min_version) in &file.definitions.gated_definitions {
+                    index.gated.entry(name.as_str()).or_insert((file.name.as_str(), *min_version, version));
+                }
+            }
+        }
+
+        index
+    }
+}
+
+pub fn link_user_definitions(definitions: &mut Vec<RuneFileDescription>, max_nesting_depth: usize) -> Result<(), RuneParserError> {
     info!("Linking user definitions");
 
     let immutable_reference = definitions.clone();
+    let index = DefinitionIndex::new(&immutable_reference);
 
     // Find every message member with the type UserDefinition, and add a link to its name and link to the list
     for file in definitions {
@@ -15,24 +79,17 @@ pub fn link_user_definitions(definitions: &mut Vec<RuneFileDescription>) -> Resu
         for message_definition in &mut file.definitions.messages {
             // Check all message fields
             for field in &mut message_definition.fields {
-                // Check if type is user defined, or array with user defined type
                 match &mut field.data_type {
-                    FieldType::Array(array) => {
-                        if let ArrayType::UserDefined(definition_name, definition_link) = &mut array.data_type {
-                            *definition_link = find_data_definition(definition_name, &immutable_reference)?;
-                        }
-                    },
-
                     FieldType::Empty => {
                         error!("Message field definition was empty! This should not happen!");
                         return Err(RuneParserError::EmptyMessageField);
                     },
 
-                    FieldType::UserDefined(definition_name, definition_link) => {
-                        *definition_link = find_field_definition(definition_name, &immutable_reference)?;
-                    },
+                    // Message fields are the only place where messages can be used as a data type
+                    FieldType::Value(value_type) => link_value_type(value_type, true, &index, &mut Vec::new(), max_nesting_depth)?,
 
-                    _ => () // Nothing
+                    // Bytes refer to no other definition
+                    FieldType::Bytes(_) => ()
                 }
             }
         }
@@ -41,19 +98,7 @@ pub fn link_user_definitions(definitions: &mut Vec<RuneFileDescription>) -> Resu
         for struct_definition in &mut file.definitions.structs {
             // Check all struct members
             for member in &mut struct_definition.members {
-                // Check if type is user defined, or array with user defined type
-                match &mut member.data_type {
-                    MemberType::Array(array) => {
-                        if let ArrayType::UserDefined(definition_name, definition_link) = &mut array.data_type {
-                            *definition_link = find_data_definition(definition_name, &immutable_reference)?;
-                        }
-                    },
-
-                    MemberType::UserDefined(definition_name, definition_link) => {
-                        *definition_link = find_data_definition(definition_name, &immutable_reference)?;
-                    },
-                    _ => () // Nothing
-                }
+                link_value_type(&mut member.data_type, false, &index, &mut Vec::new(), max_nesting_depth)?;
             }
         }
     }
@@ -61,83 +106,200 @@ pub fn link_user_definitions(definitions: &mut Vec<RuneFileDescription>) -> Resu
     Ok(())
 }
 
-fn find_data_definition(identifier: &String, definitions: &Vec<RuneFileDescription>) -> Result<UserDefinitionLink, RuneParserError> {
+/// Link a data type if it is user defined, or an array with a user defined type. The resolution path holds the names of the definitions
+/// currently being linked, which is used to stop at recursive types, and at nesting deeper than the maximum depth
+fn link_value_type(
+    value_type: &mut ValueType,
+    allow_messages: bool,
+    index: &DefinitionIndex,
+    path: &mut Vec<String>,
+    max_nesting_depth: usize
+) -> Result<(), RuneParserError> {
+    match value_type {
+        ValueType::Array(array) => {
+            if let ArrayType::UserDefined(definition_name, definition_link) = array.innermost_type_mut() {
+                *definition_link = find_data_definition(definition_name, index, path, max_nesting_depth)?;
+            }
+        },
+
+        ValueType::UserDefined(definition_name, definition_link) => {
+            *definition_link = match allow_messages {
+                false => find_data_definition(definition_name, index, path, max_nesting_depth)?,
+                true => find_field_definition(definition_name, index, path, max_nesting_depth)?
+            };
+        },
+
+        ValueType::Primitive(_) => () // Nothing
+    }
+
+    Ok(())
+}
+
+fn find_data_definition(identifier: &String, index: &DefinitionIndex, path: &mut Vec<String>, max_nesting_depth: usize) -> Result<UserDefinitionLink, RuneParserError> {
     // Then find the enum, field, message, struct with the corresponding name, and link to it
+    match index.data.get(identifier.as_str()) {
+        Some(IndexedDefinition::Bitfield(bitfield_definition)) => Ok(UserDefinitionLink::BitfieldLink((*bitfield_definition).clone())),
 
-    for file in definitions {
-        // Check if a bitfields name matches the identifier
-        for bitfield_definition in &file.definitions.bitfields {
-            // Check if bitfield matches the identifier
-            if identifier == bitfield_definition.name.as_str() {
-                return Ok(UserDefinitionLink::BitfieldLink(bitfield_definition.clone()));
-            }
-        }
+        Some(IndexedDefinition::Enum(enum_definition)) => Ok(UserDefinitionLink::EnumLink((*enum_definition).clone())),
 
-        // Check if an enums name matches the identifier
-        for enum_definition in &file.definitions.enums {
-            // Check if enum matches the identifier
-            if identifier == enum_definition.name.as_str() {
-                return Ok(UserDefinitionLink::EnumLink(enum_definition.clone()));
-            }
-        }
+        Some(IndexedDefinition::Struct(struct_definition)) => {
+            check_resolution_path("Struct", identifier, path, max_nesting_depth)?;
 
-        // Check if a structs name matches the identifier
-        for struct_definition in &file.definitions.structs {
-            // Check if struct matches the identifier
-            if identifier == struct_definition.name.as_str() {
-                let mut definition_copy = struct_definition.clone();
-
-                // Call recursively if struct found contains user defined members
-                for member in &mut definition_copy.members {
-                    if let MemberType::UserDefined(definition_name, definition_link) = &mut member.data_type {
-                        // Since we return a copy, we can easily modify the definition_copy without issue
-                        *definition_link = find_data_definition(definition_name, definitions)?;
-                    }
-                }
+            let mut definition_copy = (*struct_definition).clone();
 
-                return Ok(UserDefinitionLink::StructLink(definition_copy.clone()));
+            // Call recursively if struct found contains user defined members
+            path.push(identifier.clone());
+            for member in &mut definition_copy.members {
+                // Since we return a copy, we can easily modify the definition_copy without issue
+                link_value_type(&mut member.data_type, false, index, path, max_nesting_depth)?;
             }
-        }
+            path.pop();
+
+            Ok(UserDefinitionLink::StructLink(definition_copy))
+        },
 
         // Check messages in case a message type was used in an illegal way
-        for message_definition in &file.definitions.messages {
-            // Check if message matches the identifier
-            if identifier == message_definition.name.as_str() {
+        Some(IndexedDefinition::Message) => {
+            error!(
+                "Found a use of message type {0} being used somewhere else than a message! Messages cannot be used as array types, or as struct members!",
+                identifier
+            );
+            Err(RuneParserError::InvalidTypeUse)
+        },
+
+        None => match index.gated.get(identifier.as_str()) {
+            Some((file_name, min_version, version)) => {
                 error!(
-                    "Found a use of message type {0} being used somewhere else than a message! Messages cannot be used as array types, or as struct members!",
-                    identifier
+                    "Definition '{0}' is left out of {1}, as it requires version {2} through @min_version while the file declares version {3}!",
+                    identifier, file_name, min_version, version
                 );
-                return Err(RuneParserError::InvalidTypeUse);
+                Err(RuneParserError::UnavailableDefinition)
+            },
+            None => {
+                error!("Found no user definition for identifier '{0}'!", identifier);
+                Err(RuneParserError::UndefinedIdentifier)
             }
         }
     }
-
-    error!("Found no user definition for identifier '{0}'!", identifier);
-    Err(RuneParserError::UndefinedIdentifier)
 }
 
-fn find_field_definition(identifier: &String, definitions: &Vec<RuneFileDescription>) -> Result<UserDefinitionLink, RuneParserError> {
-    for file in definitions {
-        // Check if a messages name matches the identifier
-        for message_definition in &file.definitions.messages {
-            // Check if message matches the identifier
-            if identifier == message_definition.name.as_str() {
-                // !!! Using defines as array sizes might also require work here !!!
-
-                let mut definition_copy = message_definition.clone();
-
-                // Call recursively if struct found contains user defined members
-                for field in &mut definition_copy.fields {
-                    if let FieldType::UserDefined(definition_name, definition_link) = &mut field.data_type {
-                        // Since we return a copy, we can easily modify the definition_copy without issue
-                        *definition_link = find_field_definition(definition_name, definitions)?;
-                    }
-                }
+fn find_field_definition(identifier: &String, index: &DefinitionIndex, path: &mut Vec<String>, max_nesting_depth: usize) -> Result<UserDefinitionLink, RuneParserError> {
+    // Check if a messages name matches the identifier
+    if let Some(message_definition) = index.messages.get(identifier.as_str()) {
+        // !!! Using defines as array sizes might also require work here !!!
 
-                return Ok(UserDefinitionLink::MessageLink(definition_copy.clone()));
+        check_resolution_path("Message", identifier, path, max_nesting_depth)?;
+
+        let mut definition_copy = (*message_definition).clone();
+
+        // Call recursively if struct found contains user defined members
+        path.push(identifier.clone());
+        for field in &mut definition_copy.fields {
+            if let FieldType::Value(value_type) = &mut field.data_type {
+                // Since we return a copy, we can easily modify the definition_copy without issue
+                link_value_type(value_type, true, index, path, max_nesting_depth)?;
             }
         }
+        path.pop();
+
+        return Ok(UserDefinitionLink::MessageLink(definition_copy));
+    }
+
+    find_data_definition(identifier, index, path, max_nesting_depth)
+}
+
+/// Stop linking a definition that is already being linked further up the path, as it contains itself, or before nested definitions exhaust the stack.
+/// Definitions used several times by siblings are only on the path once at a time, so they are not mistaken for recursion
+fn check_resolution_path(kind: &str, identifier: &String, path: &[String], max_nesting_depth: usize) -> Result<(), RuneParserError> {
+    if let Some(start) = path.iter().position(|name| name == identifier) {
+        let cycle: Vec<&str> = path[start..].iter().chain([identifier]).map(|name| name.as_str()).collect();
+
+        error!("{0} {1} contains itself through {2}", kind, identifier, cycle.join(" -> "));
+        return Err(RuneParserError::RecursiveType);
+    }
+
+    if path.len() >= max_nesting_depth {
+        error!("Definition {0} is nested too deeply! Reduce the nesting of user defined types, or raise the maximum nesting depth", identifier);
+        return Err(RuneParserError::NestingTooDeep);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        parse_str,
+        post_processing::link_user_definitions,
+        test_utils::parse_files,
+        types::{UserDefinitionLink, ValueType},
+        RuneFileDescription, RuneParserError
+    };
+
+    /// A file where struct `Level0` holds `Level1`, which holds `Level2`, and so on down to `Level{levels}`
+    fn nested_structs(levels: usize) -> Vec<RuneFileDescription> {
+        let mut source = String::new();
+        for level in 0..levels {
+            source.push_str(&format!("struct Level{0} {{\n    inner: Level{1} = 0;\n}}\n\n", level, level + 1));
+        }
+        source.push_str(&format!("struct Level{0} {{\n    a: u8 = 0;\n}}\n", levels));
+
+        Vec::from([RuneFileDescription {
+            relative_path: String::new(),
+            name:          String::from("nested"),
+            definitions:   parse_str(&source, "nested.rune").unwrap()
+        }])
     }
 
-    find_data_definition(identifier, definitions)
+    #[test]
+    fn nesting_up_to_the_limit_links() {
+        assert!(link_user_definitions(&mut nested_structs(3), 3).is_ok());
+    }
+
+    #[test]
+    fn nesting_over_the_limit_is_too_deep() {
+        assert!(matches!(link_user_definitions(&mut nested_structs(4), 3), Err(RuneParserError::NestingTooDeep)));
+    }
+
+    const SOURCE: &str = "@min_version(3)\nstruct NewThing { a: u8 = 0; }\n\nmessage Packet { thing: NewThing = 1; }\n";
+
+    #[test]
+    fn reference_to_gated_definition_is_unavailable() {
+        let result = parse_files(&[("gated.rune", &format!("version 2;\n{0}", SOURCE))]);
+        assert!(matches!(result, Err(RuneParserError::UnavailableDefinition)));
+    }
+
+    #[test]
+    fn reference_to_available_definition_links() {
+        let files = parse_files(&[("gated.rune", &format!("version 3;\n{0}", SOURCE))]).unwrap();
+        assert!(files[0].definitions.find_struct("NewThing").is_some());
+    }
+
+    #[test]
+    fn many_structs_are_linked_to_their_definitions() {
+        let mut source = String::from("enum Mode: u8 {\n    Off = 0;\n}\n\nstruct Base {\n    value: u32 = 0;\n}\n\n");
+        for index in 0..500 {
+            source.push_str(&format!("struct Holder{0} {{\n    base: Base = 0;\n    mode: Mode = 1;\n}}\n\n", index));
+        }
+        source.push_str("message Packet {\n    last: Holder499 = 1;\n}\n");
+
+        let files = parse_files(&[("many.rune", &source)]).unwrap();
+        let definitions = &files[0].definitions;
+
+        for index in 0..500 {
+            let holder = definitions.find_struct(&format!("Holder{0}", index)).unwrap();
+
+            assert!(matches!(&holder.members[0].data_type, ValueType::UserDefined(_, UserDefinitionLink::StructLink(base)) if base.name == "Base"));
+            assert!(matches!(&holder.members[1].data_type, ValueType::UserDefined(_, UserDefinitionLink::EnumLink(mode)) if mode.name == "Mode"));
+        }
+
+        let packet = definitions.find_message("Packet").unwrap();
+        assert_eq!(packet.fields[0].full_encoded_size(false).unwrap(), Some(5));
+    }
+
+    #[test]
+    fn message_used_as_struct_member_is_an_invalid_type_use() {
+        let result = parse_files(&[("invalid.rune", "message Packet {\n    a: u8 = 1;\n}\n\nstruct Holder {\n    packet: Packet = 0;\n}\n")]);
+        assert!(matches!(result, Err(RuneParserError::InvalidTypeUse)));
+    }
 }
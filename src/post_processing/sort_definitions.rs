@@ -0,0 +1,211 @@
+use crate::{
+    output::*,
+    types::{FieldType, ValueType},
+    ArrayType, RuneFileDescription, RuneParserError
+};
+
+/// Visiting state of a definition during the depth first search
+#[derive(Clone, Copy, PartialEq)]
+enum VisitState {
+    Unvisited,
+    Visiting,
+    Visited
+}
+
+/// Reorder structs and messages within each file so that no definition is used before it is declared. Declaration order is kept wherever
+/// possible. The declaration order of the file is left as written, so generators following it still see the definitions in source order
+pub fn sort_definitions(definitions: &mut Vec<RuneFileDescription>) -> Result<(), RuneParserError> {
+    info!("Sorting definitions");
+
+    for file in definitions {
+        // Sort structs by the structs they hold
+        let names: Vec<String> = file.definitions.structs.iter().map(|definition| definition.name.clone()).collect();
+        let dependencies: Vec<Vec<String>> = file
+            .definitions
+            .structs
+            .iter()
+            .map(|definition| definition.members.iter().filter_map(|member| referenced_type(&member.data_type)).collect())
+            .collect();
+
+        let order: Vec<usize> = dependency_order("Struct", &names, &dependencies)?;
+        permute(&mut file.definitions.structs, &order);
+
+        // Sort messages by the messages they hold
+        let names: Vec<String> = file.definitions.messages.iter().map(|definition| definition.name.clone()).collect();
+        let dependencies: Vec<Vec<String>> = file
+            .definitions
+            .messages
+            .iter()
+            .map(|definition| {
+                definition
+                    .fields
+                    .iter()
+                    .filter_map(|field| match &field.data_type {
+                        FieldType::Empty | FieldType::Bytes(_) => None,
+                        FieldType::Value(value_type) => referenced_type(value_type)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let order: Vec<usize> = dependency_order("Message", &names, &dependencies)?;
+        permute(&mut file.definitions.messages, &order);
+    }
+
+    Ok(())
+}
+
+/// Move every item to its place in the given order, where `order[i]` is the index of the item that goes at position `i`. Items are swapped
+/// along the cycles of the permutation, so none of them is cloned
+fn permute<T>(items: &mut [T], order: &[usize]) {
+    let mut placed: Vec<bool> = vec![false; items.len()];
+
+    for start in 0..items.len() {
+        let mut position: usize = start;
+
+        while !placed[position] {
+            placed[position] = true;
+
+            let source: usize = order[position];
+            if source == start {
+                break;
+            }
+
+            items.swap(position, source);
+            position = source;
+        }
+    }
+}
+
+/// Name of the user defined type held by a value, if any
+fn referenced_type(value_type: &ValueType) -> Option<String> {
+    match value_type {
+        ValueType::Array(array) => match array.innermost_type() {
+            ArrayType::UserDefined(name, _) => Some(name.clone()),
+            _ => None
+        },
+        ValueType::UserDefined(name, _) => Some(name.clone()),
+        ValueType::Primitive(_) => None
+    }
+}
+
+/// Get the indexes of the definitions in an order where every definition comes after the definitions it depends on.
+/// Dependencies on names outside the list are ignored, as they are declared in other files, or are of another kind
+fn dependency_order(kind: &str, names: &[String], dependencies: &[Vec<String>]) -> Result<Vec<usize>, RuneParserError> {
+    let mut order: Vec<usize> = Vec::with_capacity(names.len());
+    let mut states: Vec<VisitState> = vec![VisitState::Unvisited; names.len()];
+    let mut path: Vec<usize> = Vec::with_capacity(names.len());
+
+    for index in 0..names.len() {
+        visit(kind, index, names, dependencies, &mut states, &mut path, &mut order)?;
+    }
+
+    Ok(order)
+}
+
+fn visit(
+    kind: &str,
+    index: usize,
+    names: &[String],
+    dependencies: &[Vec<String>],
+    states: &mut [VisitState],
+    path: &mut Vec<usize>,
+    order: &mut Vec<usize>
+) -> Result<(), RuneParserError> {
+    match states[index] {
+        VisitState::Visited => return Ok(()),
+        VisitState::Visiting => {
+            // The definition is already on the path, so it ends up containing itself. Reported the same way as recursion found while linking
+            let start: usize = path.iter().position(|&entry| entry == index).unwrap_or_default();
+            let cycle: Vec<&str> = path[start..].iter().chain([&index]).map(|&entry| names[entry].as_str()).collect();
+
+            error!("{0} {1} contains itself through {2}", kind, names[index], cycle.join(" -> "));
+            return Err(RuneParserError::RecursiveType);
+        },
+        VisitState::Unvisited => ()
+    }
+
+    states[index] = VisitState::Visiting;
+    path.push(index);
+
+    for dependency in &dependencies[index] {
+        if let Some(dependency_index) = names.iter().position(|name| name == dependency) {
+            visit(kind, dependency_index, names, dependencies, states, path, order)?;
+        }
+    }
+
+    path.pop();
+    states[index] = VisitState::Visited;
+    order.push(index);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::permute;
+    use crate::{test_utils::parse_files, types::Declaration, RuneParserError};
+
+    fn struct_names(files: &[crate::RuneFileDescription]) -> Vec<&str> {
+        files[0].definitions.structs.iter().map(|definition| definition.name.as_str()).collect()
+    }
+
+    #[test]
+    fn struct_is_moved_after_a_later_declared_struct_it_holds() {
+        let source = "struct Outer {\n    inner: Inner = 0;\n    flag: u8 = 1;\n}\n\nstruct Inner {\n    value: u32 = 0;\n}\n";
+        let files = parse_files(&[("later.rune", source)]).unwrap();
+
+        assert_eq!(struct_names(&files), ["Inner", "Outer"]);
+        assert_eq!(files[0].definitions.find_struct("Outer").unwrap().flat_size().unwrap(), 5);
+
+        // The declaration order still follows the source
+        assert_eq!(files[0].definitions.declaration_order, [Declaration::Struct(String::from("Outer")), Declaration::Struct(String::from("Inner"))]);
+    }
+
+    #[test]
+    fn declaration_order_is_kept_without_dependencies() {
+        let source = "struct B {\n    a: u8 = 0;\n}\n\nstruct A {\n    b: B = 0;\n}\n\nstruct C {\n    a: u8 = 0;\n}\n";
+        let files = parse_files(&[("kept.rune", source)]).unwrap();
+
+        assert_eq!(struct_names(&files), ["B", "A", "C"]);
+    }
+
+    #[test]
+    fn self_referential_struct_is_recursive() {
+        let result = parse_files(&[("self.rune", "struct A {\n    a: A = 0;\n}\n")]);
+        assert!(matches!(result, Err(RuneParserError::RecursiveType)));
+    }
+
+    #[test]
+    fn mutually_referential_structs_are_recursive() {
+        let result = parse_files(&[("mutual.rune", "struct A {\n    b: B = 0;\n}\n\nstruct B {\n    a: A = 0;\n}\n")]);
+        assert!(matches!(result, Err(RuneParserError::RecursiveType)));
+    }
+
+    #[test]
+    fn mutually_referential_structs_across_files_are_recursive() {
+        let result = parse_files(&[
+            ("a.rune", "include \"b\";\n\nstruct A {\n    b: B = 0;\n}\n"),
+            ("b.rune", "struct B {\n    a: A = 0;\n}\n")
+        ]);
+        assert!(matches!(result, Err(RuneParserError::RecursiveType)));
+    }
+
+    #[test]
+    fn diamond_is_not_recursive() {
+        let source = "struct Top {\n    left: Left = 0;\n    right: Right = 1;\n}\n\nstruct Left {\n    base: Base = 0;\n}\n\n\
+                      struct Right {\n    base: Base = 0;\n}\n\nstruct Base {\n    value: u16 = 0;\n}\n";
+        let files = parse_files(&[("diamond.rune", source)]).unwrap();
+
+        assert_eq!(struct_names(&files), ["Base", "Left", "Right", "Top"]);
+        assert_eq!(files[0].definitions.find_struct("Top").unwrap().flat_size().unwrap(), 4);
+    }
+
+    #[test]
+    fn permute_follows_every_cycle() {
+        let mut items = vec!['a', 'b', 'c', 'd', 'e'];
+        permute(&mut items, &[2, 0, 1, 4, 3]);
+
+        assert_eq!(items, ['c', 'a', 'b', 'e', 'd']);
+    }
+}
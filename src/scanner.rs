@@ -1,7 +1,12 @@
 use core::fmt;
 use std::{
+    borrow::Cow,
+    collections::HashSet,
     fmt::{Display, Formatter},
-    ops::{Deref, DerefMut}
+    num::{IntErrorKind, ParseIntError},
+    ops::{Deref, DerefMut},
+    rc::Rc,
+    str::Chars
 };
 
 use crate::{output::*, types::Primitive};
@@ -70,6 +75,41 @@ impl<T> Spanned<T> {
     }
 }
 
+/// Renders the source line a span starts on, with carets under the spanned characters, in the style of rustc diagnostics
+pub fn render_source_span(source: &str, from: Position, to: Position) -> String {
+    let line_text = match source.lines().nth((from.line as usize).saturating_sub(1)) {
+        Some(text) => text,
+        None => return format!(" --> line {0}", from.line)
+    };
+
+    let gutter = " ".repeat(from.line.to_string().len());
+
+    let offset = match from.offset {
+        Some(offset) => offset as usize,
+        None => return format!("{0}--> line {1}\n{0} |\n{1} | {2}", gutter, from.line, line_text)
+    };
+
+    // Spans reaching past the line they start on are only marked until the end of it
+    let width = match (to.line == from.line, to.offset) {
+        (true, Some(to_offset)) if to_offset as usize > offset => to_offset as usize - offset,
+        (false, _) => line_text.chars().count().saturating_sub(offset).max(1),
+        _ => 1
+    };
+
+    // Keep tabs in the indentation of the marker, so that it lines up with the source line however tabs are displayed
+    let indentation: String = line_text.chars().take(offset).map(|character| if character == '\t' { '\t' } else { ' ' }).collect();
+
+    format!(
+        "{0}--> line {1}, column {2}\n{0} |\n{1} | {3}\n{0} | {4}{5}",
+        gutter,
+        from.line,
+        offset + 1,
+        line_text,
+        indentation,
+        "^".repeat(width)
+    )
+}
+
 impl<T> Deref for Spanned<T> {
     type Target = T;
 
@@ -85,13 +125,16 @@ impl<T> DerefMut for Spanned<T> {
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum NumeralSystem {
     Binary,
     Decimal,
-    Hexadecimal
+    Hexadecimal,
+    Octal
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum NumericLiteral {
     AsciiChar(char),
     Boolean(bool),
@@ -112,6 +155,9 @@ impl NumericLiteral {
     }
 }
 
+/// Writes the literal the way it would be declared in a Rune file. Integers and finite floats are scanned back to the same value by
+/// [`Scanner::extract_number`]: decimal and octal integers round-trip exactly, while binary and hexadecimal integers keep their value and
+/// numeral system, but might gain leading zeros
 impl Display for NumericLiteral {
     fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
         match self {
@@ -128,18 +174,23 @@ impl Display for NumericLiteral {
             },
 
             NumericLiteral::Boolean(boolean) => write!(formatter, "{0}", boolean),
+
+            // Always write a decimal point for whole numbers, so the value is scanned back as a float
+            NumericLiteral::Float(float) if float.is_finite() && float.fract() == 0.0 => write!(formatter, "{0}.0", float),
             NumericLiteral::Float(float) => write!(formatter, "{0}", float),
 
             NumericLiteral::PositiveInteger(value, numeral_system) => match numeral_system {
                 NumeralSystem::Binary => write!(formatter, "0b{0:02b}", value),
                 NumeralSystem::Decimal => write!(formatter, "{0}", value),
-                NumeralSystem::Hexadecimal => write!(formatter, "0x{0:02X}", value)
+                NumeralSystem::Hexadecimal => write!(formatter, "0x{0:02X}", value),
+                NumeralSystem::Octal => write!(formatter, "0o{0:o}", value)
             },
 
             NumericLiteral::NegativeInteger(value, numeral_system) => match numeral_system {
-                NumeralSystem::Binary => write!(formatter, "-0b{0:02b}", value.abs()),
+                NumeralSystem::Binary => write!(formatter, "-0b{0:02b}", value.unsigned_abs()),
                 NumeralSystem::Decimal => write!(formatter, "{0}", value),
-                NumeralSystem::Hexadecimal => write!(formatter, "-0x{0:02X}", value.abs())
+                NumeralSystem::Hexadecimal => write!(formatter, "-0x{0:02X}", value.unsigned_abs()),
+                NumeralSystem::Octal => write!(formatter, "-0o{0:o}", value.unsigned_abs())
             }
         }
     }
@@ -198,31 +249,64 @@ impl PartialEq for NumericLiteral {
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Token {
+    Annotation(String),
+    Asterisk,
     Bitfield,
     Comma,
     Colon,
     Comment(String),
     Define,
+    Deprecate,
+    DoubleColon,
     Enum,
     Equals,
     Extend,
-    Identifier(String),
+    /// Interned by the scanner, so every identifier token with the same name shares its text
+    Identifier(Rc<str>),
     Include,
     LeftBrace,
     LeftBracket,
+    LeftParenthesis,
     Message,
+    Minus,
     NumericLiteral(NumericLiteral),
     NumericRange(NumericLiteral, NumericLiteral),
+    Plus,
+    QuestionMark,
     Redefine,
     Reserve,
     RightBrace,
     RightBracket,
+    RightParenthesis,
     SemiColon,
+    Slash,
     StringLiteral(String),
     Struct,
     Verifier
 }
 
+impl Token {
+    /// Word a keyword token is written as, or None if the token is not a keyword
+    pub fn keyword(&self) -> Option<&'static str> {
+        match self {
+            Token::Bitfield => Some("bitfield"),
+            Token::Define => Some("define"),
+            Token::Deprecate => Some("deprecate"),
+            Token::Enum => Some("enum"),
+            Token::Extend => Some("extend"),
+            Token::Include => Some("include"),
+            Token::Message => Some("message"),
+            Token::NumericLiteral(NumericLiteral::Boolean(false)) => Some("false"),
+            Token::NumericLiteral(NumericLiteral::Boolean(true)) => Some("true"),
+            Token::Redefine => Some("redefine"),
+            Token::Reserve => Some("reserve"),
+            Token::Struct => Some("struct"),
+            Token::Verifier => Some("verifier"),
+            _ => None
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum ScanningProduct {
     Skip,
@@ -239,13 +323,35 @@ pub enum ScanningError {
     UnexpectedEndOfFileWhileParsing { token_kind: &'static str, start_position: Position }
 }
 
+impl ScanningError {
+    /// Describes the error along with the offending line of the source it was scanned from
+    pub fn render_diagnostic(&self, source: &str) -> String {
+        match self {
+            ScanningError::UnexpectedCharacter(character) => {
+                format!("Unexpected character '{0}'\n{1}", character.item, render_source_span(source, character.from, character.to))
+            },
+            ScanningError::InvalidLiteral(span) => format!("Invalid literal\n{0}", render_source_span(source, span.from, span.to)),
+            ScanningError::UnexpectedEndOfFile => String::from("Unexpected end of file"),
+            ScanningError::UnexpectedEndOfFileWhileParsing { token_kind, start_position } => format!(
+                "Unexpected end of file while scanning {0}\n{1}",
+                token_kind,
+                render_source_span(source, *start_position, *start_position)
+            )
+        }
+    }
+}
+
 type ScanningResult = Result<ScanningProduct, ScanningError>;
 
 pub struct Scanner<ScannerIterator: Iterator<Item = char>> {
     input:  ScannerIterator,
     line:   u32,
     offset: u32,
-    peeked: Option<char>
+    peeked: Option<char>,
+    /// Reused for the text of identifiers and annotations, which is only copied once it is known not to be a keyword
+    buffer:      String,
+    /// Text of every identifier scanned so far, so that repeated identifiers share a single allocation
+    identifiers: HashSet<Rc<str>>
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -254,7 +360,115 @@ enum NumberType {
     Binary,
     Decimal,
     Float,
-    Hexadecimal
+    Hexadecimal,
+    Octal
+}
+
+/// Iterator scanning tokens one at a time as they are requested, stopping at the end of the input. Scanning resumes at the next
+/// character after an error
+pub struct Tokens<ScannerIterator: Iterator<Item = char>> {
+    scanner: Scanner<ScannerIterator>
+}
+
+impl<ScannerIterator: Iterator<Item = char>> Iterator for Tokens<ScannerIterator> {
+    type Item = Result<Spanned<Token>, ScanningError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.scanner.scan_token() {
+                Ok(ScanningProduct::Skip) => (),
+                Ok(ScanningProduct::Finished) => return None,
+                Ok(ScanningProduct::Token(token)) => return Some(Ok(token)),
+                Err(error) => return Some(Err(error))
+            }
+        }
+    }
+}
+
+/// Comment attached to the token following it when scanning with trivia
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trivia {
+    pub comment:  String,
+    /// Whether the comment shares its line with the end of the token before it, rather than standing on its own line
+    pub trailing: bool
+}
+
+/// Token scanned with trivia, along with the comments written before it
+#[derive(Debug, Clone)]
+pub struct TriviaToken {
+    pub token:  Spanned<Token>,
+    pub trivia: Vec<Spanned<Trivia>>
+}
+
+impl TriviaToken {
+    /// Comments written before the token, in the order they appear
+    pub fn trivia(&self) -> &[Spanned<Trivia>] {
+        &self.trivia
+    }
+}
+
+/// Iterator scanning tokens like [`Tokens`], attaching comments to the token following them instead of scanning them as comment tokens.
+/// Comments after the last token have nothing to attach to, and are still scanned as comment tokens
+pub struct TriviaTokens<ScannerIterator: Iterator<Item = char>> {
+    scanner:   Scanner<ScannerIterator>,
+    /// Comments waiting for the next token to be attached to
+    pending:   Vec<Spanned<Trivia>>,
+    /// Line the last token ended on, to tell trailing comments from those on their own line
+    last_line: Option<u32>
+}
+
+impl<ScannerIterator: Iterator<Item = char>> TriviaTokens<ScannerIterator> {
+    /// Scan the whole input, stopping at the first error
+    pub fn scan_all(self) -> Result<Vec<TriviaToken>, ScanningError> {
+        self.collect()
+    }
+}
+
+impl<ScannerIterator: Iterator<Item = char>> Iterator for TriviaTokens<ScannerIterator> {
+    type Item = Result<TriviaToken, ScanningError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.scanner.scan_token() {
+                Ok(ScanningProduct::Skip) => (),
+                Ok(ScanningProduct::Token(Spanned { item: Token::Comment(comment), from, to })) => {
+                    let trivia = Trivia {
+                        comment,
+                        trailing: self.last_line == Some(from.line)
+                    };
+                    self.pending.push(Spanned::new(trivia, from, to));
+                },
+                Ok(ScanningProduct::Token(token)) => {
+                    self.last_line = Some(token.to.line);
+                    let trivia = std::mem::take(&mut self.pending);
+                    return Some(Ok(TriviaToken { token, trivia }));
+                },
+                Ok(ScanningProduct::Finished) if !self.pending.is_empty() => {
+                    let comment = self.pending.remove(0);
+                    let token = Spanned::new(Token::Comment(comment.item.comment), comment.from, comment.to);
+                    return Some(Ok(TriviaToken { token, trivia: Vec::new() }));
+                },
+                Ok(ScanningProduct::Finished) => return None,
+                Err(error) => return Some(Err(error))
+            }
+        }
+    }
+}
+
+impl<ScannerIterator: Iterator<Item = char>> IntoIterator for Scanner<ScannerIterator> {
+    type Item = Result<Spanned<Token>, ScanningError>;
+    type IntoIter = Tokens<ScannerIterator>;
+
+    fn into_iter(self) -> Tokens<ScannerIterator> {
+        Tokens { scanner: self }
+    }
+}
+
+impl<'a> Scanner<Chars<'a>> {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(source: &'a str) -> Self {
+        Scanner::new(source.chars())
+    }
 }
 
 impl<ScannerIterator: Iterator<Item = char>> Scanner<ScannerIterator> {
@@ -263,24 +477,70 @@ impl<ScannerIterator: Iterator<Item = char>> Scanner<ScannerIterator> {
             input,
             line: 1,
             offset: 0,
-            peeked: None
+            peeked: None,
+            buffer: String::new(),
+            identifiers: HashSet::new()
         }
     }
 
-    pub fn scan_all(mut self) -> Result<Vec<Spanned<Token>>, ScanningError> {
+    /// Attach comments to the token following them, retrieved through [`TriviaToken::trivia`], instead of scanning them as comment tokens
+    pub fn with_trivia(self) -> TriviaTokens<ScannerIterator> {
+        TriviaTokens {
+            scanner:   self,
+            pending:   Vec::new(),
+            last_line: None
+        }
+    }
+
+    /// Scan the whole input, stopping at the first error
+    pub fn scan_all(self) -> Result<Vec<Spanned<Token>>, ScanningError> {
+        let (output, errors) = self.scan_all_collecting();
+
+        match errors.into_iter().next() {
+            Some(error) => Err(error),
+            None => Ok(output)
+        }
+    }
+
+    /// Scan the whole input, recovering from unexpected characters and invalid literals so every error in the input is reported
+    pub fn scan_all_collecting(mut self) -> (Vec<Spanned<Token>>, Vec<ScanningError>) {
         let mut output = Vec::new();
+        let mut errors = Vec::new();
 
         loop {
-            match self.scan_token()? {
-                ScanningProduct::Skip => (),
-                ScanningProduct::Finished => return Ok(output),
-                ScanningProduct::Token(token) => {
+            match self.scan_token() {
+                Ok(ScanningProduct::Skip) => (),
+                Ok(ScanningProduct::Finished) => return (output, errors),
+                Ok(ScanningProduct::Token(token)) => {
                     output.push(token);
+                },
+
+                // Skip the rest of the broken lexeme, and keep scanning from the next whitespace or delimiter
+                Err(error @ (ScanningError::UnexpectedCharacter(_) | ScanningError::InvalidLiteral(_))) => {
+                    errors.push(error);
+                    self.skip_to_delimiter();
+                },
+
+                // Nothing is left to recover after reaching the end of the input
+                Err(error) => {
+                    errors.push(error);
+                    return (output, errors);
                 }
             }
         }
     }
 
+    /// Skip the rest of a broken lexeme, so scanning can resume from the next whitespace or delimiter
+    fn skip_to_delimiter(&mut self) {
+        while let Some(character) = self.peek() {
+            if character.is_whitespace() || matches!(character, '{' | '}' | '[' | ']' | '(' | ')' | ';' | ',' | ':' | '=') {
+                break;
+            }
+
+            self.advance();
+        }
+    }
+
     pub fn advance(&mut self) -> Option<char> {
         self.offset += 1;
         match self.peeked {
@@ -303,10 +563,16 @@ impl<ScannerIterator: Iterator<Item = char>> Scanner<ScannerIterator> {
     }
 
     pub fn keyword(&self, what: &str) -> Option<Token> {
-        match what.to_owned().to_lowercase().as_str() {
+        // Keywords are case insensitive, but most identifiers need no lowercasing, and so no allocation, to be compared
+        let lowercase: Cow<str> = match what.chars().any(char::is_uppercase) {
+            true => Cow::Owned(what.to_lowercase()),
+            false => Cow::Borrowed(what)
+        };
+
+        match lowercase.as_ref() {
             "bitfield" => Some(Token::Bitfield),
             "define" => Some(Token::Define),
-            "deprecate" /* Alias for reserve */ => Some(Token::Reserve),
+            "deprecate" => Some(Token::Deprecate),
             "enum" => Some(Token::Enum),
             "extend" => Some(Token::Extend),
             "false" => Some(Token::NumericLiteral(NumericLiteral::Boolean(false))),
@@ -331,11 +597,14 @@ impl<ScannerIterator: Iterator<Item = char>> Scanner<ScannerIterator> {
     pub fn scan_identifier(&mut self) -> ScanningResult {
         let from = self.position();
 
-        let mut identifier = String::new();
+        self.buffer.clear();
 
         loop {
             match self.peek() {
-                Some(character) if character.is_alphanumeric() || character == '_' => identifier.push(self.advance().unwrap()),
+                Some(character) if character.is_alphanumeric() || character == '_' => {
+                    self.advance();
+                    self.buffer.push(character)
+                },
                 _ => {
                     break;
                 }
@@ -344,12 +613,64 @@ impl<ScannerIterator: Iterator<Item = char>> Scanner<ScannerIterator> {
 
         let to = self.position();
 
-        Ok(match self.keyword(&identifier) {
+        Ok(match self.keyword(&self.buffer) {
             Some(k) => ScanningProduct::Token(Spanned::new(k, from, to)),
-            None => ScanningProduct::Token(Spanned::new(Token::Identifier(identifier), from, to))
+            None => ScanningProduct::Token(Spanned::new(Token::Identifier(self.intern_identifier()), from, to))
         })
     }
 
+    /// Shared text of the identifier in the buffer, which is only allocated the first time the identifier is scanned
+    fn intern_identifier(&mut self) -> Rc<str> {
+        if let Some(identifier) = self.identifiers.get(self.buffer.as_str()) {
+            return identifier.clone();
+        }
+
+        let identifier: Rc<str> = Rc::from(self.buffer.as_str());
+        self.identifiers.insert(identifier.clone());
+        identifier
+    }
+
+    pub fn scan_annotation(&mut self) -> ScanningResult {
+        let mut from = self.position();
+        // We want to include the @ in the span
+        from.offset = from.offset.map(|v| v - 1);
+
+        self.buffer.clear();
+
+        loop {
+            match self.peek() {
+                Some(character) if character.is_alphanumeric() || character == '_' => {
+                    self.advance();
+                    self.buffer.push(character)
+                },
+                _ => {
+                    break;
+                }
+            }
+        }
+
+        if self.buffer.is_empty() {
+            error!("Expected an annotation name after '@'");
+            return Err(ScanningError::InvalidLiteral(Spanned::new((), from, self.position())));
+        }
+
+        let name = self.buffer.clone();
+
+        Ok(ScanningProduct::Token(Spanned::new(Token::Annotation(name), from, self.position())))
+    }
+
+    /// Logs why an integer literal could not be parsed. Literals only hold 64 bit values, so wider values cannot be represented yet
+    fn integer_error(error: &ParseIntError, from: Position, to: Position) -> ScanningError {
+        match error.kind() {
+            IntErrorKind::PosOverflow | IntErrorKind::NegOverflow => {
+                error!("Numeric value on line {0} does not fit in 64 bits. Values beyond 64 bits cannot be represented yet", from.line)
+            },
+            _ => error!("Could not parse numeric value! Got error {0}", error)
+        }
+
+        ScanningError::InvalidLiteral(Spanned::new((), from, to))
+    }
+
     pub fn extract_number(string: &mut String, from: Position, to: Position) -> Result<NumericLiteral, ScanningError> {
         if string.is_empty() {
             error!("Tried parsing an empty literal numeric value!");
@@ -357,55 +678,44 @@ impl<ScannerIterator: Iterator<Item = char>> Scanner<ScannerIterator> {
         }
 
         // Get whether number is negative
-        let is_negative: bool = string.chars().nth(0).unwrap() == '-';
+        let is_negative: bool = string.starts_with('-');
 
-        // Get number type
-        let number_type: NumberType = match string {
+        // Get number type from the prefix following the sign. Hexadecimal digits can contain 'b', so only the prefix can be trusted
+        let digits: &str = string.strip_prefix('-').unwrap_or(string);
+
+        let number_type: NumberType = match digits {
             // Float - First, as hexadecimal floats are a thing apparently...
-            _ if string.contains('.') => NumberType::Float,
+            _ if digits.contains('.') => NumberType::Float,
 
             // Binary
-            _ if string.contains("0b") => {
-                let index = string.find("0b").unwrap();
-                if !(string.remove(index) == '0' && string.remove(index) == 'b') {
-                    error!("Something went wrong in parsing binary literal!");
-                    return Err(ScanningError::InvalidLiteral(Spanned::new((), from, to)));
-                }
-
-                NumberType::Binary
-            },
-            _ if string.contains("0B") => {
-                let index = string.find("0B").unwrap();
-                if !(string.remove(index) == '0' && string.remove(index) == 'B') {
-                    error!("Something went wrong in parsing binary literal!");
-                    return Err(ScanningError::InvalidLiteral(Spanned::new((), from, to)));
-                }
-
-                NumberType::Binary
-            },
+            _ if digits.starts_with("0b") || digits.starts_with("0B") => NumberType::Binary,
 
             // Hexadecimal
-            _ if string.contains("0x") => {
-                let index = string.find("0x").unwrap();
-                if !(string.remove(index) == '0' && string.remove(index) == 'x') {
-                    error!("Something went wrong in parsing hexadecimal literal!");
-                    return Err(ScanningError::InvalidLiteral(Spanned::new((), from, to)));
-                }
+            _ if digits.starts_with("0x") || digits.starts_with("0X") => NumberType::Hexadecimal,
 
-                NumberType::Hexadecimal
-            },
-            _ if string.contains("0X") => {
-                let index = string.find("0X").unwrap();
-                if !(string.remove(index) == '0' && string.remove(index) == 'X') {
-                    error!("Something went wrong in parsing hexadecimal literal!");
-                    return Err(ScanningError::InvalidLiteral(Spanned::new((), from, to)));
-                }
+            // Octal
+            _ if digits.starts_with("0o") || digits.starts_with("0O") => NumberType::Octal,
+
+            // Float in scientific notation without a decimal point, such as 1e-9. Checked after hexadecimal, whose digits can contain 'e'
+            _ if digits.contains(['e', 'E']) => NumberType::Float,
 
-                NumberType::Hexadecimal
-            },
             _ => NumberType::Decimal
         };
 
+        // Remove the numeral system prefix while keeping the sign, so the remaining string can be parsed with the right radix
+        if matches!(number_type, NumberType::Binary | NumberType::Hexadecimal | NumberType::Octal) {
+            let index: usize = is_negative as usize;
+            string.replace_range(index..(index + 2), "");
+        }
+
+        // Remove digit separators, which may only be placed between two digits, such as 1_000 or 0xDEAD_BEEF
+        let digits: &str = string.strip_prefix('-').unwrap_or(string);
+        if digits.starts_with('_') || digits.ends_with('_') || digits.contains("__") {
+            error!("Invalid digit separator in numeric value {0}! Underscores may only be placed between digits", string);
+            return Err(ScanningError::InvalidLiteral(Spanned::new((), from, to)));
+        }
+        string.retain(|character| character != '_');
+
         match number_type {
             NumberType::Float => match string.parse::<f64>() {
                 Err(error) => {
@@ -420,17 +730,11 @@ impl<ScannerIterator: Iterator<Item = char>> Scanner<ScannerIterator> {
 
                 match is_negative {
                     true => match i64::from_str_radix(string, 2) {
-                        Err(error) => {
-                            error!("Could not parse numeric value! Got error {0}", error);
-                            Err(ScanningError::InvalidLiteral(Spanned::new((), from, to)))
-                        },
+                        Err(error) => Err(Self::integer_error(&error, from, to)),
                         Ok(value) => Ok(NumericLiteral::NegativeInteger(value, numeral_system))
                     },
                     false => match u64::from_str_radix(string, 2) {
-                        Err(error) => {
-                            error!("Could not parse numeric value! Got error {0}", error);
-                            Err(ScanningError::InvalidLiteral(Spanned::new((), from, to)))
-                        },
+                        Err(error) => Err(Self::integer_error(&error, from, to)),
                         Ok(value) => Ok(NumericLiteral::PositiveInteger(value, numeral_system))
                     }
                 }
@@ -440,17 +744,11 @@ impl<ScannerIterator: Iterator<Item = char>> Scanner<ScannerIterator> {
 
                 match is_negative {
                     true => match string.parse::<i64>() {
-                        Err(error) => {
-                            error!("Could not parse numeric value! Got error {0}", error);
-                            Err(ScanningError::InvalidLiteral(Spanned::new((), from, to)))
-                        },
+                        Err(error) => Err(Self::integer_error(&error, from, to)),
                         Ok(value) => Ok(NumericLiteral::NegativeInteger(value, numeral_system))
                     },
                     false => match string.parse::<u64>() {
-                        Err(error) => {
-                            error!("Could not parse numeric value! Got error {0}", error);
-                            Err(ScanningError::InvalidLiteral(Spanned::new((), from, to)))
-                        },
+                        Err(error) => Err(Self::integer_error(&error, from, to)),
                         Ok(value) => Ok(NumericLiteral::PositiveInteger(value, numeral_system))
                     }
                 }
@@ -460,17 +758,25 @@ impl<ScannerIterator: Iterator<Item = char>> Scanner<ScannerIterator> {
 
                 match is_negative {
                     true => match i64::from_str_radix(string, 16) {
-                        Err(error) => {
-                            error!("Could not parse numeric value! Got error {0}", error);
-                            Err(ScanningError::InvalidLiteral(Spanned::new((), from, to)))
-                        },
+                        Err(error) => Err(Self::integer_error(&error, from, to)),
                         Ok(value) => Ok(NumericLiteral::NegativeInteger(value, numeral_system))
                     },
                     false => match u64::from_str_radix(string, 16) {
-                        Err(error) => {
-                            error!("Could not parse numeric value! Got error {0}", error);
-                            Err(ScanningError::InvalidLiteral(Spanned::new((), from, to)))
-                        },
+                        Err(error) => Err(Self::integer_error(&error, from, to)),
+                        Ok(value) => Ok(NumericLiteral::PositiveInteger(value, numeral_system))
+                    }
+                }
+            },
+            NumberType::Octal => {
+                let numeral_system: NumeralSystem = NumeralSystem::Octal;
+
+                match is_negative {
+                    true => match i64::from_str_radix(string, 8) {
+                        Err(error) => Err(Self::integer_error(&error, from, to)),
+                        Ok(value) => Ok(NumericLiteral::NegativeInteger(value, numeral_system))
+                    },
+                    false => match u64::from_str_radix(string, 8) {
+                        Err(error) => Err(Self::integer_error(&error, from, to)),
                         Ok(value) => Ok(NumericLiteral::PositiveInteger(value, numeral_system))
                     }
                 }
@@ -479,9 +785,8 @@ impl<ScannerIterator: Iterator<Item = char>> Scanner<ScannerIterator> {
     }
 
     pub fn scan_char(&mut self) -> ScanningResult {
-        let starting_from = self.position();
-        let mut from = starting_from;
-        from.offset = from.offset.map(|v| v - 1);
+        // Called before the opening ' is consumed, so the current position is already the start of the literal
+        let from = self.position();
 
         // Advance past the ' that caused this function to be called
         self.advance();
@@ -546,7 +851,7 @@ impl<ScannerIterator: Iterator<Item = char>> Scanner<ScannerIterator> {
             1 => text.chars().nth(0).unwrap(),
             // We already checked that the escape character was the first one when > 1 characters
             2 => match text.chars().nth(1).unwrap() {
-                character if character.is_numeric() => u8::from_str_radix(&character.to_string(), 10).unwrap() as char,
+                character if character.is_numeric() => character.to_string().parse::<u8>().unwrap() as char,
                 'a' => 0x07 as char,
                 'b' => 0x08 as char,
                 'e' => 0x1B as char,
@@ -564,14 +869,22 @@ impl<ScannerIterator: Iterator<Item = char>> Scanner<ScannerIterator> {
                 }
             },
             3.. => match text.chars().nth(1).unwrap() {
-                character if character.is_numeric() => match u8::from_str_radix(&text[1..], 10) {
+                character if character.is_numeric() => match text[1..].parse::<u8>() {
                     Ok(value) => value as char,
                     Err(_) => {
                         error!("Invalid escape sequence {0} found", text);
                         return Err(ScanningError::InvalidLiteral(Spanned::new((), from, self.position())));
                     }
                 },
-                'u' => match u8::from_str_radix(&text[2..], 10) {
+                // Unicode codepoint in hexadecimal, such as \u{1F600}
+                'u' if text[2..].starts_with('{') && text.ends_with('}') => match u32::from_str_radix(&text[3..(text.len() - 1)], 16).ok().and_then(char::from_u32) {
+                    Some(value) => value,
+                    None => {
+                        error!("Invalid unicode escape sequence {0} found", text);
+                        return Err(ScanningError::InvalidLiteral(Spanned::new((), from, self.position())));
+                    }
+                },
+                'u' => match text[2..].parse::<u8>() {
                     Ok(value) => value as char,
                     Err(_) => {
                         error!("Invalid escape sequence {0} found", text);
@@ -598,17 +911,30 @@ impl<ScannerIterator: Iterator<Item = char>> Scanner<ScannerIterator> {
     }
 
     pub fn scan_numerics(&mut self) -> ScanningResult {
-        let starting_from = self.position();
-        let mut from = starting_from;
-        from.offset = from.offset.map(|v| v - 1);
+        // Called before the first character is consumed, so the current position is already the start of the literal
+        self.continue_numerics(self.position(), String::new())
+    }
 
-        let mut text: String = String::new();
+    /// Whether the last number of the text ends with the exponent mark of a decimal number. In hexadecimal numbers, such as 0x1e, 'e' is a digit
+    fn ends_with_exponent(text: &str) -> bool {
+        let number: &str = text.rsplit("..").next().unwrap_or(text).trim_start();
+        let digits: &str = number.strip_prefix('-').unwrap_or(number);
 
+        digits.ends_with(['e', 'E']) && !digits.starts_with("0x") && !digits.starts_with("0X")
+    }
+
+    /// Scan the rest of a numeric literal whose first characters, such as a minus sign, have already been consumed
+    fn continue_numerics(&mut self, from: Position, mut text: String) -> ScanningResult {
         // Parse a whole number. Ranges should be handled elsewhere.
         while self.peek().is_some() {
             match self.peek().unwrap() {
-                '-' | '_' | '.' | ' ' => text.push(self.advance().unwrap()),
-                character if character.is_alphanumeric() => text.push(self.advance().unwrap()),
+                '_' | '.' | ' ' => text.push(self.advance().unwrap()),
+                // Sign of the number, of the end of a range, or of an exponent. Any other minus is a subtraction, such as in (SIZE - 1)
+                '-' if text.is_empty() || text.trim_end().ends_with("..") || Self::ends_with_exponent(&text) => text.push(self.advance().unwrap()),
+                // Explicitly positive exponent, such as 1e+9
+                '+' if Self::ends_with_exponent(&text) => text.push(self.advance().unwrap()),
+                // Spaces are only part of a number around the dots of a range, so a word following a number is a token of its own
+                character if character.is_alphanumeric() && (!text.ends_with(' ') || text.trim_end().ends_with("..")) => text.push(self.advance().unwrap()),
 
                 // End of number
                 _ => break
@@ -653,6 +979,40 @@ impl<ScannerIterator: Iterator<Item = char>> Scanner<ScannerIterator> {
                 '"' => {
                     return Ok(ScanningProduct::Token(Spanned::new(Token::StringLiteral(string), from, self.position())));
                 },
+
+                // Escape sequence
+                '\\' => {
+                    let escaped = self.advance().ok_or(ScanningError::UnexpectedEndOfFileWhileParsing {
+                        token_kind:     "string_literal",
+                        start_position: from
+                    })?;
+
+                    let character: char = match escaped {
+                        'n' => '\n',
+                        'r' => '\r',
+                        't' => '\t',
+                        '\\' => '\\',
+                        '"' => '"',
+                        'x' => {
+                            let digits: String = [self.advance(), self.advance()].into_iter().flatten().collect();
+
+                            match u8::from_str_radix(&digits, 16) {
+                                Ok(value) if digits.len() == 2 => value as char,
+                                _ => {
+                                    error!("Invalid escape sequence \\x{0} found in string literal", digits);
+                                    return Err(ScanningError::InvalidLiteral(Spanned::new((), from, self.position())));
+                                }
+                            }
+                        },
+                        _ => {
+                            error!("Invalid escape sequence \\{0} found in string literal", escaped);
+                            return Err(ScanningError::InvalidLiteral(Spanned::new((), from, self.position())));
+                        }
+                    };
+
+                    string.push(character);
+                },
+
                 character => string.push(character)
             }
         }
@@ -668,7 +1028,11 @@ impl<ScannerIterator: Iterator<Item = char>> Scanner<ScannerIterator> {
             }
         };
 
-        let to = self.position();
+        // Single character tokens end right after the character
+        let to = Position {
+            line:   from.line,
+            offset: from.offset.map(|offset| offset + 1)
+        };
         let token = |t| Ok(ScanningProduct::Token(Spanned::new(t, from, to)));
 
         match character {
@@ -702,7 +1066,6 @@ impl<ScannerIterator: Iterator<Item = char>> Scanner<ScannerIterator> {
                     Some('*') => {
                         self.advance();
 
-                        let from = self.position();
                         let mut comment = String::new();
 
                         loop {
@@ -734,8 +1097,26 @@ impl<ScannerIterator: Iterator<Item = char>> Scanner<ScannerIterator> {
                             }
                         }
                     },
-                    Some(c) => Err(ScanningError::UnexpectedCharacter(Spanned::new(c, self.position(), self.position()))),
-                    None => Err(ScanningError::UnexpectedEndOfFile)
+                    // Division in a define expression
+                    _ => token(Token::Slash)
+                }
+            },
+
+            '*' => {
+                self.advance();
+                token(Token::Asterisk)
+            },
+            '+' => {
+                self.advance();
+                token(Token::Plus)
+            },
+            '-' => {
+                self.advance();
+
+                // A minus directly followed by a digit is the sign of a numeric literal
+                match self.peek() {
+                    Some(character) if character.is_numeric() => self.continue_numerics(from, String::from("-")),
+                    _ => token(Token::Minus)
                 }
             },
 
@@ -751,7 +1132,19 @@ impl<ScannerIterator: Iterator<Item = char>> Scanner<ScannerIterator> {
 
             ':' => {
                 self.advance();
-                token(Token::Colon)
+
+                // Two colons separate a namespace from the name inside it, such as Foo::Header
+                match self.peek() {
+                    Some(':') => {
+                        self.advance();
+                        Ok(ScanningProduct::Token(Spanned::new(Token::DoubleColon, from, self.position())))
+                    },
+                    _ => token(Token::Colon)
+                }
+            },
+            '?' => {
+                self.advance();
+                token(Token::QuestionMark)
             },
             ';' => {
                 self.advance();
@@ -773,10 +1166,22 @@ impl<ScannerIterator: Iterator<Item = char>> Scanner<ScannerIterator> {
                 self.advance();
                 token(Token::RightBracket)
             },
+            '(' => {
+                self.advance();
+                token(Token::LeftParenthesis)
+            },
+            ')' => {
+                self.advance();
+                token(Token::RightParenthesis)
+            },
             '"' => {
                 self.advance();
                 self.scan_string_literal()
             },
+            '@' => {
+                self.advance();
+                self.scan_annotation()
+            },
             '\n' => {
                 self.advance();
                 self.line += 1;
@@ -785,7 +1190,7 @@ impl<ScannerIterator: Iterator<Item = char>> Scanner<ScannerIterator> {
             },
             '\'' => self.scan_char(),
 
-            character if character.is_numeric() || character == '-' => self.scan_numerics(),
+            character if character.is_numeric() => self.scan_numerics(),
             character if character.is_alphanumeric() || character == '_' => self.scan_identifier(),
             character if character.is_whitespace() => {
                 self.advance();
@@ -798,3 +1203,292 @@ impl<ScannerIterator: Iterator<Item = char>> Scanner<ScannerIterator> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::count_allocations;
+
+    const NO_POSITION: Position = Position { line: 0, offset: None };
+
+    /// Small xorshift generator, so that generated values are the same on every run
+    struct Values(u64);
+
+    impl Iterator for Values {
+        type Item = u64;
+
+        fn next(&mut self) -> Option<u64> {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            Some(self.0)
+        }
+    }
+
+    /// Integers to round-trip: the edges of every width, and pseudo random values of every magnitude
+    fn integers() -> Vec<u64> {
+        let edges = [0, 1, 2, 7, 8, 9, 10, 15, 16, 255, 256, u32::MAX as u64, i64::MAX as u64, u64::MAX];
+        let generated = Values(0x2545_F491_4F6C_DD1D).take(2000).enumerate().map(|(i, value)| value >> (i % 64));
+
+        edges.into_iter().chain(generated).collect()
+    }
+
+    fn rescan(literal: &NumericLiteral) -> NumericLiteral {
+        Scanner::<Chars>::extract_number(&mut literal.to_string(), NO_POSITION, NO_POSITION).unwrap()
+    }
+
+    fn same_system(a: &NumeralSystem, b: &NumeralSystem) -> bool {
+        std::mem::discriminant(a) == std::mem::discriminant(b)
+    }
+
+    #[test]
+    fn positive_integers_round_trip_in_every_numeral_system() {
+        for value in integers() {
+            for numeral_system in [NumeralSystem::Binary, NumeralSystem::Decimal, NumeralSystem::Hexadecimal, NumeralSystem::Octal] {
+                let literal = NumericLiteral::PositiveInteger(value, numeral_system);
+
+                match rescan(&literal) {
+                    NumericLiteral::PositiveInteger(scanned, scanned_system) => {
+                        assert_eq!(scanned, value, "{0} scanned back to a different value", literal);
+                        assert!(same_system(&scanned_system, &numeral_system), "{0} scanned back as {1:?}", literal, scanned_system);
+                    },
+                    scanned => panic!("{0} scanned back as {1:?}", literal, scanned)
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn negative_integers_round_trip_in_every_numeral_system() {
+        let values = integers().into_iter().map(|value| -((value >> 1) as i64) - 1).chain([i64::MIN, -1]);
+
+        for value in values {
+            for numeral_system in [NumeralSystem::Binary, NumeralSystem::Decimal, NumeralSystem::Hexadecimal, NumeralSystem::Octal] {
+                let literal = NumericLiteral::NegativeInteger(value, numeral_system);
+
+                match rescan(&literal) {
+                    NumericLiteral::NegativeInteger(scanned, scanned_system) => {
+                        assert_eq!(scanned, value, "{0} scanned back to a different value", literal);
+                        assert!(same_system(&scanned_system, &numeral_system), "{0} scanned back as {1:?}", literal, scanned_system);
+                    },
+                    scanned => panic!("{0} scanned back as {1:?}", literal, scanned)
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn finite_floats_round_trip() {
+        let edges = [0.0, -0.0, 1.0, -1.0, 0.5, 1e300, -1e-300, f64::MAX, f64::MIN_POSITIVE, f64::EPSILON];
+        let generated = Values(0x9E37_79B9_7F4A_7C15).take(2000).map(f64::from_bits).filter(|value| value.is_finite());
+
+        for value in edges.into_iter().chain(generated) {
+            let literal = NumericLiteral::Float(value);
+
+            match rescan(&literal) {
+                NumericLiteral::Float(scanned) => assert_eq!(scanned.to_bits(), value.to_bits(), "{0} scanned back to {1}", literal, scanned),
+                scanned => panic!("{0} scanned back as {1:?}", literal, scanned)
+            }
+        }
+    }
+
+    #[test]
+    fn zero_round_trips_with_padding() {
+        assert_eq!(NumericLiteral::PositiveInteger(0, NumeralSystem::Binary).to_string(), "0b00");
+        assert!(matches!(rescan(&NumericLiteral::PositiveInteger(0, NumeralSystem::Binary)), NumericLiteral::PositiveInteger(0, NumeralSystem::Binary)));
+        assert!(matches!(rescan(&NumericLiteral::PositiveInteger(0x0B, NumeralSystem::Hexadecimal)), NumericLiteral::PositiveInteger(0x0B, NumeralSystem::Hexadecimal)));
+    }
+
+    fn scan(source: &str) -> Vec<Token> {
+        Scanner::from_str(source).into_iter().map(|token| token.unwrap().item).collect()
+    }
+
+    #[test]
+    fn scientific_notation_is_scanned_as_float() {
+        assert_eq!(scan("6.022e23"), vec![Token::NumericLiteral(NumericLiteral::Float(6.022e23))]);
+        assert_eq!(scan("1e-9"), vec![Token::NumericLiteral(NumericLiteral::Float(1e-9))]);
+        assert_eq!(scan("1E+3"), vec![Token::NumericLiteral(NumericLiteral::Float(1e3))]);
+    }
+
+    #[test]
+    fn scientific_notation_range_is_scanned() {
+        assert_eq!(scan("1e5..2e5"), vec![Token::NumericRange(NumericLiteral::Float(1e5), NumericLiteral::Float(2e5))]);
+    }
+
+    #[test]
+    fn hexadecimal_e_is_a_digit() {
+        assert_eq!(scan("0x1e"), vec![Token::NumericLiteral(NumericLiteral::PositiveInteger(0x1e, NumeralSystem::Hexadecimal))]);
+
+        // The minus following a hexadecimal 'e' starts a number of its own, instead of an exponent
+        assert_eq!(
+            scan("0x1e-5"),
+            vec![
+                Token::NumericLiteral(NumericLiteral::PositiveInteger(0x1e, NumeralSystem::Hexadecimal)),
+                Token::NumericLiteral(NumericLiteral::NegativeInteger(-5, NumeralSystem::Decimal))
+            ]
+        );
+    }
+
+    #[test]
+    fn digit_separators_are_ignored() {
+        assert_eq!(scan("1_000"), vec![Token::NumericLiteral(NumericLiteral::PositiveInteger(1000, NumeralSystem::Decimal))]);
+        assert_eq!(scan("0b1010_1010"), vec![Token::NumericLiteral(NumericLiteral::PositiveInteger(0b1010_1010, NumeralSystem::Binary))]);
+        assert_eq!(scan("0xDEAD_BEEF"), vec![Token::NumericLiteral(NumericLiteral::PositiveInteger(0xDEAD_BEEF, NumeralSystem::Hexadecimal))]);
+    }
+
+    #[test]
+    fn misplaced_digit_separators_are_rejected() {
+        for literal in ["1__0", "5_", "0x_FF"] {
+            assert!(Scanner::<Chars>::extract_number(&mut String::from(literal), NO_POSITION, NO_POSITION).is_err(), "{0} was accepted", literal);
+        }
+
+        // A leading underscore makes an identifier rather than a number
+        assert_eq!(scan("_5"), vec![Token::Identifier(Rc::from("_5"))]);
+    }
+
+    #[test]
+    fn unicode_escapes_are_scanned() {
+        assert_eq!(scan("'\\u{41}'"), vec![Token::NumericLiteral(NumericLiteral::AsciiChar('A'))]);
+        assert_eq!(scan("'\\u{00E9}'"), vec![Token::NumericLiteral(NumericLiteral::AsciiChar('\u{e9}'))]);
+    }
+
+    #[test]
+    fn surrogate_unicode_escape_is_rejected() {
+        assert!(matches!(Scanner::from_str("'\\u{D800}'").into_iter().next(), Some(Err(ScanningError::InvalidLiteral(_)))));
+    }
+
+    #[test]
+    fn octal_literals_round_trip() {
+        for (source, value) in [("0o17", 0o17), ("0o777", 0o777)] {
+            let literal = NumericLiteral::PositiveInteger(value, NumeralSystem::Octal);
+
+            assert_eq!(scan(source), vec![Token::NumericLiteral(literal.clone())]);
+            assert_eq!(literal.to_string(), source);
+        }
+    }
+
+    #[test]
+    fn string_escapes_are_processed() {
+        assert_eq!(scan(r#""a\tb""#), vec![Token::StringLiteral(String::from("a\tb"))]);
+        assert_eq!(scan(r#""\"quoted\"""#), vec![Token::StringLiteral(String::from("\"quoted\""))]);
+        assert_eq!(scan(r#""C:\\path\x41\n""#), vec![Token::StringLiteral(String::from("C:\\pathA\n"))]);
+    }
+
+    #[test]
+    fn unknown_string_escape_is_rejected() {
+        assert!(matches!(Scanner::from_str(r#""\q""#).into_iter().next(), Some(Err(ScanningError::InvalidLiteral(_)))));
+    }
+
+    #[test]
+    fn every_scanning_error_is_collected() {
+        let (tokens, errors) = Scanner::from_str("struct A {\n    a: u8 = 0; $\n}\n\n$ struct B {}\n").scan_all_collecting();
+
+        let lines: Vec<u32> = errors
+            .iter()
+            .map(|error| match error {
+                ScanningError::UnexpectedCharacter(character) => character.from.line,
+                error => panic!("Expected an unexpected character, got {0:?}", error)
+            })
+            .collect();
+        assert_eq!(lines, vec![2, 5]);
+
+        // Scanning carries on after each error
+        assert!(tokens.iter().any(|token| token.item == Token::Identifier(Rc::from("B"))));
+    }
+
+    #[test]
+    fn position_after_block_comment_is_exact() {
+        let tokens: Vec<Spanned<Token>> = Scanner::from_str("/* multi\nline */ struct Foo").into_iter().map(|token| token.unwrap()).collect();
+        let struct_token = tokens.iter().find(|token| token.item == Token::Struct).unwrap();
+
+        assert_eq!(struct_token.from.line, 2);
+        assert_eq!(struct_token.from.offset, Some(8));
+    }
+
+    #[test]
+    fn position_after_line_comment_is_exact() {
+        let tokens: Vec<Spanned<Token>> = Scanner::from_str("// comment\n  struct Foo").into_iter().map(|token| token.unwrap()).collect();
+        let struct_token = tokens.iter().find(|token| token.item == Token::Struct).unwrap();
+
+        assert_eq!(struct_token.from.line, 2);
+        assert_eq!(struct_token.from.offset, Some(2));
+    }
+
+    /// Number of allocations made scanning the given source to the end
+    fn scanning_allocations(source: &str) -> (usize, usize) {
+        let (tokens, allocations) = count_allocations(|| Scanner::from_str(source).scan_all().unwrap());
+
+        (tokens.len(), allocations)
+    }
+
+    #[test]
+    fn keywords_and_repeated_identifiers_are_scanned_without_allocating_text() {
+        let (keywords, keyword_allocations) = scanning_allocations(&"struct enum bitfield message define\n".repeat(10000));
+        let (identifiers, identifier_allocations) = scanning_allocations(&"alpha beta gamma delta epsilon\n".repeat(10000));
+
+        assert_eq!(keywords, 50000);
+        assert_eq!(identifiers, 50000);
+
+        // Only the reused buffer, the set of interned identifiers, and the growing list of tokens allocate, besides the first time each of
+        // the five identifiers is scanned
+        assert!(keyword_allocations < 64, "{0} allocations for {1} keywords", keyword_allocations, keywords);
+        assert!(identifier_allocations < 64, "{0} allocations for {1} identifiers", identifier_allocations, identifiers);
+    }
+
+    #[test]
+    fn distinct_identifiers_allocate_once_each() {
+        let source: String = (0..10000).map(|line| format!("field_{0} field_{0}\n", line)).collect();
+        let (identifiers, allocations) = scanning_allocations(&source);
+
+        assert_eq!(identifiers, 20000);
+        assert!(allocations < 10000 + 64, "{0} allocations for {1} identifiers", allocations, identifiers);
+    }
+
+    #[test]
+    fn repeated_identifiers_share_their_text() {
+        let tokens: Vec<Spanned<Token>> = Scanner::from_str("alpha beta alpha").into_iter().map(|token| token.unwrap()).collect();
+
+        match (&tokens[0].item, &tokens[1].item, &tokens[2].item) {
+            (Token::Identifier(first), Token::Identifier(second), Token::Identifier(third)) => {
+                assert!(Rc::ptr_eq(first, third));
+                assert!(!Rc::ptr_eq(first, second));
+            },
+            tokens => panic!("Expected three identifiers, got {0:?}", tokens)
+        }
+    }
+
+    #[test]
+    fn comment_above_struct_is_attached_to_the_struct_token() {
+        let tokens = Scanner::from_str("// A point\n/* In 2D */\nstruct Point {\n    x: i16 = 0; // Trailing\n}\n").with_trivia().scan_all().unwrap();
+
+        assert_eq!(tokens[0].token.item, Token::Struct);
+        let comments: Vec<(&str, bool)> = tokens[0].trivia().iter().map(|trivia| (trivia.item.comment.trim(), trivia.item.trailing)).collect();
+        assert_eq!(comments, [("A point", false), ("In 2D", false)]);
+        assert_eq!(tokens[0].trivia()[1].from.line, 2);
+
+        // The comment after the semicolon trails it, and is attached to the closing brace on the next line
+        let brace = tokens.iter().find(|token| token.token.item == Token::RightBrace).unwrap();
+        assert!(matches!(brace.trivia(), [trivia] if trivia.item.comment.trim() == "Trailing" && trivia.item.trailing));
+    }
+
+    #[test]
+    fn comments_after_the_last_token_are_still_comment_tokens() {
+        let tokens = Scanner::from_str("struct Point {}\n// Last\n").with_trivia().scan_all().unwrap();
+
+        assert!(matches!(&tokens.last().unwrap().token.item, Token::Comment(comment) if comment.trim() == "Last"));
+        assert!(tokens.iter().all(|token| token.trivia().is_empty()));
+    }
+
+    #[test]
+    fn default_scanning_keeps_comment_tokens() {
+        let tokens = scan("// A point\nstruct Point {}\n");
+        assert!(matches!(&tokens[0], Token::Comment(comment) if comment.trim() == "A point"));
+    }
+
+    #[test]
+    fn spans_of_copyable_items_can_be_copied() {
+        let span: Spanned<()> = Spanned::<()>::empty();
+        let copy = span;
+        assert_eq!(span.from, copy.from);
+    }
+}
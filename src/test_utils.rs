@@ -0,0 +1,87 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+    path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering}
+};
+
+use crate::{parser_rune_files_with_options, ParserOptions, RuneFileDescription, RuneParserError, Warning};
+
+/// Number of temporary input directories created so far, to give every test its own
+static DIRECTORY_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// System allocator counting the allocations and reallocations made by each thread, so tests running in parallel do not count each other's
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATION_COUNT: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATION_COUNT.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, pointer: *mut u8, layout: Layout) {
+        System.dealloc(pointer, layout)
+    }
+
+    unsafe fn realloc(&self, pointer: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATION_COUNT.with(|count| count.set(count.get() + 1));
+        System.realloc(pointer, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Run the given closure, and return its result along with the number of allocations and reallocations it made
+pub fn count_allocations<T>(function: impl FnOnce() -> T) -> (T, usize) {
+    let before: usize = ALLOCATION_COUNT.with(|count| count.get());
+    let result: T = function();
+    let after: usize = ALLOCATION_COUNT.with(|count| count.get());
+
+    (result, after - before)
+}
+
+/// Run the whole pipeline on the given files, written under a fresh temporary input directory. Files are given as their path relative
+/// to the input directory, such as `sub/file.rune`, and their contents
+pub fn parse_files(files: &[(&str, &str)]) -> Result<Vec<RuneFileDescription>, RuneParserError> {
+    parse_files_with_warnings(files, &mut Vec::new())
+}
+
+/// Same as [`parse_files`], also collecting every warning found
+pub fn parse_files_with_warnings(files: &[(&str, &str)], warnings: &mut Vec<Warning>) -> Result<Vec<RuneFileDescription>, RuneParserError> {
+    let options = ParserOptions {
+        append_extensions: true,
+        silent:            true,
+        warn_unused:       false,
+        array_size_type:   None
+    };
+
+    parse_files_with_options(files, &options, warnings)
+}
+
+/// Same as [`parse_files_with_warnings`], with the given options
+pub fn parse_files_with_options(files: &[(&str, &str)], options: &ParserOptions, warnings: &mut Vec<Warning>) -> Result<Vec<RuneFileDescription>, RuneParserError> {
+    let input_path: PathBuf = write_files(files);
+    let result = parser_rune_files_with_options(&[&input_path], options, warnings);
+
+    std::fs::remove_dir_all(&input_path).expect("Could not remove temporary input directory");
+
+    result
+}
+
+/// Write the given files under a fresh temporary directory, and return the path of the directory
+pub fn write_files(files: &[(&str, &str)]) -> PathBuf {
+    let input_path: PathBuf = std::env::temp_dir().join(format!("rune_parser_test_{0}_{1}", std::process::id(), DIRECTORY_COUNT.fetch_add(1, Ordering::Relaxed)));
+
+    for (path, contents) in files {
+        let file_path: PathBuf = input_path.join(path);
+        std::fs::create_dir_all(file_path.parent().unwrap()).expect("Could not create temporary input directory");
+        std::fs::write(&file_path, contents).expect("Could not write temporary rune file");
+    }
+
+    input_path
+}
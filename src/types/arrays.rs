@@ -8,6 +8,7 @@ use crate::{
 };
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Size of an array, storing how the user described the value
 pub enum ArraySize {
     /// Size described by a integer number. Can be written in several numeric systems
@@ -17,12 +18,17 @@ pub enum ArraySize {
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[allow(clippy::large_enum_variant)]
 pub enum ArrayType {
     Primitive(Primitive),
-    UserDefined(String, UserDefinitionLink)
+    UserDefined(String, UserDefinitionLink),
+    /// Element of a nested array, such as the `[u8; 4]` rows of `[[u8; 4]; 4]`
+    Array(Box<Array>)
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Array {
     pub data_type:     ArrayType,
     pub element_count: ArraySize
@@ -34,7 +40,8 @@ impl ArraySize {
             ArraySize::Integer(value, numeral_system) => match numeral_system {
                 NumeralSystem::Binary => format!("0b{0:b}", value - 1),
                 NumeralSystem::Decimal => (value - 1).to_string(),
-                NumeralSystem::Hexadecimal => format!("0x{0:02X}", value - 1)
+                NumeralSystem::Hexadecimal => format!("0x{0:02X}", value - 1),
+                NumeralSystem::Octal => format!("0o{0:o}", value - 1)
             },
             ArraySize::UserDefinition(definition) => {
                 let value = match &definition.redefinition {
@@ -43,12 +50,13 @@ impl ArraySize {
                 };
 
                 match value {
-                    DefineValue::NoValue => format!("{0} - 1", definition.name),
+                    DefineValue::NoValue | DefineValue::Alias(_) | DefineValue::Expression(_) | DefineValue::StringLiteral(_) => format!("{0} - 1", definition.name),
                     DefineValue::NumericLiteral(literal) => match literal {
                         NumericLiteral::PositiveInteger(value, numeral_system) => match numeral_system {
                             NumeralSystem::Binary => format!("0b{0:b}", value - 1),
                             NumeralSystem::Decimal => format!("{0}", value - 1),
-                            NumeralSystem::Hexadecimal => format!("0x{0:02X}", value - 1)
+                            NumeralSystem::Hexadecimal => format!("0x{0:02X}", value - 1),
+                            NumeralSystem::Octal => format!("0o{0:o}", value - 1)
                         },
 
                         _ => unreachable!("Only positive integer numbers can be indexes")
@@ -82,7 +90,8 @@ impl Display for ArraySize {
             ArraySize::Integer(value, numeral_system) => match numeral_system {
                 NumeralSystem::Binary => write!(formatter, "0b{0:b}", value),
                 NumeralSystem::Decimal => write!(formatter, "{0}", value),
-                NumeralSystem::Hexadecimal => write!(formatter, "0x{0:02X}", value)
+                NumeralSystem::Hexadecimal => write!(formatter, "0x{0:02X}", value),
+                NumeralSystem::Octal => write!(formatter, "0o{0:o}", value)
             },
 
             ArraySize::UserDefinition(value) => write!(formatter, "{0}", value.name)
@@ -121,7 +130,8 @@ impl ArrayType {
                     Err(RuneParserError::InvalidArrayType)
                 },
                 UserDefinitionLink::StructLink(struct_link) => Ok(struct_link.flat_size()?)
-            }
+            },
+            ArrayType::Array(array) => array.byte_size()
         }
     }
 }
@@ -136,6 +146,10 @@ impl PartialEq for ArrayType {
             ArrayType::UserDefined(definition, _) => match other {
                 ArrayType::UserDefined(other_definition, _) => definition == other_definition,
                 _ => false
+            },
+            ArrayType::Array(array) => match other {
+                ArrayType::Array(other_array) => array == other_array,
+                _ => false
             }
         }
     }
@@ -151,4 +165,53 @@ impl Array {
     pub fn byte_size(&self) -> Result<u64, RuneParserError> {
         Ok(self.data_type.size()? * self.element_count.value()?)
     }
+
+    /// Type of the innermost elements, which is the data type itself unless the array is nested
+    pub fn innermost_type(&self) -> &ArrayType {
+        match &self.data_type {
+            ArrayType::Array(array) => array.innermost_type(),
+            data_type => data_type
+        }
+    }
+
+    pub fn innermost_type_mut(&mut self) -> &mut ArrayType {
+        match self.data_type {
+            ArrayType::Array(ref mut array) => array.innermost_type_mut(),
+            ref mut data_type => data_type
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        scanner::NumeralSystem,
+        test_utils::parse_files,
+        types::{ArraySize, ArrayType, FieldType, Primitive, ValueType}
+    };
+
+    #[test]
+    fn last_index_keeps_the_numeral_system() {
+        assert_eq!(ArraySize::Integer(16, NumeralSystem::Octal).last_index_string(), "0o17");
+        assert_eq!(ArraySize::Integer(16, NumeralSystem::Hexadecimal).last_index_string(), "0x0F");
+        assert_eq!(ArraySize::Integer(16, NumeralSystem::Decimal).last_index_string(), "15");
+    }
+
+    #[test]
+    fn nested_array_multiplies_its_dimensions() {
+        let files = parse_files(&[("matrix.rune", "struct Matrix {\n    cells: [[u16; 3]; 4] = 0;\n}\n\nmessage Frame {\n    field: [[u8; 4]; 4] = 1;\n}\n")]).unwrap();
+        let definitions = &files[0].definitions;
+
+        let matrix = definitions.find_struct("Matrix").unwrap();
+        assert_eq!(matrix.flat_size().unwrap(), 2 * 3 * 4);
+
+        let array = match &definitions.find_message("Frame").unwrap().fields[0].data_type {
+            FieldType::Value(ValueType::Array(array)) => array,
+            data_type => panic!("Expected an array, got {0:?}", data_type)
+        };
+        assert!(matches!(array.element_count, ArraySize::Integer(4, _)));
+        assert!(matches!(&array.data_type, ArrayType::Array(inner) if matches!(inner.element_count, ArraySize::Integer(4, _))));
+        assert!(matches!(array.innermost_type(), ArrayType::Primitive(Primitive::U8)));
+        assert_eq!(array.byte_size().unwrap(), 16);
+    }
 }
@@ -1,22 +1,39 @@
 use crate::types::{Primitive, StandaloneCommentDefinition};
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BitfieldDefinition {
     /// Name of the bitfield
-    pub name:             String,
+    pub name:               String,
     /// The primitive backing type of the bitfield. Only integers are valid
-    pub backing_type:     Primitive,
+    pub backing_type:       Primitive,
     /// Members of the bitfield
-    pub members:          Vec<BitfieldMember>,
+    pub members:            Vec<BitfieldMember>,
     /// Indexes that are reserved, and should not be used
-    pub reserved_indexes: Vec<u64>,
+    pub reserved_indexes:   Vec<u64>,
+    /// Indexes of deprecated members, which are blocked like reserved indexes
+    pub deprecated_indexes: Vec<u64>,
     /// Comment describing the bitfield
-    pub comment:          Option<String>,
+    pub comment:            Option<String>,
     /// Loose comments inside the bitfield declaration
-    pub orphan_comments:  Vec<StandaloneCommentDefinition>
+    pub orphan_comments:    Vec<StandaloneCommentDefinition>,
+    /// Schema version the definition was introduced in. It is left out of files declaring an older version
+    pub min_version:        Option<u64>,
+    /// Name of the group the definition belongs to, used to cluster related definitions in generated code
+    pub group:              Option<String>,
+    /// Compiler specific attributes emitted verbatim in generated C, such as `section(".fast")`
+    pub c_attributes:       Vec<String>
+}
+
+impl BitfieldDefinition {
+    /// Number of bits the members take up, from the first bit to the end of the last member, when packed at the bit level
+    pub fn bit_size(&self) -> u64 {
+        self.members.iter().map(|member| member.index + member.size.absolute()).max().unwrap_or(0)
+    }
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Describes the size of the bit field, and whether it's signed or not
 pub enum BitSize {
     Signed(u64),
@@ -35,6 +52,7 @@ impl BitSize {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BitfieldMember {
     /// Name of the bit field
     pub identifier: String,
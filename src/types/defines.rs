@@ -1,6 +1,7 @@
 use crate::scanner::NumericLiteral;
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DefineDefinition {
     /// Name of the definition
     pub name:         String,
@@ -13,6 +14,7 @@ pub struct DefineDefinition {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RedefineDefinition {
     /// Name of the original definition
     pub name:    String,
@@ -23,9 +25,46 @@ pub struct RedefineDefinition {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DefineValue {
     /// Definition with no value. Used only while parsing before the linkage of user definitions is performed
     NoValue,
-    /// Numeric value of a user definition. No other type is allowed for now
-    NumericLiteral(NumericLiteral)
+    /// Numeric value of a user definition
+    NumericLiteral(NumericLiteral),
+    /// Name of another define whose value is used, such as `define MAX_TOTAL MAX_A;`. Chains of aliases are checked in post processing
+    Alias(String),
+    /// Arithmetic on literals and other defines, such as `define BUFFER (HEADER + PAYLOAD);`. Evaluated in post processing
+    Expression(DefineExpression),
+    /// Text a char array member or field is initialized to, such as `default "hi"`. Only valid as a default value
+    StringLiteral(String)
+}
+
+/// Arithmetic expression in the value of a define
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DefineExpression {
+    Literal(NumericLiteral),
+    /// Name of another define
+    Identifier(String),
+    Binary(Box<DefineExpression>, DefineOperator, Box<DefineExpression>)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DefineOperator {
+    Add,
+    Subtract,
+    Multiply,
+    /// Integer division, rounding towards zero
+    Divide
+}
+
+impl DefineDefinition {
+    /// Value of the definition, taking a possible redefinition into account
+    pub fn effective_value(&self) -> &DefineValue {
+        match &self.redefinition {
+            None => &self.value,
+            Some(redefinition) => &redefinition.value
+        }
+    }
 }
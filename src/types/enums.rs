@@ -4,22 +4,32 @@ use crate::{
 };
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EnumDefinition {
     /// Name of the enum
-    pub name:            String,
+    pub name:              String,
     /// The primitive backing type of the enum
-    pub backing_type:    Primitive,
+    pub backing_type:      Primitive,
+    /// A narrower primitive type used when encoding the enum in messages, if any
+    pub encoded_type:      Option<Primitive>,
     /// Members of the enum
-    pub members:         Vec<EnumMember>,
+    pub members:           Vec<EnumMember>,
     /// Values that are reserved, and should not be used
-    pub reserved_values: Vec<NumericLiteral>,
+    pub reserved_values:   Vec<NumericLiteral>,
+    /// Values of deprecated members, which are blocked like reserved values
+    pub deprecated_values: Vec<NumericLiteral>,
     /// Comment describing the enum
-    pub comment:         Option<String>,
+    pub comment:           Option<String>,
     /// Loose comments inside the enum declaration
-    pub orphan_comments: Vec<StandaloneCommentDefinition>
+    pub orphan_comments:   Vec<StandaloneCommentDefinition>,
+    /// Schema version the definition was introduced in. It is left out of files declaring an older version
+    pub min_version:       Option<u64>,
+    /// Name of the group the definition belongs to, used to cluster related definitions in generated code
+    pub group:             Option<String>
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EnumMember {
     /// Name of the enum member
     pub identifier: String,
@@ -28,3 +38,120 @@ pub struct EnumMember {
     /// Comment describing the enum member
     pub comment:    Option<String>
 }
+
+impl EnumDefinition {
+    /// Primitive type used when encoding the enum in messages. Structs always hold the backing type
+    pub fn wire_type(&self) -> &Primitive {
+        match &self.encoded_type {
+            None => &self.backing_type,
+            Some(encoded_type) => encoded_type
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        parse_str,
+        scanner::NumericLiteral,
+        test_utils::parse_files,
+        types::{Primitive, StructDefinition},
+        RuneParserError
+    };
+
+    const NARROWED_ENUM: &str = "@encode_as(u8)\nenum Command: u32 {\n    Start = 1;\n    Stop = 2;\n}\n\n\
+                                 struct Frame {\n    command: Command = 0;\n}\n\nmessage Request {\n    command: Command = 1;\n}\n";
+
+    #[test]
+    fn narrowed_enum_is_encoded_with_its_wire_type() {
+        let files = parse_files(&[("command.rune", NARROWED_ENUM)]).unwrap();
+        let definitions = &files[0].definitions;
+
+        let command = definitions.find_enum("Command").unwrap();
+        assert_eq!(command.backing_type, Primitive::U32);
+        assert_eq!(command.wire_type(), &Primitive::U8);
+
+        // Messages encode the narrowed type, while structs keep the backing type
+        let request = definitions.find_message("Request").unwrap();
+        assert_eq!(request.fields[0].full_encoded_size(true).unwrap(), Some(1));
+
+        let frame: &StructDefinition = definitions.find_struct("Frame").unwrap();
+        assert_eq!(frame.flat_size().unwrap(), 4);
+    }
+
+    #[test]
+    fn narrowed_enum_values_must_fit_the_wire_type() {
+        let result = parse_str("@encode_as(u8)\nenum Command: u32 {\n    Start = 1;\n    Far = 300;\n}\n", "command.rune");
+        assert!(matches!(result, Err(RuneParserError::InvalidNumericValue)));
+    }
+
+    #[test]
+    fn narrowed_enum_reserved_values_must_fit_the_wire_type() {
+        let result = parse_str("@encode_as(u8)\nenum Command: u32 {\n    Start = 1;\n    reserve 300;\n}\n", "command.rune");
+        assert!(matches!(result, Err(RuneParserError::InvalidNumericValue)));
+    }
+
+    #[test]
+    fn infinite_float_value_is_rejected() {
+        // Too large for an f64, so it scans as infinity
+        let result = parse_str("enum Limit: f64 {\n    Huge = 1e400;\n}\n", "limit.rune");
+        assert!(matches!(result, Err(RuneParserError::InvalidSyntax)));
+    }
+
+    #[test]
+    fn duplicate_float_values_collide() {
+        let result = parse_str("enum Ratio: f64 {\n    Half = 0.5;\n    Other = 0.5;\n}\n", "ratio.rune");
+        assert!(matches!(result, Err(RuneParserError::ValueCollision)));
+
+        assert!(parse_str("enum Ratio: f64 {\n    Half = 0.5;\n    Quarter = 0.25;\n}\n", "ratio.rune").is_ok());
+    }
+
+    fn member_values(source: &str) -> Vec<u64> {
+        let definitions = parse_str(source, "color.rune").unwrap();
+
+        definitions
+            .find_enum("Color")
+            .unwrap()
+            .members
+            .iter()
+            .map(|member| match member.value {
+                NumericLiteral::PositiveInteger(value, _) => value,
+                ref value => panic!("Expected a positive integer, got {0:?}", value)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn omitted_enum_values_count_up_from_zero() {
+        assert_eq!(member_values("enum Color: u8 {\n    Red;\n    Green;\n    Blue;\n}\n"), [0, 1, 2]);
+    }
+
+    #[test]
+    fn explicit_enum_values_are_kept() {
+        assert_eq!(member_values("enum Color: u8 {\n    Red = 4;\n    Green = 2;\n    Blue = 9;\n}\n"), [4, 2, 9]);
+    }
+
+    #[test]
+    fn omitted_enum_values_follow_the_previous_member() {
+        assert_eq!(member_values("enum Color: u8 {\n    Red = 5;\n    Green;\n    Blue = 10;\n    Cyan;\n}\n"), [5, 6, 10, 11]);
+    }
+
+    #[test]
+    fn omitted_enum_value_must_fit_the_backing_type() {
+        assert!(parse_str("enum Color: i8 {\n    Red = 127;\n    Green;\n}\n", "color.rune").is_err());
+    }
+
+    #[test]
+    fn char_enum_accepts_char_literals_and_codes() {
+        let definitions = parse_str("enum Letter: char {\n    A = 'A';\n    B = 66;\n}\n", "letters.rune").unwrap();
+        let members = &definitions.find_enum("Letter").unwrap().members;
+
+        assert!(matches!(members[0].value, NumericLiteral::AsciiChar('A')));
+        assert!(matches!(members[1].value, NumericLiteral::PositiveInteger(66, _)));
+    }
+
+    #[test]
+    fn char_enum_rejects_codes_outside_ascii() {
+        assert!(matches!(parse_str("enum Letter: char {\n    High = 128;\n}\n", "letters.rune"), Err(RuneParserError::InvalidSyntax)));
+    }
+}
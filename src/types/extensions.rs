@@ -8,13 +8,35 @@ pub enum ExtensionDefinition {
     Struct(StructDefinition)
 }
 
+/// Borrowed view of a single extension, regardless of the kind of definition it extends
+#[derive(Debug, Clone, Copy)]
+pub enum ExtensionRef<'a> {
+    Bitfield(&'a BitfieldDefinition),
+    Enum(&'a EnumDefinition),
+    Message(&'a MessageDefinition),
+    Struct(&'a StructDefinition)
+}
+
+impl<'a> ExtensionRef<'a> {
+    /// Name of the definition being extended
+    pub fn name(&self) -> &'a str {
+        match self {
+            ExtensionRef::Bitfield(definition) => &definition.name,
+            ExtensionRef::Enum(definition) => &definition.name,
+            ExtensionRef::Message(definition) => &definition.name,
+            ExtensionRef::Struct(definition) => &definition.name
+        }
+    }
+}
+
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Extensions {
     /// List of bitfield extensions
     pub bitfields: Vec<BitfieldDefinition>,
     /// List of enum extensions
     pub enums:     Vec<EnumDefinition>,
-    // List of message extensions
+    /// List of message extensions
     pub messages:  Vec<MessageDefinition>,
     /// List of struct extensions
     pub structs:   Vec<StructDefinition>
@@ -31,7 +53,22 @@ impl Extensions {
     }
 
     pub fn is_empty(&self) -> bool {
-        self.bitfields.is_empty() && self.enums.is_empty() && self.structs.is_empty()
+        self.bitfields.is_empty() && self.enums.is_empty() && self.messages.is_empty() && self.structs.is_empty()
+    }
+
+    /// Total number of extensions of all kinds
+    pub fn len(&self) -> usize {
+        self.bitfields.len() + self.enums.len() + self.messages.len() + self.structs.len()
+    }
+
+    /// Iterate over the extensions of all kinds, in the order bitfields, enums, messages, and structs
+    pub fn iter(&self) -> impl Iterator<Item = ExtensionRef<'_>> {
+        self.bitfields
+            .iter()
+            .map(ExtensionRef::Bitfield)
+            .chain(self.enums.iter().map(ExtensionRef::Enum))
+            .chain(self.messages.iter().map(ExtensionRef::Message))
+            .chain(self.structs.iter().map(ExtensionRef::Struct))
     }
 
     pub fn with_capacity(size: usize) -> Extensions {
@@ -43,3 +80,22 @@ impl Extensions {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse_str, types::ExtensionRef};
+
+    #[test]
+    fn mixed_extensions_are_iterated_by_kind() {
+        let source = "extend struct Point {\n    z: i32 = 2;\n}\n\nextend enum Mode: u8 {\n    Idle = 2;\n}\n\nextend message Packet {\n    b: u8 = 2;\n}\n\n\
+                      extend bitfield Flags: u8 {\n    ready: u1 = 1;\n}\n";
+        let extensions = parse_str(source, "extensions.rune").unwrap().extensions;
+
+        assert_eq!(extensions.len(), 4);
+        assert!(!extensions.is_empty());
+
+        let names: Vec<&str> = extensions.iter().map(|extension| extension.name()).collect();
+        assert_eq!(names, vec!["Flags", "Mode", "Packet", "Point"]);
+        assert!(matches!(extensions.iter().nth(2), Some(ExtensionRef::Message(definition)) if definition.fields.len() == 1));
+    }
+}
@@ -1,6 +1,7 @@
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// File inclusion definition. Used to track dependencies between files
 pub struct IncludeDefinition {
-    // pub path: String, --> Implement later if needed
+    /// Path of the included file relative to the input path, without the .rune extension
     pub file: String
 }
@@ -1,6 +1,9 @@
 use crate::types::{BitfieldDefinition, EnumDefinition, MessageDefinition, StructDefinition};
 
+/// Links are serialized as the kind and name of the linked definition, instead of a copy of it. Deserialized links are left unlinked, and
+/// are restored by linking the user definitions again
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(into = "LinkReference", from = "LinkReference"))]
 pub enum UserDefinitionLink {
     NoLink,
     // Clone value of the bitfield definition
@@ -12,3 +15,75 @@ pub enum UserDefinitionLink {
     // Clone value of the struct definition
     StructLink(StructDefinition)
 }
+
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+enum LinkKind {
+    NoLink,
+    Bitfield,
+    Enum,
+    Message,
+    Struct
+}
+
+/// Serialized form of a user definition link
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct LinkReference {
+    kind: LinkKind,
+    name: Option<String>
+}
+
+#[cfg(feature = "serde")]
+impl From<UserDefinitionLink> for LinkReference {
+    fn from(link: UserDefinitionLink) -> LinkReference {
+        let (kind, name) = match link {
+            UserDefinitionLink::NoLink => (LinkKind::NoLink, None),
+            UserDefinitionLink::BitfieldLink(definition) => (LinkKind::Bitfield, Some(definition.name)),
+            UserDefinitionLink::EnumLink(definition) => (LinkKind::Enum, Some(definition.name)),
+            UserDefinitionLink::MessageLink(definition) => (LinkKind::Message, Some(definition.name)),
+            UserDefinitionLink::StructLink(definition) => (LinkKind::Struct, Some(definition.name))
+        };
+
+        LinkReference { kind, name }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<LinkReference> for UserDefinitionLink {
+    fn from(_: LinkReference) -> UserDefinitionLink {
+        UserDefinitionLink::NoLink
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use crate::{post_processing::link_user_definitions, test_utils::parse_files, types::Definitions, RuneFileDescription};
+
+    const SOURCE: &str = "enum Mode: u8 {\n    Off = 0;\n    On = 1;\n}\n\nstruct Point {\n    x: i16 = 0;\n    y: i16 = 1;\n}\n\n\
+                          message Move {\n    mode: Mode = 1;\n    to: Point = 2;\n    path?: [Point; 4] = 3;\n}\n";
+
+    #[test]
+    fn definitions_round_trip_through_json() {
+        let files = parse_files(&[("move.rune", SOURCE)]).unwrap();
+        let json: String = serde_json::to_string(&files[0].definitions).unwrap();
+
+        // Links are serialized as names only, so they are linked again after deserializing
+        let mut round_trip: Vec<RuneFileDescription> = Vec::from([RuneFileDescription {
+            relative_path: String::new(),
+            name:          String::from("move"),
+            definitions:   serde_json::from_str::<Definitions>(&json).unwrap()
+        }]);
+        link_user_definitions(&mut round_trip, 8).unwrap();
+
+        assert_eq!(serde_json::to_string(&round_trip[0].definitions).unwrap(), json);
+    }
+
+    #[test]
+    fn links_are_serialized_as_names() {
+        let files = parse_files(&[("move.rune", SOURCE)]).unwrap();
+        let field = serde_json::to_value(&files[0].definitions.find_message("Move").unwrap().fields[1]).unwrap();
+
+        assert_eq!(field["data_type"]["Value"]["UserDefined"][1], serde_json::json!({ "kind": "Struct", "name": "Point" }));
+    }
+}
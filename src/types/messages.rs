@@ -1,38 +1,58 @@
-use std::fmt::{Debug, Formatter};
+use std::{
+    fmt::{Debug, Formatter},
+    hash::{Hash, Hasher}
+};
 
 use crate::{
     output::*,
-    types::{Array, Primitive, StandaloneCommentDefinition, UserDefinitionLink},
+    types::{DefineValue, Primitive, StandaloneCommentDefinition, UserDefinitionLink, ValueType},
     RuneParserError
 };
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MessageDefinition {
     /// Name of the struct
-    pub name:             String,
+    pub name:               String,
     /// Data fields of the message
-    pub fields:           Vec<MessageField>,
+    pub fields:             Vec<MessageField>,
     /// Indexes that are reserved, and should not be used
-    pub reserved_indexes: Vec<FieldIndex>,
+    pub reserved_indexes:   Vec<FieldIndex>,
+    /// Indexes of deprecated fields, which are blocked like reserved indexes
+    pub deprecated_indexes: Vec<FieldIndex>,
     /// Comment describing the message
-    pub comment:          Option<String>,
+    pub comment:            Option<String>,
     /// Loose comments inside the message declaration
-    pub orphan_comments:  Vec<StandaloneCommentDefinition>
+    pub orphan_comments:    Vec<StandaloneCommentDefinition>,
+    /// Schema version the definition was introduced in. It is left out of files declaring an older version
+    pub min_version:        Option<u64>,
+    /// Name of the group the definition belongs to, used to cluster related definitions in generated code
+    pub group:              Option<String>
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MessageField {
     /// Name of the data field
-    pub identifier: String,
+    pub identifier:     String,
     /// Type of the data field
-    pub data_type:  FieldType,
+    pub data_type:      FieldType,
     /// Index of the data field
-    pub index:      FieldIndex,
+    pub index:          FieldIndex,
+    /// Whether the field may be left out of a message, declared as `name?: type`
+    pub optional:       bool,
     /// Comment describing the data field
-    pub comment:    Option<String>
+    pub comment:        Option<String>,
+    /// Last schema version in which the field is still in use, if it has been marked as obsolete
+    pub obsolete_after: Option<u64>,
+    /// Whether a char array field should be treated as a NUL terminated C string
+    pub cstring:        bool,
+    /// Value the field is initialized to, if declared
+    pub default:        Option<DefineValue>
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum FieldIndex {
     /// Used for regular fields
     Numeric(u64),
@@ -62,37 +82,75 @@ impl PartialEq for FieldIndex {
     }
 }
 
+impl Eq for FieldIndex {}
+
+// Hashes the value, so the verifier hashes like index 0, as the two compare equal
+impl Hash for FieldIndex {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.value().hash(state);
+    }
+}
+
+#[allow(clippy::large_enum_variant)]
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum FieldType {
     /// Used for skipped fields
     Empty,
-    Primitive(Primitive),
-    Array(Array),
-    UserDefined(String, UserDefinitionLink)
+    /// Any data type that can also be held by a struct member
+    Value(ValueType),
+    /// Length prefixed blob of bytes, such as `bytes` or `bytes(256)`, with the largest number of bytes it can hold if it is bounded
+    Bytes(Option<u64>)
+}
+
+impl FieldType {
+    /// Get the underlying data type of the field, if it is not a skipped field
+    pub fn value_type(&self) -> Option<&ValueType> {
+        match self {
+            FieldType::Empty | FieldType::Bytes(_) => None,
+            FieldType::Value(value_type) => Some(value_type)
+        }
+    }
+
+    /// Number of bits needed to hold the field when packed at the bit level. Skipped fields hold no data
+    pub fn bit_size(&self) -> Result<u64, RuneParserError> {
+        match self {
+            FieldType::Empty => Ok(0),
+            FieldType::Value(value_type) => value_type.bit_size(),
+            FieldType::Bytes(Some(max_size)) => Ok(max_size * 8),
+            FieldType::Bytes(None) => {
+                error!("Unbounded bytes have no fixed size, so they cannot be packed at the bit level");
+                Err(RuneParserError::InvalidTypeUse)
+            }
+        }
+    }
+}
+
+impl From<ValueType> for FieldType {
+    fn from(value_type: ValueType) -> FieldType {
+        FieldType::Value(value_type)
+    }
+}
+
+impl TryFrom<FieldType> for ValueType {
+    type Error = RuneParserError;
+
+    fn try_from(field_type: FieldType) -> Result<ValueType, RuneParserError> {
+        match field_type {
+            FieldType::Empty => Err(RuneParserError::EmptyMessageField),
+            FieldType::Value(value_type) => Ok(value_type),
+            FieldType::Bytes(_) => Err(RuneParserError::InvalidTypeUse)
+        }
+    }
 }
 
 impl Debug for FieldType {
     fn fmt(&self, formatter: &mut Formatter) -> std::fmt::Result {
         match self {
             FieldType::Empty => write!(formatter, "(empty)"),
-            FieldType::Primitive(primitive) => match primitive {
-                Primitive::Bool => write!(formatter, "bool"),
-                Primitive::Char => write!(formatter, "char"),
-                Primitive::I8 => write!(formatter, "i8"),
-                Primitive::U8 => write!(formatter, "u8"),
-                Primitive::I16 => write!(formatter, "i16"),
-                Primitive::U16 => write!(formatter, "u16"),
-                Primitive::F32 => write!(formatter, "f32"),
-                Primitive::I32 => write!(formatter, "i32"),
-                Primitive::U32 => write!(formatter, "u32"),
-                Primitive::F64 => write!(formatter, "f64"),
-                Primitive::I64 => write!(formatter, "i64"),
-                Primitive::U64 => write!(formatter, "u64"),
-                Primitive::I128 => write!(formatter, "i128"),
-                Primitive::U128 => write!(formatter, "u128")
-            },
-            FieldType::Array(array) => write!(formatter, "[{0:?}; {1}]", array.data_type, array.element_count),
-            FieldType::UserDefined(string, _) => write!(formatter, "{0}", string.clone())
+            FieldType::Value(value_type) => write!(formatter, "{0:?}", value_type),
+            FieldType::Bytes(None) => write!(formatter, "bytes"),
+            FieldType::Bytes(Some(max_size)) => write!(formatter, "bytes({0})", max_size)
         }
     }
 }
@@ -102,25 +160,52 @@ impl PartialEq for FieldType {
         match self {
             FieldType::Empty => matches!(other, FieldType::Empty),
 
-            FieldType::Primitive(primitive) => match other {
-                FieldType::Primitive(other_primitive) => primitive == other_primitive,
+            FieldType::Value(value_type) => match other {
+                FieldType::Value(other_value_type) => value_type == other_value_type,
                 _ => false
             },
 
-            FieldType::Array(array) => match other {
-                FieldType::Array(other_array) => array == other_array,
-                _ => false
-            },
-
-            FieldType::UserDefined(string, _) => match other {
-                FieldType::UserDefined(other_string, _) => string == other_string,
+            FieldType::Bytes(max_size) => match other {
+                FieldType::Bytes(other_max_size) => max_size == other_max_size,
                 _ => false
             }
         }
     }
 }
 
-fn optimal_encoded_data_size(size: &u64) -> Result<u64, RuneParserError> {
+/// Encoding of the field headers and length prefixes of messages on the wire
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WireFormat {
+    /// Length prefixes are 1, 2 or 4 byte integers, depending on the size of the data
+    #[default]
+    Fixed,
+
+    /// Length prefixes are LEB128 varints. Field headers stay a single byte, as field indexes are below 32
+    Varint
+}
+
+impl WireFormat {
+    /// Number of bytes needed to encode a value as a LEB128 varint
+    pub fn varint_size(value: u64) -> u64 {
+        let significant_bits: u64 = (u64::BITS - value.leading_zeros()).max(1) as u64;
+        significant_bits.div_ceil(7)
+    }
+
+    /// Largest overhead a field can be encoded with, which is a header followed by the largest possible length prefix
+    pub fn pessimal_overhead(&self) -> u64 {
+        const HEADER_SIZE: u64 = 1;
+
+        match self {
+            WireFormat::Fixed => HEADER_SIZE + 4,
+            WireFormat::Varint => HEADER_SIZE + WireFormat::varint_size(u32::MAX as u64)
+        }
+    }
+}
+
+/// Gives the smallest encoding of a field with the given data size. Sizes of 1, 2, 4 or 8 bytes are sent as a plain value after the header, unless
+/// the data is length prefixed, which is always the case for bytes fields as their length is only known when sending
+fn optimal_encoded_data_size(size: &u64, wire_format: WireFormat, length_prefixed: bool) -> Result<u64, RuneParserError> {
     const HEADER_SIZE: u64 = 1;
     const ARRAY_SIZE_U8: u64 = 1;
     const ARRAY_SIZE_U16: u64 = 2;
@@ -128,7 +213,8 @@ fn optimal_encoded_data_size(size: &u64) -> Result<u64, RuneParserError> {
 
     match size {
         0 => Ok(0),
-        1 | 2 | 4 | 8 => Ok(HEADER_SIZE + size),
+        1 | 2 | 4 | 8 if !length_prefixed => Ok(HEADER_SIZE + size),
+        size if wire_format == WireFormat::Varint && *size <= u32::MAX as u64 => Ok(HEADER_SIZE + WireFormat::varint_size(*size) + size),
         size if Primitive::U8_RANGE.contains(size) => Ok(HEADER_SIZE + ARRAY_SIZE_U8 + size),
         size if Primitive::U16_RANGE.contains(size) => Ok(HEADER_SIZE + ARRAY_SIZE_U16 + size),
         size if Primitive::U32_RANGE.contains(size) => Ok(HEADER_SIZE + ARRAY_SIZE_U32 + size),
@@ -146,20 +232,32 @@ fn optimal_encoded_data_size(size: &u64) -> Result<u64, RuneParserError> {
 impl MessageField {
     /// Gives the full encoded data size of the field. If it's a message, then the flag will determine whether optimal encoding is used, or pessimal (most inefficient) encoding
     pub fn full_encoded_size(&self, pessimal: bool) -> Result<Option<u64>, RuneParserError> {
+        self.full_encoded_size_in(pessimal, WireFormat::Fixed)
+    }
+
+    /// Gives the full encoded data size of the field, with nested messages encoded in the given wire format
+    pub fn full_encoded_size_in(&self, pessimal: bool, wire_format: WireFormat) -> Result<Option<u64>, RuneParserError> {
         match &self.data_type {
-            FieldType::Array(array) => Ok(Some(array.byte_size()?)),
             FieldType::Empty => Ok(Some(0)),
-            FieldType::Primitive(primitive) => Ok(Some(primitive.encoded_max_data_size())),
-            FieldType::UserDefined(type_identifier, definition_link) => match &definition_link {
+            // Bounded bytes are sized like an array of u8 at their largest, always behind a length prefix. Unbounded bytes have no largest size, and are
+            // empty at their smallest
+            FieldType::Bytes(Some(max_size)) => Ok(Some(*max_size)),
+            FieldType::Bytes(None) => match pessimal {
+                false => Ok(Some(0)),
+                true => Ok(None)
+            },
+            FieldType::Value(ValueType::Array(array)) => Ok(Some(array.byte_size()?)),
+            FieldType::Value(ValueType::Primitive(primitive)) => Ok(Some(primitive.encoded_max_data_size())),
+            FieldType::Value(ValueType::UserDefined(type_identifier, definition_link)) => match &definition_link {
                 UserDefinitionLink::NoLink => {
                     error!("No definition for message field {0} of type {1}! This should not happen!", self.identifier, type_identifier);
                     Err(RuneParserError::UndefinedIdentifier)
                 },
                 UserDefinitionLink::BitfieldLink(bitfield_definition) => Ok(Some(bitfield_definition.backing_type.encoded_max_data_size())),
-                UserDefinitionLink::EnumLink(enum_definition) => Ok(Some(enum_definition.backing_type.encoded_max_data_size())),
+                UserDefinitionLink::EnumLink(enum_definition) => Ok(Some(enum_definition.wire_type().encoded_max_data_size())),
                 UserDefinitionLink::MessageLink(message_link) => match pessimal {
-                    false => Ok(Some(message_link.optimal_full_encoded_size()?)),
-                    true => message_link.pessimal_encoded_size()
+                    false => Ok(Some(message_link.optimal_full_encoded_size_in(wire_format)?)),
+                    true => message_link.pessimal_encoded_size_in(wire_format)
                 },
                 UserDefinitionLink::StructLink(struct_definition) => Ok(Some(struct_definition.flat_size()?))
             }
@@ -170,12 +268,31 @@ impl MessageField {
 impl MessageDefinition {
     /// Gives the encoded size of this message if all non-skipped fields have encoded to their nominal size in the most efficient manner possible. Used for allocating buffers.
     pub fn optimal_full_encoded_size(&self) -> Result<u64, RuneParserError> {
+        self.optimal_full_encoded_size_in(WireFormat::Fixed)
+    }
+
+    /// Gives the most efficient encoded size of this message in the given wire format
+    pub fn optimal_full_encoded_size_in(&self, wire_format: WireFormat) -> Result<u64, RuneParserError> {
+        self.optimal_encoded_size_of(wire_format, true)
+    }
+
+    /// Gives the encoded size of this message if only the fields that are not optional are present, encoded in the most efficient manner possible
+    pub fn optimal_required_encoded_size(&self) -> Result<u64, RuneParserError> {
+        self.optimal_required_encoded_size_in(WireFormat::Fixed)
+    }
+
+    /// Gives the most efficient encoded size of the fields of this message that are not optional, in the given wire format
+    pub fn optimal_required_encoded_size_in(&self, wire_format: WireFormat) -> Result<u64, RuneParserError> {
+        self.optimal_encoded_size_of(wire_format, false)
+    }
+
+    fn optimal_encoded_size_of(&self, wire_format: WireFormat, include_optional: bool) -> Result<u64, RuneParserError> {
         let mut total_size: u64 = 0;
 
-        for field in &self.fields {
-            match field.full_encoded_size(false) {
+        for field in self.fields.iter().filter(|field| include_optional || !field.optional) {
+            match field.full_encoded_size_in(false, wire_format) {
                 // Not setting the pessimal flag will mean optimal_encoded_data_size() never returns None, and we can thus safely unwrap the value
-                Ok(value) => total_size += optimal_encoded_data_size(&value.unwrap())?,
+                Ok(value) => total_size += optimal_encoded_data_size(&value.unwrap(), wire_format, matches!(field.data_type, FieldType::Bytes(_)))?,
                 Err(error) => {
                     error!("Could not get encoded size of field {0} of message {1}. Got error {2:?}", field.identifier, self.name, error);
                     return Err(error);
@@ -189,6 +306,11 @@ impl MessageDefinition {
     /// If there are no skipped field indexes, then this gives the largest possible encoding of the present fields will full data. Used for allocation of buffers in worst case scenarios where another implementation might not use the most efficient encoding.
     /// This returns nothing in case there are skipped fields, as there is no way of knowing if they might be sent, and how big they are
     pub fn pessimal_encoded_size(&self) -> Result<Option<u64>, RuneParserError> {
+        self.pessimal_encoded_size_in(WireFormat::Fixed)
+    }
+
+    /// Gives the largest possible encoding of this message in the given wire format, or nothing if there are skipped field indexes
+    pub fn pessimal_encoded_size_in(&self, wire_format: WireFormat) -> Result<Option<u64>, RuneParserError> {
         let mut total_size: u64 = 0;
 
         let mut largest_index: u64 = 0;
@@ -200,16 +322,16 @@ impl MessageDefinition {
             }
         }
 
-        // Encoding as a large array (header + 4 byte size) is the one with the largest overhead, and thus the worst case
-        const PESSIMAL_ENCODING: u64 = 5;
+        // Encoding as a large array (header + largest length prefix) is the one with the largest overhead, and thus the worst case
+        let pessimal_encoding: u64 = wire_format.pessimal_overhead();
 
         for i in 0..(largest_index + 1) {
             let mut found_field: bool = false;
 
             for field in &self.fields {
                 if field.index.value() == i {
-                    total_size += match field.full_encoded_size(true)? {
-                        Some(value) => PESSIMAL_ENCODING + value,
+                    total_size += match field.full_encoded_size_in(true, wire_format)? {
+                        Some(value) => pessimal_encoding + value,
                         // Field was a sub-message with a skipped field, and we thus cannot calculate a worst case size
                         None => return Ok(None)
                     };
@@ -227,3 +349,79 @@ impl MessageDefinition {
         Ok(Some(total_size))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::{test_utils::parse_files, types::{FieldIndex, WireFormat}};
+
+    #[test]
+    fn varint_size_grows_every_seven_bits() {
+        let sizes: Vec<u64> = [0, 1, 127, 128, 16_383, 16_384, u32::MAX as u64, u64::MAX].into_iter().map(WireFormat::varint_size).collect();
+        assert_eq!(sizes, vec![1, 1, 1, 2, 2, 3, 5, 10]);
+    }
+
+    #[test]
+    fn varint_length_prefixes_follow_the_data_size() {
+        let files = parse_files(&[("blob.rune", "message Small {\n    data: [u8; 100] = 1;\n}\n\nmessage Large {\n    data: [u8; 200] = 1;\n}\n")]).unwrap();
+        let small = files[0].definitions.find_message("Small").unwrap();
+        let large = files[0].definitions.find_message("Large").unwrap();
+
+        // Header, length prefix, and data
+        assert_eq!(small.optimal_full_encoded_size_in(WireFormat::Fixed).unwrap(), 1 + 1 + 100);
+        assert_eq!(small.optimal_full_encoded_size_in(WireFormat::Varint).unwrap(), 1 + 1 + 100);
+        assert_eq!(large.optimal_full_encoded_size_in(WireFormat::Fixed).unwrap(), 1 + 1 + 200);
+        assert_eq!(large.optimal_full_encoded_size_in(WireFormat::Varint).unwrap(), 1 + 2 + 200);
+    }
+
+    #[test]
+    fn varint_pessimal_overhead_fits_any_length() {
+        assert_eq!(WireFormat::Fixed.pessimal_overhead(), 1 + 4);
+        assert_eq!(WireFormat::Varint.pessimal_overhead(), 1 + 5);
+    }
+
+    #[test]
+    fn optional_fields_only_count_when_present() {
+        let files = parse_files(&[("reading.rune", "message Reading {\n    value: u32 = 0;\n    note?: u32 = 1;\n}\n\nmessage Required {\n    value: u32 = 0;\n}\n")]).unwrap();
+        let reading = files[0].definitions.find_message("Reading").unwrap();
+        let required = files[0].definitions.find_message("Required").unwrap();
+
+        assert!(reading.fields[1].optional);
+        assert_eq!(reading.optimal_required_encoded_size().unwrap(), required.optimal_full_encoded_size().unwrap());
+        assert!(reading.optimal_full_encoded_size().unwrap() > reading.optimal_required_encoded_size().unwrap());
+
+        // The worst case still has room for the optional field
+        assert!(reading.pessimal_encoded_size().unwrap().unwrap() > required.pessimal_encoded_size().unwrap().unwrap());
+    }
+
+    #[test]
+    fn verifier_and_index_zero_hash_as_one_field() {
+        let indexes: HashSet<FieldIndex> = [FieldIndex::Verifier, FieldIndex::Numeric(0)].into_iter().collect();
+        assert_eq!(indexes.len(), 1);
+    }
+
+    #[test]
+    fn bytes_fields_always_carry_a_length_prefix() {
+        let files = parse_files(&[("frame.rune", "message Frame {\n    key: bytes(4) = 0;\n}\n\nmessage Word {\n    key: [u8; 4] = 0;\n}\n")]).unwrap();
+        let frame = files[0].definitions.find_message("Frame").unwrap();
+        let word = files[0].definitions.find_message("Word").unwrap();
+
+        // A header, a one byte length prefix, and four bytes of data, while the fixed size array needs no prefix
+        assert_eq!(frame.optimal_full_encoded_size().unwrap(), 6);
+        assert_eq!(word.optimal_full_encoded_size().unwrap(), 5);
+        assert_eq!(frame.pessimal_encoded_size().unwrap(), Some(9));
+    }
+
+    #[test]
+    fn worst_case_size_of_bytes_fields() {
+        let files = parse_files(&[("blob.rune", "message Open {\n    data: bytes = 0;\n}\n\nmessage Bounded {\n    data: bytes(256) = 0;\n}\n")]).unwrap();
+        let open = files[0].definitions.find_message("Open").unwrap();
+        let bounded = files[0].definitions.find_message("Bounded").unwrap();
+
+        // Unbounded bytes have no largest size, and bounded bytes take a header and the largest length prefix on top of their data
+        assert_eq!(open.pessimal_encoded_size().unwrap(), None);
+        assert_eq!(bounded.pessimal_encoded_size().unwrap(), Some(261));
+        assert_eq!(bounded.optimal_full_encoded_size().unwrap(), 259);
+    }
+}
@@ -7,23 +7,68 @@ pub mod includes;
 pub mod links;
 pub mod messages;
 pub mod primitives;
+pub mod schema_hash;
 pub mod standalone_comments;
 pub mod structs;
+pub mod values;
 
 pub use arrays::{Array, ArraySize, ArrayType};
 pub use bitfields::{BitSize, BitfieldDefinition, BitfieldMember};
-pub use defines::{DefineDefinition, DefineValue, RedefineDefinition};
+pub use defines::{DefineDefinition, DefineExpression, DefineOperator, DefineValue, RedefineDefinition};
 pub use enums::{EnumDefinition, EnumMember};
-pub use extensions::{ExtensionDefinition, Extensions};
+pub use extensions::{ExtensionDefinition, ExtensionRef, Extensions};
 pub use includes::IncludeDefinition;
 pub use links::UserDefinitionLink;
-pub use messages::{FieldIndex, FieldType, MessageDefinition, MessageField};
+pub use messages::{FieldIndex, FieldType, MessageDefinition, MessageField, WireFormat};
 pub use primitives::Primitive;
 pub use standalone_comments::StandaloneCommentDefinition;
 pub use structs::{MemberType, StructDefinition, StructMember};
+pub use values::ValueType;
+
+/// Borrowed view of a single type definition, regardless of its kind
+#[derive(Debug, Clone, Copy)]
+pub enum TypeRef<'a> {
+    Bitfield(&'a BitfieldDefinition),
+    Enum(&'a EnumDefinition),
+    Message(&'a MessageDefinition),
+    Struct(&'a StructDefinition)
+}
+
+impl TypeRef<'_> {
+    /// Name of the referenced definition
+    pub fn name(&self) -> &str {
+        match self {
+            TypeRef::Bitfield(definition) => &definition.name,
+            TypeRef::Enum(definition) => &definition.name,
+            TypeRef::Message(definition) => &definition.name,
+            TypeRef::Struct(definition) => &definition.name
+        }
+    }
+}
+
+/// Top level declaration of a file. Definitions are referred to by name, and standalone comments by their position among the standalone comments
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Declaration {
+    Bitfield(String),
+    Comment(usize),
+    Define(String),
+    Enum(String),
+    Message(String),
+    Struct(String)
+}
+
+/// Borrowed view of a top level declaration
+#[derive(Debug, Clone, Copy)]
+pub enum DeclarationRef<'a> {
+    Comment(&'a StandaloneCommentDefinition),
+    Define(&'a DefineDefinition),
+    Type(TypeRef<'a>)
+}
 
 /// Top Level Struct containing all message definitions in a compilation unit (file + includes)
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Definitions {
     pub bitfields:           Vec<BitfieldDefinition>,
     pub defines:             Vec<DefineDefinition>,
@@ -33,5 +78,173 @@ pub struct Definitions {
     pub includes:            Vec<IncludeDefinition>,
     pub messages:            Vec<MessageDefinition>,
     pub standalone_comments: Vec<StandaloneCommentDefinition>,
-    pub structs:             Vec<StructDefinition>
+    pub structs:             Vec<StructDefinition>,
+    /// Schema version declared by the file, if any
+    pub version:             Option<u64>,
+    /// Bitfields, comments, defines, enums, messages, and structs in the order they are declared in the file. Sorting definitions by
+    /// dependency reorders their lists, but never this one
+    pub declaration_order:   Vec<Declaration>,
+    /// Names of the definitions left out because they require a later version than the file declares, with the version they require
+    pub gated_definitions:   Vec<(String, u64)>
+}
+
+impl Definitions {
+    pub fn with_capacity(size: usize) -> Definitions {
+        Definitions {
+            bitfields:           Vec::with_capacity(size),
+            defines:             Vec::with_capacity(size),
+            redefines:           Vec::with_capacity(size),
+            enums:               Vec::with_capacity(size),
+            extensions:          Extensions::with_capacity(size),
+            includes:            Vec::with_capacity(size),
+            messages:            Vec::with_capacity(size),
+            standalone_comments: Vec::with_capacity(size),
+            structs:             Vec::with_capacity(size),
+            version:             None,
+            declaration_order:   Vec::with_capacity(size),
+            gated_definitions:   Vec::new()
+        }
+    }
+
+    pub fn find_bitfield(&self, name: &str) -> Option<&BitfieldDefinition> {
+        self.bitfields.iter().find(|definition| definition.name == name)
+    }
+
+    pub fn find_enum(&self, name: &str) -> Option<&EnumDefinition> {
+        self.enums.iter().find(|definition| definition.name == name)
+    }
+
+    pub fn find_message(&self, name: &str) -> Option<&MessageDefinition> {
+        self.messages.iter().find(|definition| definition.name == name)
+    }
+
+    pub fn find_struct(&self, name: &str) -> Option<&StructDefinition> {
+        self.structs.iter().find(|definition| definition.name == name)
+    }
+
+    /// Finds a bitfield, enum, message, or struct by name
+    pub fn find_type(&self, name: &str) -> Option<TypeRef<'_>> {
+        self.find_bitfield(name)
+            .map(TypeRef::Bitfield)
+            .or_else(|| self.find_enum(name).map(TypeRef::Enum))
+            .or_else(|| self.find_message(name).map(TypeRef::Message))
+            .or_else(|| self.find_struct(name).map(TypeRef::Struct))
+    }
+
+    /// Top level declarations in the order they are declared in the file. Definitions that were left out, such as by the declared version, are skipped
+    pub fn declarations(&self) -> impl Iterator<Item = DeclarationRef<'_>> {
+        self.declaration_order.iter().filter_map(|declaration| match declaration {
+            Declaration::Bitfield(name) => self.find_bitfield(name).map(|definition| DeclarationRef::Type(TypeRef::Bitfield(definition))),
+            Declaration::Comment(index) => self.standalone_comments.get(*index).map(DeclarationRef::Comment),
+            Declaration::Define(name) => self.defines.iter().find(|definition| definition.name == *name).map(DeclarationRef::Define),
+            Declaration::Enum(name) => self.find_enum(name).map(|definition| DeclarationRef::Type(TypeRef::Enum(definition))),
+            Declaration::Message(name) => self.find_message(name).map(|definition| DeclarationRef::Type(TypeRef::Message(definition))),
+            Declaration::Struct(name) => self.find_struct(name).map(|definition| DeclarationRef::Type(TypeRef::Struct(definition)))
+        })
+    }
+
+    /// Names of all bitfields, enums, messages, and structs, in that order
+    pub fn all_type_names(&self) -> Vec<&str> {
+        self.bitfields
+            .iter()
+            .map(|definition| definition.name.as_str())
+            .chain(self.enums.iter().map(|definition| definition.name.as_str()))
+            .chain(self.messages.iter().map(|definition| definition.name.as_str()))
+            .chain(self.structs.iter().map(|definition| definition.name.as_str()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        parse_str,
+        test_utils::count_allocations,
+        types::{DeclarationRef, Definitions, TypeRef}
+    };
+
+    /// Fill definitions with every struct and message of a large schema, moving them in so only the lists themselves allocate
+    fn fill(mut definitions: Definitions, source: &Definitions) -> usize {
+        let structs = source.structs.clone();
+        let messages = source.messages.clone();
+
+        let ((), allocations) = count_allocations(|| {
+            for definition in structs {
+                definitions.structs.push(definition);
+            }
+            for definition in messages {
+                definitions.messages.push(definition);
+            }
+        });
+
+        allocations
+    }
+
+    #[test]
+    fn definitions_with_capacity_allocate_less() {
+        let mut source = String::new();
+        for index in 0..16 {
+            source.push_str(&format!("struct Point{0} {{\n    x: i32 = 0;\n    y: i32 = 1;\n}}\n\n", index));
+            source.push_str(&format!("message Move{0} {{\n    to: Point{0} = 1;\n}}\n\n", index));
+        }
+        let schema = parse_str(&source, "large.rune").unwrap();
+
+        let growing = fill(Definitions::default(), &schema);
+        let sized = fill(Definitions::with_capacity(16), &schema);
+
+        // Growing from empty reallocates each list as it fills up, while sized lists never need to
+        assert_eq!(sized, 0);
+        assert!(growing > sized, "{0} allocations when growing, {1} when sized", growing, sized);
+    }
+
+    const LOOKUP_SOURCE: &str = "bitfield Flags: u8 {\n    ready: u1 = 0;\n}\n\nenum Mode: u8 {\n    Off = 0;\n}\n\n\
+                                 message Packet {\n    mode: Mode = 1;\n}\n\nstruct Point {\n    x: i32 = 0;\n}\n";
+
+    #[test]
+    fn lookups_find_each_kind() {
+        let definitions = parse_str(LOOKUP_SOURCE, "lookup.rune").unwrap();
+
+        assert_eq!(definitions.find_bitfield("Flags").unwrap().name, "Flags");
+        assert_eq!(definitions.find_enum("Mode").unwrap().name, "Mode");
+        assert_eq!(definitions.find_message("Packet").unwrap().name, "Packet");
+        assert_eq!(definitions.find_struct("Point").unwrap().name, "Point");
+
+        assert!(matches!(definitions.find_type("Flags"), Some(TypeRef::Bitfield(_))));
+        assert!(matches!(definitions.find_type("Mode"), Some(TypeRef::Enum(_))));
+        assert!(matches!(definitions.find_type("Packet"), Some(TypeRef::Message(_))));
+        assert!(matches!(definitions.find_type("Point"), Some(TypeRef::Struct(_))));
+    }
+
+    #[test]
+    fn lookups_miss_unknown_names_and_other_kinds() {
+        let definitions = parse_str(LOOKUP_SOURCE, "lookup.rune").unwrap();
+
+        assert!(definitions.find_type("Missing").is_none());
+        assert!(definitions.find_struct("Packet").is_none());
+        assert!(definitions.find_enum("Flags").is_none());
+    }
+
+    #[test]
+    fn all_type_names_are_listed_by_kind() {
+        let definitions = parse_str(LOOKUP_SOURCE, "lookup.rune").unwrap();
+        assert_eq!(definitions.all_type_names(), ["Flags", "Mode", "Packet", "Point"]);
+    }
+
+    #[test]
+    fn declarations_keep_source_order_across_kinds() {
+        let source = "struct First {\n    a: u8 = 0;\n}\n\n// Between the struct and the enum\n\n// Comment of the enum\nenum Second: u8 {\n    A = 0;\n}\n\ndefine THIRD 3;\n\n\
+                      struct Fourth {\n    a: u8 = 0;\n}\n";
+        let definitions = parse_str(source, "order.rune").unwrap();
+
+        let order: Vec<String> = definitions
+            .declarations()
+            .map(|declaration| match declaration {
+                DeclarationRef::Comment(comment) => comment.comment.trim().to_string(),
+                DeclarationRef::Define(define) => define.name.clone(),
+                DeclarationRef::Type(type_ref) => type_ref.name().to_string()
+            })
+            .collect();
+
+        assert_eq!(order, ["First", "Between the struct and the enum", "Second", "THIRD", "Fourth"]);
+    }
 }
@@ -1,6 +1,7 @@
-use std::ops::Range;
+use std::ops::RangeInclusive;
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Primitive {
     // 1 byte primitives
     Bool,
@@ -9,6 +10,7 @@ pub enum Primitive {
     U8,
 
     // 2 byte primitives
+    F16,
     I16,
     U16,
 
@@ -29,32 +31,164 @@ pub enum Primitive {
 
 impl Primitive {
     // Single Byte
-    pub const I8_RANGE: Range<i64> = (i8::MIN as i64)..(i8::MAX as i64);
-    pub const U8_RANGE: Range<u64> = (u8::MIN as u64)..(u8::MAX as u64);
+    pub const I8_RANGE: RangeInclusive<i64> = (i8::MIN as i64)..=(i8::MAX as i64);
+    pub const U8_RANGE: RangeInclusive<u64> = (u8::MIN as u64)..=(u8::MAX as u64);
 
-    // Two Bytes
-    pub const I16_RANGE: Range<i64> = (i16::MIN as i64)..(i16::MAX as i64);
-    pub const U16_RANGE: Range<u64> = (u16::MIN as u64)..(u16::MAX as u64);
+    // Two Bytes - Half precision floats hold up to 65504 in magnitude
+    pub const F16_RANGE: RangeInclusive<f64> = -65504.0..=65504.0;
+    pub const I16_RANGE: RangeInclusive<i64> = (i16::MIN as i64)..=(i16::MAX as i64);
+    pub const U16_RANGE: RangeInclusive<u64> = (u16::MIN as u64)..=(u16::MAX as u64);
 
     // Four Bytes
-    pub const F32_RANGE: Range<f64> = (f32::MIN as f64)..(f32::MAX as f64);
-    pub const I32_RANGE: Range<i64> = (i32::MIN as i64)..(i32::MAX as i64);
-    pub const U32_RANGE: Range<u64> = (u32::MIN as u64)..(u32::MAX as u64);
+    pub const F32_RANGE: RangeInclusive<f64> = (f32::MIN as f64)..=(f32::MAX as f64);
+    pub const I32_RANGE: RangeInclusive<i64> = (i32::MIN as i64)..=(i32::MAX as i64);
+    pub const U32_RANGE: RangeInclusive<u64> = (u32::MIN as u64)..=(u32::MAX as u64);
+
+    // Sixteen Bytes
+    pub const I128_RANGE: RangeInclusive<i128> = i128::MIN..=i128::MAX;
+    pub const U128_RANGE: RangeInclusive<u128> = u128::MIN..=u128::MAX;
+
+    /// Primitive written as the given type name, such as `u8`, or None if the name is not a primitive
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(name: &str) -> Option<Primitive> {
+        match name {
+            "bool" => Some(Primitive::Bool),
+            "char" => Some(Primitive::Char),
+            "i8" => Some(Primitive::I8),
+            "u8" => Some(Primitive::U8),
+            "i16" => Some(Primitive::I16),
+            "u16" => Some(Primitive::U16),
+            "f16" => Some(Primitive::F16),
+            "f32" => Some(Primitive::F32),
+            "i32" => Some(Primitive::I32),
+            "u32" => Some(Primitive::U32),
+            "f64" => Some(Primitive::F64),
+            "i64" => Some(Primitive::I64),
+            "u64" => Some(Primitive::U64),
+            "i128" => Some(Primitive::I128),
+            "u128" => Some(Primitive::U128),
+            _ => None
+        }
+    }
+
+    /// Type name the primitive is written as in a rune file
+    pub fn name(&self) -> &'static str {
+        match self {
+            Primitive::Bool => "bool",
+            Primitive::Char => "char",
+            Primitive::I8 => "i8",
+            Primitive::U8 => "u8",
+            Primitive::I16 => "i16",
+            Primitive::U16 => "u16",
+            Primitive::F16 => "f16",
+            Primitive::F32 => "f32",
+            Primitive::I32 => "i32",
+            Primitive::U32 => "u32",
+            Primitive::F64 => "f64",
+            Primitive::I64 => "i64",
+            Primitive::U64 => "u64",
+            Primitive::I128 => "i128",
+            Primitive::U128 => "u128"
+        }
+    }
 
     pub fn is_signed(&self) -> bool {
         matches!(
             self,
-            Primitive::Char | Primitive::I8 | Primitive::I16 | Primitive::F32 | Primitive::I32 | Primitive::F64 | Primitive::I64 | Primitive::I128
+            Primitive::Char | Primitive::I8 | Primitive::F16 | Primitive::I16 | Primitive::F32 | Primitive::I32 | Primitive::F64 | Primitive::I64 | Primitive::I128
         )
     }
 
     pub fn encoded_max_data_size(&self) -> u64 {
         match self {
             Primitive::Bool | Primitive::Char | Primitive::I8 | Primitive::U8 => 1,
-            Primitive::I16 | Primitive::U16 => 2,
+            Primitive::F16 | Primitive::I16 | Primitive::U16 => 2,
             Primitive::F32 | Primitive::I32 | Primitive::U32 => 4,
             Primitive::F64 | Primitive::I64 | Primitive::U64 => 8,
             Primitive::I128 | Primitive::U128 => 16
         }
     }
+
+    /// Number of bits needed to hold any value of the primitive when packed at the bit level. Booleans only need a single bit
+    pub fn bit_size(&self) -> u64 {
+        match self {
+            Primitive::Bool => 1,
+            primitive => primitive.encoded_max_data_size() * 8
+        }
+    }
+
+    /// Natural alignment of the primitive when laid out in a non-packed C struct
+    pub fn alignment(&self) -> u64 {
+        self.encoded_max_data_size()
+    }
+
+    /// Name of the C type holding the primitive. 128 bit integers use the GCC and Clang extension types
+    pub fn c_type(&self) -> &'static str {
+        match self {
+            Primitive::Bool => "bool",
+            Primitive::Char => "char",
+            Primitive::I8 => "int8_t",
+            Primitive::U8 => "uint8_t",
+            Primitive::I16 => "int16_t",
+            Primitive::U16 => "uint16_t",
+            Primitive::F16 => "_Float16",
+            Primitive::F32 => "float",
+            Primitive::I32 => "int32_t",
+            Primitive::U32 => "uint32_t",
+            Primitive::F64 => "double",
+            Primitive::I64 => "int64_t",
+            Primitive::U64 => "uint64_t",
+            Primitive::I128 => "__int128_t",
+            Primitive::U128 => "__uint128_t"
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::Primitive;
+
+    const PRIMITIVES: [Primitive; 15] = [
+        Primitive::Bool,
+        Primitive::Char,
+        Primitive::I8,
+        Primitive::U8,
+        Primitive::I16,
+        Primitive::U16,
+        Primitive::F16,
+        Primitive::F32,
+        Primitive::I32,
+        Primitive::U32,
+        Primitive::F64,
+        Primitive::I64,
+        Primitive::U64,
+        Primitive::I128,
+        Primitive::U128
+    ];
+
+    #[test]
+    fn every_primitive_has_a_c_type() {
+        let c_types: Vec<&str> = PRIMITIVES.iter().map(Primitive::c_type).collect();
+
+        assert_eq!(
+            c_types,
+            [
+                "bool", "char", "int8_t", "uint8_t", "int16_t", "uint16_t", "_Float16", "float", "int32_t", "uint32_t", "double", "int64_t", "uint64_t",
+                "__int128_t", "__uint128_t"
+            ]
+        );
+    }
+
+    #[test]
+    fn every_primitive_round_trips_through_its_name() {
+        for primitive in PRIMITIVES {
+            assert_eq!(Primitive::from_str(primitive.name()), Some(primitive.clone()), "{0} did not round trip", primitive.name());
+        }
+    }
+
+    #[test]
+    fn unknown_type_name_is_not_a_primitive() {
+        assert_eq!(Primitive::from_str("foo"), None);
+        assert_eq!(Primitive::from_str("U8"), None);
+    }
 }
@@ -0,0 +1,183 @@
+use crate::{
+    scanner::NumericLiteral,
+    types::{Array, ArrayType, BitSize, Definitions, FieldType, ValueType}
+};
+
+/// 64 bit FNV-1a hasher. Unlike the hashers of the standard library, its output is the same on every platform and every Rust version
+struct SchemaHasher {
+    state: u64
+}
+
+impl SchemaHasher {
+    const OFFSET_BASIS: u64 = 0xCBF2_9CE4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01B3;
+
+    /// Separates written pieces of text, so that "ab" followed by "c" does not hash like "a" followed by "bc"
+    const SEPARATOR: u8 = 0x1F;
+
+    fn new() -> SchemaHasher {
+        SchemaHasher { state: SchemaHasher::OFFSET_BASIS }
+    }
+
+    fn write(&mut self, text: &str) {
+        for byte in text.bytes().chain([SchemaHasher::SEPARATOR]) {
+            self.state ^= byte as u64;
+            self.state = self.state.wrapping_mul(SchemaHasher::PRIME);
+        }
+    }
+}
+
+impl Definitions {
+    /// Stable hash of the structure of all bitfields, enums, messages, and structs, for peers to check that they were built against
+    /// compatible schemas. Names, indexes, types, enum wire types, and enum values are hashed, while comments, other annotations, and the
+    /// order definitions and fields are written in are not. Array sizes given by defines are hashed by value once resolved, and by name
+    /// before that
+    pub fn schema_hash(&self) -> u64 {
+        let mut hasher = SchemaHasher::new();
+
+        let mut bitfields: Vec<_> = self.bitfields.iter().collect();
+        bitfields.sort_by(|a, b| a.name.cmp(&b.name));
+
+        for bitfield_definition in bitfields {
+            hasher.write("bitfield");
+            hasher.write(&bitfield_definition.name);
+            hasher.write(bitfield_definition.backing_type.name());
+
+            let mut members: Vec<_> = bitfield_definition.members.iter().collect();
+            members.sort_by_key(|member| member.index);
+
+            for member in members {
+                hasher.write(&member.index.to_string());
+                hasher.write(&member.identifier);
+                hasher.write(&match member.size {
+                    BitSize::Signed(size) => format!("i{0}", size),
+                    BitSize::Unsigned(size) => format!("u{0}", size)
+                });
+            }
+        }
+
+        let mut enums: Vec<_> = self.enums.iter().collect();
+        enums.sort_by(|a, b| a.name.cmp(&b.name));
+
+        for enum_definition in enums {
+            hasher.write("enum");
+            hasher.write(&enum_definition.name);
+            hasher.write(enum_definition.backing_type.name());
+            // Values are sent as the wire type, which differs from the backing type when narrowed with @encode_as
+            hasher.write(enum_definition.wire_type().name());
+
+            let mut members: Vec<_> = enum_definition.members.iter().collect();
+            members.sort_by(|a, b| a.identifier.cmp(&b.identifier));
+
+            for member in members {
+                hasher.write(&member.identifier);
+                hasher.write(&canonical_literal(&member.value));
+            }
+        }
+
+        let mut messages: Vec<_> = self.messages.iter().collect();
+        messages.sort_by(|a, b| a.name.cmp(&b.name));
+
+        for message_definition in messages {
+            hasher.write("message");
+            hasher.write(&message_definition.name);
+
+            let mut fields: Vec<_> = message_definition.fields.iter().collect();
+            fields.sort_by_key(|field| field.index.value());
+
+            for field in fields {
+                hasher.write(&field.index.value().to_string());
+                hasher.write(&field.identifier);
+                hasher.write(if field.optional { "optional" } else { "required" });
+                hasher.write(&match &field.data_type {
+                    FieldType::Empty => String::from("(empty)"),
+                    FieldType::Value(value_type) => canonical_type(value_type),
+                    FieldType::Bytes(max_size) => format!("{0:?}", FieldType::Bytes(*max_size))
+                });
+            }
+        }
+
+        let mut structs: Vec<_> = self.structs.iter().collect();
+        structs.sort_by(|a, b| a.name.cmp(&b.name));
+
+        for struct_definition in structs {
+            hasher.write("struct");
+            hasher.write(&struct_definition.name);
+
+            let mut members: Vec<_> = struct_definition.members.iter().collect();
+            members.sort_by_key(|member| member.index);
+
+            for member in members {
+                hasher.write(&member.index.to_string());
+                hasher.write(&member.identifier);
+                hasher.write(&canonical_type(&member.data_type));
+            }
+
+            if let Some(size) = struct_definition.external_size {
+                hasher.write(&size.to_string());
+            }
+        }
+
+        hasher.state
+    }
+}
+
+/// Type as written in Rune, with array sizes written in decimal
+fn canonical_type(value_type: &ValueType) -> String {
+    match value_type {
+        ValueType::Primitive(primitive) => String::from(primitive.name()),
+        ValueType::UserDefined(name, _) => name.clone(),
+        ValueType::Array(array) => canonical_array(array)
+    }
+}
+
+fn canonical_array(array: &Array) -> String {
+    let element_type: String = match &array.data_type {
+        ArrayType::Primitive(primitive) => String::from(primitive.name()),
+        ArrayType::UserDefined(name, _) => name.clone(),
+        ArrayType::Array(inner_array) => canonical_array(inner_array)
+    };
+
+    let element_count: String = match array.element_count.value() {
+        Ok(value) => value.to_string(),
+        Err(_) => array.element_count.to_string()
+    };
+
+    format!("[{0}; {1}]", element_type, element_count)
+}
+
+/// Literal written in decimal, so that the same value written in another numeral system hashes the same
+fn canonical_literal(literal: &NumericLiteral) -> String {
+    match literal {
+        NumericLiteral::PositiveInteger(value, _) => value.to_string(),
+        NumericLiteral::NegativeInteger(value, _) => value.to_string(),
+        literal => literal.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse_str;
+
+    fn hash(source: &str) -> u64 {
+        parse_str(source, "schema.rune").unwrap().schema_hash()
+    }
+
+    const COMMAND: &str = "enum Command: u32 {\n    Start = 1;\n    Stop = 2;\n}\n\nmessage Request {\n    command: Command = 1;\n    count: u16 = 2;\n}\n";
+
+    #[test]
+    fn changing_a_field_index_changes_the_hash() {
+        assert_ne!(hash(COMMAND), hash(&COMMAND.replace("count: u16 = 2;", "count: u16 = 3;")));
+    }
+
+    #[test]
+    fn changing_only_comments_keeps_the_hash() {
+        let commented = COMMAND.replace("message Request {", "// Sent by the client\nmessage Request {\n    // How many times").replace("    Stop", "    /* Halt */ Stop");
+        assert_eq!(hash(COMMAND), hash(&commented));
+    }
+
+    #[test]
+    fn narrowing_an_enum_changes_the_hash() {
+        assert_ne!(hash(COMMAND), hash(&format!("@encode_as(u8)\n{0}", COMMAND)));
+    }
+}
@@ -1,4 +1,5 @@
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// A comment not connected to any data field or data declaration
 pub struct StandaloneCommentDefinition {
     pub comment: String,
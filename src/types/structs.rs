@@ -1,12 +1,11 @@
-use std::fmt::{Debug, Formatter};
-
 use crate::{
     output::*,
-    types::{Array, Primitive, StandaloneCommentDefinition, UserDefinitionLink},
+    types::{Array, ArrayType, DefineValue, StandaloneCommentDefinition, UserDefinitionLink, ValueType},
     RuneParserError
 };
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct StructDefinition {
     /// Name of the struct
     pub name:            String,
@@ -15,10 +14,21 @@ pub struct StructDefinition {
     /// Comment describing the struct
     pub comment:         Option<String>,
     /// Loose comments inside the struct declaration
-    pub orphan_comments: Vec<StandaloneCommentDefinition>
+    pub orphan_comments: Vec<StandaloneCommentDefinition>,
+    /// Schema version the definition was introduced in. It is left out of files declaring an older version
+    pub min_version:     Option<u64>,
+    /// Name of the group the definition belongs to, used to cluster related definitions in generated code
+    pub group:           Option<String>,
+    /// Compiler specific attributes emitted verbatim in generated C, such as `section(".fast")`
+    pub c_attributes:    Vec<String>,
+    /// Whether generated code must keep the members in index order, rather than reordering them to reduce padding
+    pub no_sort:         bool,
+    /// Size in bytes of a struct defined outside of Rune, such as in a hand-written C header. External structs have no members
+    pub external_size:   Option<u64>
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct StructMember {
     /// Name of the data field
     pub identifier: String,
@@ -27,10 +37,15 @@ pub struct StructMember {
     /// Index of the data field - Structs do not have a limit on indexes
     pub index:      u64,
     /// Comment describing the data field
-    pub comment:    Option<String>
+    pub comment:    Option<String>,
+    /// Whether a char array member should be treated as a NUL terminated C string
+    pub cstring:    bool,
+    /// Value the member is initialized to, if declared
+    pub default:    Option<DefineValue>
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MemberIndex {
     /// Used for regular fields
     Numeric(u64),
@@ -39,64 +54,21 @@ pub enum MemberIndex {
     Verifier
 }
 
-#[derive(Clone)]
-pub enum MemberType {
-    Array(Array),
-    Primitive(Primitive),
-
-    /// If the data type of the field is a user defined one, then it will contain a copy of its definition
-    UserDefined(String, UserDefinitionLink)
-}
-
-impl Debug for MemberType {
-    fn fmt(&self, formatter: &mut Formatter) -> std::fmt::Result {
-        match self {
-            MemberType::Primitive(primitive) => match primitive {
-                Primitive::Bool => write!(formatter, "bool"),
-                Primitive::Char => write!(formatter, "char"),
-                Primitive::I8 => write!(formatter, "i8"),
-                Primitive::U8 => write!(formatter, "u8"),
-                Primitive::I16 => write!(formatter, "i16"),
-                Primitive::U16 => write!(formatter, "u16"),
-                Primitive::F32 => write!(formatter, "f32"),
-                Primitive::I32 => write!(formatter, "i32"),
-                Primitive::U32 => write!(formatter, "u32"),
-                Primitive::F64 => write!(formatter, "f64"),
-                Primitive::I64 => write!(formatter, "i64"),
-                Primitive::U64 => write!(formatter, "u64"),
-                Primitive::I128 => write!(formatter, "i128"),
-                Primitive::U128 => write!(formatter, "u128")
-            },
-            MemberType::Array(array) => write!(formatter, "[{0:?}; {1}]", array.data_type, array.element_count),
-            MemberType::UserDefined(string, _) => write!(formatter, "{0}", string.clone())
-        }
-    }
-}
-
-impl PartialEq for MemberType {
-    fn eq(&self, other: &MemberType) -> bool {
-        match self {
-            MemberType::Primitive(primitive) => match other {
-                MemberType::Primitive(other_primitive) => primitive == other_primitive,
-                _ => false
-            },
-
-            MemberType::Array(array) => match other {
-                MemberType::Array(other_array) => array == other_array,
-                _ => false
-            },
+/// Struct members share their data types with message fields
+pub type MemberType = ValueType;
 
-            MemberType::UserDefined(string, _) => match other {
-                MemberType::UserDefined(other_string, _) => string == other_string,
-                _ => false
-            }
-        }
+impl StructDefinition {
+    /// Whether the struct is defined outside of Rune, and only declared so other definitions can use it
+    pub fn is_external(&self) -> bool {
+        self.external_size.is_some()
     }
-}
 
-impl StructDefinition {
     /// Size of struct when all members are flattened into a long data blob with no padding
     pub fn flat_size(&self) -> Result<u64, RuneParserError> {
+        if let Some(external_size) = self.external_size {
+            return Ok(external_size);
+        }
+
         let mut total_size: u64 = 0;
 
         for member in &self.members {
@@ -129,4 +101,188 @@ impl StructDefinition {
 
         Ok(total_size)
     }
+
+    /// Size of the struct in bits when all members are packed at the bit level, with no padding between or inside them. External structs
+    /// are opaque, so they take all the bits of their declared size
+    pub fn bit_packed_size(&self) -> Result<u64, RuneParserError> {
+        if let Some(external_size) = self.external_size {
+            return Ok(external_size * 8);
+        }
+
+        let mut total_size: u64 = 0;
+
+        for member in &self.members {
+            total_size += member.data_type.bit_size()?;
+        }
+
+        Ok(total_size)
+    }
+
+    /// Alignment of the struct when laid out without packing, which is the largest alignment of any of its members
+    pub fn alignment(&self) -> Result<u64, RuneParserError> {
+        let (_, alignment) = self.layout()?;
+        Ok(alignment)
+    }
+
+    /// Size of struct when laid out without packing, following C rules. Members are placed in index order at their natural alignment, and the total is rounded up to the alignment of the struct
+    pub fn size_with_padding(&self) -> Result<u64, RuneParserError> {
+        let (size, _) = self.layout()?;
+        Ok(size)
+    }
+
+    /// Padded size and alignment of the struct, computed together so nested structs are only walked once
+    fn layout(&self) -> Result<(u64, u64), RuneParserError> {
+        // The layout of external structs is unknown, so assume the largest primitive alignment that evenly divides their size
+        if let Some(external_size) = self.external_size {
+            let alignment: u64 = [8, 4, 2].into_iter().find(|alignment| external_size % alignment == 0).unwrap_or(1);
+            return Ok((external_size, alignment));
+        }
+
+        let mut members: Vec<&StructMember> = self.members.iter().collect();
+        members.sort_by_key(|member| member.index);
+
+        let mut total_size: u64 = 0;
+        let mut alignment: u64 = 1;
+
+        for member in members {
+            let (member_size, member_alignment) = self.member_layout(member)?;
+
+            // Insert padding before the member so that it starts at its natural alignment
+            total_size = total_size.next_multiple_of(member_alignment) + member_size;
+            alignment = alignment.max(member_alignment);
+        }
+
+        // Insert trailing padding so that arrays of the struct keep every element aligned
+        Ok((total_size.next_multiple_of(alignment), alignment))
+    }
+
+    /// Padded size and alignment of a single member
+    fn member_layout(&self, member: &StructMember) -> Result<(u64, u64), RuneParserError> {
+        match &member.data_type {
+            MemberType::Primitive(primitive) => Ok((primitive.encoded_max_data_size(), primitive.alignment())),
+            MemberType::Array(array) => self.array_layout(member, array),
+            MemberType::UserDefined(type_identifier, definition_link) => self.link_layout(member, type_identifier, definition_link)
+        }
+    }
+
+    /// Padded size and alignment of an array member. Nested arrays are laid out contiguously, with the alignment of their innermost type
+    fn array_layout(&self, member: &StructMember, array: &Array) -> Result<(u64, u64), RuneParserError> {
+        let (element_size, alignment) = match &array.data_type {
+            ArrayType::Primitive(primitive) => (primitive.encoded_max_data_size(), primitive.alignment()),
+            ArrayType::UserDefined(type_identifier, definition_link) => self.link_layout(member, type_identifier, definition_link)?,
+            ArrayType::Array(inner_array) => self.array_layout(member, inner_array)?
+        };
+
+        Ok((element_size * array.element_count.value()?, alignment))
+    }
+
+    /// Padded size and alignment of a user defined member type
+    fn link_layout(&self, member: &StructMember, type_identifier: &String, definition_link: &UserDefinitionLink) -> Result<(u64, u64), RuneParserError> {
+        match definition_link {
+            UserDefinitionLink::NoLink => {
+                error!(
+                    "No definition for member {0} of type {1} in struct {2}! This should not happen!",
+                    member.identifier, type_identifier, self.name
+                );
+                Err(RuneParserError::UndefinedIdentifier)
+            },
+            UserDefinitionLink::BitfieldLink(bitfield_definition) => Ok((bitfield_definition.backing_type.encoded_max_data_size(), bitfield_definition.backing_type.alignment())),
+            UserDefinitionLink::EnumLink(enum_definition) => Ok((enum_definition.backing_type.encoded_max_data_size(), enum_definition.backing_type.alignment())),
+            UserDefinitionLink::MessageLink(message_link) => {
+                error!(
+                    "Structs cannot contain message members! Member {0} of struct {1} contained message {2}",
+                    member.identifier, self.name, message_link.name
+                );
+                Err(RuneParserError::InvalidStructMemberType)
+            },
+            UserDefinitionLink::StructLink(struct_definition) => struct_definition.layout()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::parse_files;
+
+    #[test]
+    fn padded_size_includes_alignment_padding() {
+        let files = parse_files(&[("mixed.rune", "struct Mixed {\n    a: u8 = 0;\n    b: u32 = 1;\n    c: u16 = 2;\n}\n")]).unwrap();
+        let mixed = &files[0].definitions.structs[0];
+
+        // 1 byte, 3 bytes of padding, 4 bytes, 2 bytes, and 2 bytes of trailing padding
+        assert_eq!(mixed.flat_size().unwrap(), 7);
+        assert_eq!(mixed.size_with_padding().unwrap(), 12);
+        assert_eq!(mixed.alignment().unwrap(), 4);
+    }
+
+    #[test]
+    fn padded_size_follows_index_order() {
+        let files = parse_files(&[("ordered.rune", "struct Ordered {\n    c: u16 = 2;\n    a: u8 = 0;\n    b: u64 = 1;\n}\n")]).unwrap();
+        let ordered = &files[0].definitions.structs[0];
+
+        assert_eq!(ordered.flat_size().unwrap(), 11);
+        assert_eq!(ordered.size_with_padding().unwrap(), 24);
+        assert_eq!(ordered.alignment().unwrap(), 8);
+    }
+
+    #[test]
+    fn nested_structs_keep_their_own_padding() {
+        let source = "struct Inner {\n    a: u32 = 0;\n    b: u8 = 1;\n}\n\nstruct Outer {\n    flag: u8 = 0;\n    inner: Inner = 1;\n    tail: [u8; 3] = 2;\n}\n";
+        let files = parse_files(&[("nested.rune", source)]).unwrap();
+        let outer = files[0].definitions.structs.iter().find(|definition| definition.name == "Outer").unwrap();
+
+        // Inner is 5 bytes flat and 8 padded, so Outer is 1 + 3 padding + 8 + 3, rounded up to 16
+        assert_eq!(outer.flat_size().unwrap(), 9);
+        assert_eq!(outer.size_with_padding().unwrap(), 16);
+        assert_eq!(outer.alignment().unwrap(), 4);
+    }
+
+    #[test]
+    fn packed_and_padded_sizes_match_without_padding() {
+        let files = parse_files(&[("bytes.rune", "struct Bytes {\n    a: u8 = 0;\n    b: [u8; 3] = 1;\n}\n")]).unwrap();
+        let bytes = &files[0].definitions.structs[0];
+
+        assert_eq!(bytes.flat_size().unwrap(), bytes.size_with_padding().unwrap());
+        assert_eq!(bytes.alignment().unwrap(), 1);
+    }
+
+    #[test]
+    fn external_member_uses_its_declared_size() {
+        let files = parse_files(&[("event.rune", "@size(8)\nexternal struct PlatformTime;\n\nstruct Event {\n    time: PlatformTime = 0;\n    id: u8 = 1;\n}\n")]).unwrap();
+        let event = files[0].definitions.find_struct("Event").unwrap();
+
+        assert_eq!(event.flat_size().unwrap(), 9);
+    }
+
+    #[test]
+    fn bit_packed_size_sums_member_bits() {
+        let source = "bitfield Nibble: u8 {\n    value: u4 = 0;\n}\n\nstruct Packed {\n    flag: bool = 0;\n    nibble: Nibble = 1;\n    count: u16 = 2;\n}\n";
+        let files = parse_files(&[("packed.rune", source)]).unwrap();
+        let packed = files[0].definitions.find_struct("Packed").unwrap();
+
+        // One bit for the bool, four for the bitfield member, and sixteen for the u16
+        assert_eq!(packed.bit_packed_size().unwrap(), 21);
+        assert_eq!(packed.flat_size().unwrap(), 4);
+    }
+
+    #[test]
+    fn unsorted_struct_can_be_reordered() {
+        let files = parse_files(&[("loose.rune", "struct Loose {\n    a: u8 = 0;\n    b: u64 = 1;\n    c: u8 = 2;\n}\n")]).unwrap();
+        let loose = &files[0].definitions.structs[0];
+
+        // Index order wastes 14 bytes of padding, so generators are free to sort the members
+        assert!(!loose.no_sort);
+        assert_eq!(loose.flat_size().unwrap(), 10);
+        assert_eq!(loose.size_with_padding().unwrap(), 24);
+    }
+
+    #[test]
+    fn no_sort_struct_keeps_declaration_order() {
+        let files = parse_files(&[("wire.rune", "@no_sort\nstruct Wire {\n    a: u8 = 0;\n    b: u64 = 1;\n    c: u8 = 2;\n}\n")]).unwrap();
+        let wire = &files[0].definitions.structs[0];
+
+        assert!(wire.no_sort);
+        assert_eq!(wire.members.iter().map(|member| member.identifier.as_str()).collect::<Vec<_>>(), ["a", "b", "c"]);
+        assert_eq!(wire.size_with_padding().unwrap(), 24);
+    }
 }
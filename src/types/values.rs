@@ -0,0 +1,129 @@
+use std::fmt::{Debug, Formatter};
+
+use crate::{
+    output::*,
+    types::{Array, ArrayType, Primitive, UserDefinitionLink},
+    RuneParserError
+};
+
+/// Data type of a value stored in a struct member or a message field
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ValueType {
+    Array(Array),
+    Primitive(Primitive),
+
+    /// If the data type of the value is a user defined one, then it will contain a copy of its definition
+    UserDefined(String, UserDefinitionLink)
+}
+
+impl ValueType {
+    /// Whether the value is a fixed size char buffer, such as `[char; 32]`
+    pub fn is_char_array(&self) -> bool {
+        matches!(self, ValueType::Array(Array { data_type: ArrayType::Primitive(Primitive::Char), .. }))
+    }
+
+    /// Number of bits needed to hold the value when packed at the bit level. Bitfields take the bits up to the end of their last member,
+    /// enums the bits of their backing type, and structs the sum of their members
+    pub fn bit_size(&self) -> Result<u64, RuneParserError> {
+        match self {
+            ValueType::Primitive(primitive) => Ok(primitive.bit_size()),
+            ValueType::Array(array) => array.bit_size(),
+            ValueType::UserDefined(name, definition_link) => link_bit_size(name, definition_link)
+        }
+    }
+}
+
+impl Array {
+    /// Number of bits needed to hold every element of the array when packed at the bit level
+    pub fn bit_size(&self) -> Result<u64, RuneParserError> {
+        let element_size: u64 = match &self.data_type {
+            ArrayType::Primitive(primitive) => primitive.bit_size(),
+            ArrayType::UserDefined(name, definition_link) => link_bit_size(name, definition_link)?,
+            ArrayType::Array(inner_array) => inner_array.bit_size()?
+        };
+
+        Ok(element_size * self.element_count.value()?)
+    }
+}
+
+/// Number of bits needed to hold a user defined type when packed at the bit level
+fn link_bit_size(name: &str, definition_link: &UserDefinitionLink) -> Result<u64, RuneParserError> {
+    match definition_link {
+        UserDefinitionLink::NoLink => {
+            error!("No definition for type {0}! Types must be linked before their size is known", name);
+            Err(RuneParserError::UndefinedIdentifier)
+        },
+        UserDefinitionLink::BitfieldLink(bitfield_definition) => Ok(bitfield_definition.bit_size()),
+        UserDefinitionLink::EnumLink(enum_definition) => Ok(enum_definition.backing_type.bit_size()),
+        UserDefinitionLink::MessageLink(message_definition) => {
+            error!("Message {0} has no fixed size, so it cannot be packed at the bit level", message_definition.name);
+            Err(RuneParserError::InvalidTypeUse)
+        },
+        UserDefinitionLink::StructLink(struct_definition) => struct_definition.bit_packed_size()
+    }
+}
+
+impl Debug for ValueType {
+    fn fmt(&self, formatter: &mut Formatter) -> std::fmt::Result {
+        match self {
+            ValueType::Primitive(primitive) => write!(formatter, "{0}", primitive.name()),
+            ValueType::Array(array) => write!(formatter, "[{0:?}; {1}]", array.data_type, array.element_count),
+            ValueType::UserDefined(string, _) => write!(formatter, "{0}", string.clone())
+        }
+    }
+}
+
+impl PartialEq for ValueType {
+    fn eq(&self, other: &ValueType) -> bool {
+        match self {
+            ValueType::Primitive(primitive) => match other {
+                ValueType::Primitive(other_primitive) => primitive == other_primitive,
+                _ => false
+            },
+
+            ValueType::Array(array) => match other {
+                ValueType::Array(other_array) => array == other_array,
+                _ => false
+            },
+
+            ValueType::UserDefined(string, _) => match other {
+                ValueType::UserDefined(other_string, _) => string == other_string,
+                _ => false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{test_utils::parse_files, types::ValueType};
+
+    const SOURCE: &str = "enum Mode: u8 {\n    Off = 0;\n    On = 1;\n}\n\nstruct Pair {\n    a: u16 = 0;\n    b: u16 = 1;\n}\n\n\
+                          struct Record {\n    count: u32 = 0;\n    mode: Mode = 1;\n    pairs: [Pair; 2] = 2;\n}\n\n\
+                          message Packet {\n    count: u32 = 1;\n    mode: Mode = 2;\n    pairs: [Pair; 2] = 3;\n}\n";
+
+    #[test]
+    fn struct_members_and_message_fields_share_value_types() {
+        let files = parse_files(&[("shared.rune", SOURCE)]).unwrap();
+        let record = files[0].definitions.find_struct("Record").unwrap();
+        let packet = files[0].definitions.find_message("Packet").unwrap();
+
+        for (member, field) in record.members.iter().zip(&packet.fields) {
+            let field_type: &ValueType = field.data_type.value_type().unwrap();
+            assert_eq!(&member.data_type, field_type);
+        }
+    }
+
+    #[test]
+    fn struct_and_message_sizes_agree_on_shared_value_types() {
+        let files = parse_files(&[("shared.rune", SOURCE)]).unwrap();
+        let record = files[0].definitions.find_struct("Record").unwrap();
+        let packet = files[0].definitions.find_message("Packet").unwrap();
+
+        let field_sizes: Vec<u64> = packet.fields.iter().map(|field| field.full_encoded_size(false).unwrap().unwrap()).collect();
+
+        assert_eq!(field_sizes, [4, 1, 8]);
+        assert_eq!(record.flat_size().unwrap(), field_sizes.iter().sum::<u64>());
+    }
+}
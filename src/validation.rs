@@ -1,8 +1,11 @@
 use crate::{
     output::is_silent,
     scanner::NumericLiteral,
-    types::{FieldIndex, Primitive},
-    RuneFileDescription, RuneParserError
+    types::{
+        Array, ArrayType, ArraySize, BitSize, BitfieldMember, DefineDefinition, DefineExpression, DefineValue, FieldIndex, FieldType, Primitive, UserDefinitionLink,
+        ValueType
+    },
+    RuneFileDescription, RuneParserError, Warning
 };
 
 impl Primitive {
@@ -21,6 +24,7 @@ impl Primitive {
             | Primitive::Char
             | Primitive::I8
             | Primitive::U8
+            | Primitive::F16
             | Primitive::I16
             | Primitive::U16
             | Primitive::F32
@@ -66,9 +70,10 @@ impl Primitive {
             // Single Byte
             Primitive::Bool => matches!(numeric_literal, NumericLiteral::Boolean(_)),
 
+            // Chars hold ASCII characters, written either as a char literal or as their code
             Primitive::Char => match numeric_literal {
                 NumericLiteral::AsciiChar(character) => character.is_ascii(),
-                NumericLiteral::PositiveInteger(value, _) => *value <= u8::MAX as u64,
+                NumericLiteral::PositiveInteger(value, _) => (0..=0x7F).contains(value),
                 _ => false
             },
 
@@ -86,6 +91,11 @@ impl Primitive {
             },
 
             // Two Bytes
+            Primitive::F16 => match numeric_literal {
+                NumericLiteral::AsciiChar(character) => character.is_ascii(),
+                NumericLiteral::Float(float) => float.is_finite() && Primitive::F16_RANGE.contains(float),
+                _ => false
+            },
             Primitive::I16 => match numeric_literal {
                 NumericLiteral::AsciiChar(character) => character.is_ascii(),
                 NumericLiteral::PositiveInteger(value, _) => *value <= i16::MAX as u64,
@@ -101,7 +111,7 @@ impl Primitive {
             // Four Bytes
             Primitive::F32 => match numeric_literal {
                 NumericLiteral::AsciiChar(character) => character.is_ascii(),
-                NumericLiteral::Float(float) => Primitive::F32_RANGE.contains(float),
+                NumericLiteral::Float(float) => float.is_finite() && Primitive::F32_RANGE.contains(float),
                 _ => false
             },
             Primitive::I32 => match numeric_literal {
@@ -117,11 +127,11 @@ impl Primitive {
             },
 
             // Eight Bytes - Make assumptions, as if the value would not fit, we would not even be able to parse it into the program...
-            Primitive::F64 => matches!(numeric_literal, NumericLiteral::Float(_)),
+            Primitive::F64 => matches!(numeric_literal, NumericLiteral::Float(float) if float.is_finite()),
 
             Primitive::I64 => match numeric_literal {
                 NumericLiteral::AsciiChar(character) => character.is_ascii(),
-                NumericLiteral::PositiveInteger(value, _) => *value < i64::MAX as u64,
+                NumericLiteral::PositiveInteger(value, _) => *value <= i64::MAX as u64,
                 NumericLiteral::NegativeInteger(_, _) => true,
                 _ => false
             },
@@ -132,20 +142,101 @@ impl Primitive {
                 _ => false
             },
 
-            _ => unreachable!("Critical! Invalid backing type for enum encountered during verification. This should never happen!")
+            // Sixteen Bytes - Literals only hold 64 bit values, so every literal that could be scanned fits
+            Primitive::I128 => match numeric_literal {
+                NumericLiteral::AsciiChar(character) => character.is_ascii(),
+                NumericLiteral::PositiveInteger(value, _) => Primitive::I128_RANGE.contains(&(*value as i128)),
+                NumericLiteral::NegativeInteger(value, _) => Primitive::I128_RANGE.contains(&(*value as i128)),
+                _ => false
+            },
+            Primitive::U128 => match numeric_literal {
+                NumericLiteral::AsciiChar(character) => character.is_ascii(),
+                NumericLiteral::PositiveInteger(value, _) => Primitive::U128_RANGE.contains(&(*value as u128)),
+                _ => false
+            }
         }
     }
 }
 
+/// Whether a name is a keyword of C up to C23, which cannot be used as the name of a type, member, or define in generated C code
+fn is_c_keyword(name: &str) -> bool {
+    matches!(
+        name,
+        "_Alignas"
+            | "_Alignof"
+            | "_Atomic"
+            | "_BitInt"
+            | "_Bool"
+            | "_Complex"
+            | "_Decimal128"
+            | "_Decimal32"
+            | "_Decimal64"
+            | "_Generic"
+            | "_Imaginary"
+            | "_Noreturn"
+            | "_Pragma"
+            | "_Static_assert"
+            | "_Thread_local"
+            | "alignas"
+            | "alignof"
+            | "auto"
+            | "bool"
+            | "break"
+            | "case"
+            | "char"
+            | "const"
+            | "constexpr"
+            | "continue"
+            | "default"
+            | "do"
+            | "double"
+            | "else"
+            | "enum"
+            | "extern"
+            | "false"
+            | "float"
+            | "for"
+            | "goto"
+            | "if"
+            | "inline"
+            | "int"
+            | "long"
+            | "nullptr"
+            | "register"
+            | "restrict"
+            | "return"
+            | "short"
+            | "signed"
+            | "sizeof"
+            | "static"
+            | "static_assert"
+            | "struct"
+            | "switch"
+            | "thread_local"
+            | "true"
+            | "typedef"
+            | "typeof"
+            | "typeof_unqual"
+            | "union"
+            | "unsigned"
+            | "void"
+            | "volatile"
+            | "while"
+    )
+}
+
 // Overall validation function
-pub fn validate_parsed_files(files: &Vec<RuneFileDescription>) -> Result<(), RuneParserError> {
+pub fn validate_parsed_files(files: &Vec<RuneFileDescription>, warnings: &mut Vec<Warning>) -> Result<(), RuneParserError> {
     info!("Validating declarations");
 
     // Validate all type names (Define, Bitfield, Enum, and Struct) against each other to check for collisions
     validate_names(files)?;
 
+    // Check that no name would break generated C code
+    validate_c_identifiers(files)?;
+
     // Validate bitfields
-    validate_bitfields(files)?;
+    validate_bitfields(files, warnings)?;
 
     // Validate defines - Not needed, as they are mere text replace, and thus have no backing type
 
@@ -155,9 +246,21 @@ pub fn validate_parsed_files(files: &Vec<RuneFileDescription>) -> Result<(), Run
     // Validate messages
     validate_messages(files)?;
 
+    // Warn about fields that have outlived the declared schema version
+    validate_obsolete_fields(files, warnings)?;
+
+    // Warn about deprecations that look like mistakes
+    validate_deprecated_indexes(files, warnings)?;
+
+    // Warn about values reserved more than once
+    validate_repeated_reservations(files, warnings)?;
+
     // Validate structs
     validate_structs(files)?;
 
+    // Validate array sizes
+    validate_arrays(files)?;
+
     Ok(())
 }
 
@@ -171,10 +274,6 @@ pub fn validate_names(files: &Vec<RuneFileDescription>) -> Result<(), RuneParser
         for definition in &file.definitions.bitfields {
             names_list.push(definition.name.clone());
         }
-        // Defines
-        for definition in &file.definitions.defines {
-            names_list.push(definition.name.clone());
-        }
         // Enums
         for definition in &file.definitions.enums {
             names_list.push(definition.name.clone());
@@ -185,13 +284,23 @@ pub fn validate_names(files: &Vec<RuneFileDescription>) -> Result<(), RuneParser
         }
     }
 
-    for i in 0..names_list.len() - 1 {
+    for i in 0..names_list.len().saturating_sub(1) {
         if names_list[i + 1..].contains(&names_list[i]) {
             error!("Found two data types with the name {0}!", names_list[i]);
             return Err(RuneParserError::NameCollision);
         }
     }
 
+    // Defines only have to be unique among the files that can see each other, which is checked while parsing them, but never share a name with a data type
+    for file in files {
+        for definition in &file.definitions.defines {
+            if names_list.contains(&definition.name) {
+                error!("Found a define and a data type with the name {0}!", definition.name);
+                return Err(RuneParserError::NameCollision);
+            }
+        }
+    }
+
     Ok(())
 }
 
@@ -199,10 +308,11 @@ pub fn validate_names(files: &Vec<RuneFileDescription>) -> Result<(), RuneParser
 // ————————————————————
 
 /// Check that no two fields have the same index or identifier, and that the total size of the bitfield is valid
-pub fn validate_bitfields(files: &Vec<RuneFileDescription>) -> Result<(), RuneParserError> {
+pub fn validate_bitfields(files: &Vec<RuneFileDescription>, warnings: &mut Vec<Warning>) -> Result<(), RuneParserError> {
     // Check that there are no two bitfield fields that have the same identifier
     // No use of reserved indexes
     // No duplicate indexes
+    // No members spanning into the bits of other members, or into reserved bits
     // !!! Indexes against backing type with SIZES - No overflow !!!
     //  - Overall index check in done in parser, but it does not take field sizes into account
 
@@ -218,6 +328,39 @@ pub fn validate_bitfields(files: &Vec<RuneFileDescription>) -> Result<(), RunePa
                 // Add bit size to total
                 total_size += member.size.absolute();
 
+                // Check member size
+                // ——————————————————
+
+                match member.size {
+                    BitSize::Signed(0) | BitSize::Unsigned(0) => {
+                        error!("Error at {0}: Field {1} must be at least one bit wide", bitfield_definition.name, identifier);
+                        return Err(RuneParserError::InvalidBitfieldMemberSize);
+                    },
+                    BitSize::Signed(1) => {
+                        let warning = Warning::SingleBitSigned {
+                            file: file.include_path(),
+                            bitfield: bitfield_definition.name.clone(),
+                            member: identifier.clone()
+                        };
+                        warning!("{0}", warning);
+                        warnings.push(warning);
+                    },
+                    _ => ()
+                }
+
+                // Members must end within the backing type, so the unused bits after them are never negative
+                if !bitfield_definition.backing_type.validate_bitfield_size(&(index + member.size.absolute())) {
+                    error!(
+                        "Error at {0}: Field {1} spans bits {2} to {3}, which goes past the end of backing type {4:?}",
+                        bitfield_definition.name,
+                        identifier,
+                        index,
+                        index + member.size.absolute() - 1,
+                        bitfield_definition.backing_type
+                    );
+                    return Err(RuneParserError::InvalidBitfieldMemberSize);
+                }
+
                 // Check field index
                 // ——————————————————
 
@@ -231,7 +374,7 @@ pub fn validate_bitfields(files: &Vec<RuneFileDescription>) -> Result<(), RunePa
                     return Err(RuneParserError::IndexCollision);
                 }
 
-                if bitfield_definition.reserved_indexes.contains(&index) {
+                if bitfield_definition.reserved_indexes.contains(&index) || bitfield_definition.deprecated_indexes.contains(&index) {
                     error!(
                         "Error at {0}: Field {1} was declared with index {2} is declared even though field index {2} is reserved",
                         bitfield_definition.name, identifier, index
@@ -250,6 +393,50 @@ pub fn validate_bitfields(files: &Vec<RuneFileDescription>) -> Result<(), RunePa
                 }
             }
 
+            // Check member spans
+            // ————————————————————
+
+            // Members are sorted by their starting bit, so each member only has to end before the next one starts
+            let mut members: Vec<&BitfieldMember> = bitfield_definition.members.iter().collect();
+            members.sort_by_key(|member| member.index);
+
+            for pair in members.windows(2) {
+                let (member, next_member) = (pair[0], pair[1]);
+
+                if member.index + member.size.absolute() > next_member.index {
+                    error!(
+                        "Error at {0}: Field {1} spans bits {2} to {3}, which overlaps field {4} starting at bit {5}",
+                        bitfield_definition.name,
+                        member.identifier,
+                        member.index,
+                        member.index + member.size.absolute() - 1,
+                        next_member.identifier,
+                        next_member.index
+                    );
+                    return Err(RuneParserError::IndexCollision);
+                }
+            }
+
+            for member in &members {
+                let span = member.index..(member.index + member.size.absolute());
+
+                if let Some(reserved_index) = bitfield_definition
+                    .reserved_indexes
+                    .iter()
+                    .chain(&bitfield_definition.deprecated_indexes)
+                    .find(|&index| span.contains(index)) {
+                    error!(
+                        "Error at {0}: Field {1} spans bits {2} to {3}, which includes the reserved bit {4}",
+                        bitfield_definition.name,
+                        member.identifier,
+                        span.start,
+                        span.end - 1,
+                        reserved_index
+                    );
+                    return Err(RuneParserError::UseOfReservedIndex);
+                }
+            }
+
             // Check if bitfield members can fit within backing type
             if !bitfield_definition.backing_type.validate_bitfield_size(&total_size) {
                 error!(
@@ -264,6 +451,50 @@ pub fn validate_bitfields(files: &Vec<RuneFileDescription>) -> Result<(), RunePa
     Ok(())
 }
 
+// C identifier validation
+// —————————————————————————
+
+/// Check that no definition, member, or define is named after a C keyword, as the generated C code would not compile
+pub fn validate_c_identifiers(files: &Vec<RuneFileDescription>) -> Result<(), RuneParserError> {
+    for file in files {
+        let definitions = &file.definitions;
+
+        let mut names: Vec<(&str, &str, &str)> = Vec::new();
+
+        for definition in &definitions.bitfields {
+            names.push(("Bitfield", &definition.name, &definition.name));
+            names.extend(definition.members.iter().map(|member| ("Bitfield member", definition.name.as_str(), member.identifier.as_str())));
+        }
+        for definition in &definitions.defines {
+            names.push(("Define", &definition.name, &definition.name));
+        }
+        for definition in &definitions.enums {
+            names.push(("Enum", &definition.name, &definition.name));
+            names.extend(definition.members.iter().map(|member| ("Enum member", definition.name.as_str(), member.identifier.as_str())));
+        }
+        for definition in &definitions.messages {
+            names.push(("Message", &definition.name, &definition.name));
+            names.extend(definition.fields.iter().map(|field| ("Message field", definition.name.as_str(), field.identifier.as_str())));
+        }
+        for definition in &definitions.structs {
+            names.push(("Struct", &definition.name, &definition.name));
+            names.extend(definition.members.iter().map(|member| ("Struct member", definition.name.as_str(), member.identifier.as_str())));
+        }
+
+        for (kind, definition_name, name) in names {
+            if is_c_keyword(name) {
+                error!(
+                    "Error at {0}: {1} {2} in {3}.rune is named after a C keyword, and would not compile in generated C code",
+                    definition_name, kind, name, file.name
+                );
+                return Err(RuneParserError::ReservedIdentifier);
+            }
+        }
+    }
+
+    Ok(())
+}
+
 // Enum validation
 // ————————————————
 
@@ -295,7 +526,7 @@ pub fn validate_enums(files: &Vec<RuneFileDescription>) -> Result<(), RuneParser
                     return Err(RuneParserError::ValueCollision);
                 }
 
-                if enum_definition.reserved_values.contains(&value) {
+                if enum_definition.reserved_values.contains(&value) || enum_definition.deprecated_values.contains(&value) {
                     error!(
                         "Error at {0}: Enum member {1} was declared with value {2} even though value {2} is reserved",
                         enum_definition.name,
@@ -315,6 +546,23 @@ pub fn validate_enums(files: &Vec<RuneFileDescription>) -> Result<(), RuneParser
                     return Err(RuneParserError::IdentifierCollision);
                 }
             }
+
+            // Check that all values fit within the encoded type, if the enum is narrowed when encoded
+            // ——————————————————————————————————————————————————————————————————————————————————————————
+
+            if let Some(encoded_type) = &enum_definition.encoded_type {
+                for value in enum_definition.members.iter().map(|member| &member.value).chain(&enum_definition.reserved_values).chain(&enum_definition.deprecated_values) {
+                    if !encoded_type.validate_value(value) {
+                        error!(
+                            "Error at {0}: Value {1} does not fit within encoded type {2:?}",
+                            enum_definition.name,
+                            value.to_string(),
+                            encoded_type
+                        );
+                        return Err(RuneParserError::InvalidNumericValue);
+                    }
+                }
+            }
         }
     }
 
@@ -365,7 +613,7 @@ pub fn validate_messages(files: &Vec<RuneFileDescription>) -> Result<(), RunePar
                     return Err(RuneParserError::IndexCollision);
                 }
 
-                if message_definition.reserved_indexes.contains(&index) {
+                if message_definition.reserved_indexes.contains(&index) || message_definition.deprecated_indexes.contains(&index) {
                     error!(
                         "Error at {0}: Field {1} was declared with index {2} is declared even though field index {2} is reserved",
                         message_definition.name,
@@ -391,6 +639,159 @@ pub fn validate_messages(files: &Vec<RuneFileDescription>) -> Result<(), RunePar
     Ok(())
 }
 
+/// Warn about message fields marked as obsolete after a version older than the version declared by their file
+pub fn validate_obsolete_fields(files: &Vec<RuneFileDescription>, warnings: &mut Vec<Warning>) -> Result<(), RuneParserError> {
+    for file in files {
+        // Only files declaring a version can have obsolete fields
+        let version: u64 = match file.definitions.version {
+            None => continue,
+            Some(version) => version
+        };
+
+        for message_definition in file.definitions.messages.iter().chain(&file.definitions.extensions.messages) {
+            for field in &message_definition.fields {
+                if let Some(obsolete_after) = field.obsolete_after {
+                    if version > obsolete_after {
+                        let warning = Warning::ObsoleteField {
+                            file: file.include_path(),
+                            message: message_definition.name.clone(),
+                            field: field.identifier.clone(),
+                            index: field.index.value(),
+                            obsolete_after,
+                            version
+                        };
+                        warning!("{0}", warning);
+                        warnings.push(warning);
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Warn about every value a definition reserves more than once, across all of its reserve statements, including values covered by
+/// overlapping ranges. Literals are compared by value, so 255 and 0xFF reserve the same value, which is most likely a mistake
+pub fn validate_repeated_reservations(files: &Vec<RuneFileDescription>, warnings: &mut Vec<Warning>) -> Result<(), RuneParserError> {
+    for file in files {
+        let mut report = |definition: &str, value: String, earlier: String| {
+            let warning = Warning::RepeatedReservation {
+                file: file.include_path(),
+                definition: definition.to_string(),
+                value,
+                earlier
+            };
+            warning!("{0}", warning);
+            warnings.push(warning);
+        };
+
+        for bitfield_definition in file.definitions.bitfields.iter().chain(&file.definitions.extensions.bitfields) {
+            for (i, index) in bitfield_definition.reserved_indexes.iter().enumerate() {
+                if bitfield_definition.reserved_indexes[..i].contains(index) {
+                    report(&bitfield_definition.name, index.to_string(), index.to_string());
+                }
+            }
+        }
+
+        for enum_definition in file.definitions.enums.iter().chain(&file.definitions.extensions.enums) {
+            for (i, value) in enum_definition.reserved_values.iter().enumerate() {
+                if let Some(earlier) = enum_definition.reserved_values[..i].iter().find(|&earlier| earlier == value) {
+                    report(&enum_definition.name, value.to_string(), earlier.to_string());
+                }
+            }
+        }
+
+        for message_definition in file.definitions.messages.iter().chain(&file.definitions.extensions.messages) {
+            for (i, index) in message_definition.reserved_indexes.iter().enumerate() {
+                if message_definition.reserved_indexes[..i].contains(index) {
+                    report(&message_definition.name, index.value().to_string(), index.value().to_string());
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Deprecation validation
+// ————————————————————————
+
+/// Number of consecutive deprecated indexes above which a deprecation is considered unusually large
+const DEPRECATED_RUN_LIMIT: usize = 8;
+
+/// Warn about deprecated bitfield and message indexes that span an unusually large range, or that sit right next to an index in use
+pub fn validate_deprecated_indexes(files: &Vec<RuneFileDescription>, warnings: &mut Vec<Warning>) -> Result<(), RuneParserError> {
+    for file in files {
+        let include_path: String = file.include_path();
+
+        for bitfield_definition in &file.definitions.bitfields {
+            let used_bits: Vec<u64> = bitfield_definition.members.iter().flat_map(|member| member.index..(member.index + member.size.absolute())).collect();
+
+            warn_about_deprecations(&include_path, &bitfield_definition.name, &bitfield_definition.deprecated_indexes, &used_bits, warnings);
+        }
+
+        for message_definition in file.definitions.messages.iter().chain(&file.definitions.extensions.messages) {
+            let deprecated_indexes: Vec<u64> = message_definition.deprecated_indexes.iter().map(|index| index.value()).collect();
+            let used_indexes: Vec<u64> = message_definition.fields.iter().map(|field| field.index.value()).collect();
+
+            warn_about_deprecations(&include_path, &message_definition.name, &deprecated_indexes, &used_indexes, warnings);
+        }
+    }
+
+    Ok(())
+}
+
+fn warn_about_deprecations(include_path: &str, definition_name: &str, deprecated_indexes: &[u64], used_indexes: &[u64], warnings: &mut Vec<Warning>) {
+    let mut sorted_indexes: Vec<u64> = deprecated_indexes.to_vec();
+    sorted_indexes.sort_unstable();
+    sorted_indexes.dedup();
+
+    // Split the deprecated indexes into runs of consecutive indexes
+    let mut runs: Vec<&[u64]> = Vec::new();
+    let mut run_start: usize = 0;
+
+    for i in 1..=sorted_indexes.len() {
+        if i == sorted_indexes.len() || sorted_indexes[i] != sorted_indexes[i - 1] + 1 {
+            runs.push(&sorted_indexes[run_start..i]);
+            run_start = i;
+        }
+    }
+
+    for run in runs {
+        let (first, last) = (run[0], run[run.len() - 1]);
+
+        if run.len() > DEPRECATED_RUN_LIMIT {
+            let warning = Warning::LongDeprecatedRange {
+                file: include_path.to_string(),
+                definition: definition_name.to_string(),
+                first,
+                last
+            };
+            warning!("{0}", warning);
+            warnings.push(warning);
+        }
+
+        let neighbours = [first.checked_sub(1), last.checked_add(1)];
+
+        for neighbour in neighbours.into_iter().flatten() {
+            if used_indexes.contains(&neighbour) {
+                let warning = Warning::DeprecatedNextToUsed {
+                    file: include_path.to_string(),
+                    definition: definition_name.to_string(),
+                    deprecated: match neighbour < first {
+                        true => first,
+                        false => last
+                    },
+                    used: neighbour
+                };
+                warning!("{0}", warning);
+                warnings.push(warning);
+            }
+        }
+    }
+}
+
 // Struct validation
 // ——————————————————
 
@@ -432,3 +833,611 @@ pub fn validate_structs(files: &Vec<RuneFileDescription>) -> Result<(), RunePars
 
     Ok(())
 }
+
+// Array validation
+// —————————————————
+
+/// Check that every array dimension holds at least one element, and that arrays in messages fit in a length prefix. Extension bodies are
+/// checked as well, as they are not merged into the definitions they extend unless requested
+pub fn validate_arrays(files: &Vec<RuneFileDescription>) -> Result<(), RuneParserError> {
+    for file in files {
+        for message_definition in file.definitions.messages.iter().chain(&file.definitions.extensions.messages) {
+            for field in &message_definition.fields {
+                if let FieldType::Value(ValueType::Array(array)) = &field.data_type {
+                    validate_array_dimensions(array, &message_definition.name, &field.identifier)?;
+                    validate_text_default(array, &field.default, field.cstring, &message_definition.name, &field.identifier)?;
+
+                    // The sizes of unlinked user defined types are unknown, such as when parsing a single string
+                    if matches!(array.innermost_type(), ArrayType::UserDefined(_, UserDefinitionLink::NoLink)) {
+                        continue;
+                    }
+
+                    // Array data is sent after a length prefix, which can hold at most a 32 bit length
+                    if let Ok(byte_size) = array.byte_size() {
+                        if !Primitive::U32_RANGE.contains(&byte_size) {
+                            error!(
+                                "Error at {0}: Array field {1} is {2} bytes long, which is more than a length prefix can hold. Arrays in messages can be at most {3} bytes",
+                                message_definition.name,
+                                field.identifier,
+                                byte_size,
+                                Primitive::U32_RANGE.end()
+                            );
+                            return Err(RuneParserError::InvalidArraySize);
+                        }
+                    }
+                }
+            }
+        }
+
+        for struct_definition in file.definitions.structs.iter().chain(&file.definitions.extensions.structs) {
+            for member in &struct_definition.members {
+                if let ValueType::Array(array) = &member.data_type {
+                    validate_array_dimensions(array, &struct_definition.name, &member.identifier)?;
+                    validate_text_default(array, &member.default, member.cstring, &struct_definition.name, &member.identifier)?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Check that the text default of a char array fits in it, leaving room for the terminating NUL of C strings
+fn validate_text_default(array: &Array, default: &Option<DefineValue>, cstring: bool, definition_name: &str, identifier: &str) -> Result<(), RuneParserError> {
+    let (text, size) = match (default, array.element_count.value()) {
+        (Some(DefineValue::StringLiteral(text)), Ok(size)) => (text, size),
+        _ => return Ok(())
+    };
+
+    let capacity: u64 = match cstring {
+        true => size.saturating_sub(1),
+        false => size
+    };
+
+    if text.len() as u64 > capacity {
+        error!(
+            "Error at {0}: Default value \"{1}\" of {2} is {3} characters long, but the array only holds {4}{5}",
+            definition_name,
+            text,
+            identifier,
+            text.len(),
+            capacity,
+            if cstring { " besides the terminating NUL" } else { "" }
+        );
+        return Err(RuneParserError::InvalidArraySize);
+    }
+
+    Ok(())
+}
+
+/// Reject empty arrays, including empty inner dimensions of nested arrays, and arrays sized by a define that could not be resolved
+fn validate_array_dimensions(array: &Array, definition_name: &str, identifier: &str) -> Result<(), RuneParserError> {
+    match array.element_count.value() {
+        Ok(0) => {
+            error!("Error at {0}: Array {1} is declared with a size of 0! Arrays must hold at least one element", definition_name, identifier);
+            return Err(RuneParserError::InvalidArraySize);
+        },
+        Err(error) => {
+            error!("Error at {0}: Size {1} of array {2} is not a known define", definition_name, array.element_count, identifier);
+            return Err(error);
+        },
+        Ok(_) => ()
+    }
+
+    if let ArrayType::Array(inner_array) = &array.data_type {
+        validate_array_dimensions(inner_array, definition_name, identifier)?;
+    }
+
+    Ok(())
+}
+
+/// Check that no array dimension holds more elements than the given size type can count, such as 256 elements with a one byte size type.
+/// Runs after [`validate_arrays`], so every array size is known
+pub fn validate_array_lengths(files: &[RuneFileDescription], size_type: &Primitive) -> Result<(), RuneParserError> {
+    let max_length: u64 = match size_type {
+        Primitive::U8 => *Primitive::U8_RANGE.end(),
+        Primitive::U16 => *Primitive::U16_RANGE.end(),
+        Primitive::U32 => *Primitive::U32_RANGE.end(),
+        Primitive::U64 => u64::MAX,
+        _ => {
+            error!("Array size type must be an unsigned integer, but got {0}", size_type.name());
+            return Err(RuneParserError::InvalidTypeUse);
+        }
+    };
+
+    for file in files {
+        for message_definition in file.definitions.messages.iter().chain(&file.definitions.extensions.messages) {
+            for field in &message_definition.fields {
+                if let FieldType::Value(ValueType::Array(array)) = &field.data_type {
+                    validate_array_length(array, max_length, size_type, &message_definition.name, &field.identifier)?;
+                }
+            }
+        }
+
+        for struct_definition in file.definitions.structs.iter().chain(&file.definitions.extensions.structs) {
+            for member in &struct_definition.members {
+                if let ValueType::Array(array) = &member.data_type {
+                    validate_array_length(array, max_length, size_type, &struct_definition.name, &member.identifier)?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Check an array and every array nested inside it against the largest length of the size type
+fn validate_array_length(array: &Array, max_length: u64, size_type: &Primitive, definition_name: &str, identifier: &str) -> Result<(), RuneParserError> {
+    let length: u64 = array.element_count.value()?;
+
+    if length > max_length {
+        error!(
+            "Error at {0}: Array {1} holds {2} elements, but its size type {3} can count at most {4}",
+            definition_name,
+            identifier,
+            length,
+            size_type.name(),
+            max_length
+        );
+        return Err(RuneParserError::InvalidArraySize);
+    }
+
+    if let ArrayType::Array(inner_array) = &array.data_type {
+        validate_array_length(inner_array, max_length, size_type, definition_name, identifier)?;
+    }
+
+    Ok(())
+}
+
+// Unused definitions
+// ———————————————————
+
+/// Warn about every bitfield, enum, struct, and define that no message uses, directly or through other definitions. All messages, including
+/// those declared by extensions, are used. Extensions themselves are never reported, but the types they use count as used
+pub fn find_unused_definitions(files: &[RuneFileDescription], warnings: &mut Vec<Warning>) {
+    let mut used_types: Vec<&str> = Vec::new();
+    let mut used_defines: Vec<&str> = Vec::new();
+
+    // Walk the types of every message field, and of the members of every struct found on the way
+    let mut pending: Vec<&ValueType> = files
+        .iter()
+        .flat_map(|file| file.definitions.messages.iter().chain(&file.definitions.extensions.messages))
+        .flat_map(|message_definition| message_definition.fields.iter().filter_map(|field| field.data_type.value_type()))
+        .collect();
+
+    while let Some(value_type) = pending.pop() {
+        let name: &str = match value_type {
+            ValueType::Primitive(_) => continue,
+            ValueType::UserDefined(name, _) => name,
+            ValueType::Array(array) => {
+                collect_array_defines(array, &mut used_defines);
+
+                match array.innermost_type() {
+                    ArrayType::UserDefined(name, _) => name,
+                    _ => continue
+                }
+            }
+        };
+
+        if used_types.contains(&name) {
+            continue;
+        }
+        used_types.push(name);
+
+        // Members added to a struct by an extension are used along with the struct
+        for file in files {
+            for struct_definition in file.definitions.structs.iter().chain(&file.definitions.extensions.structs).filter(|definition| definition.name == name) {
+                pending.extend(struct_definition.members.iter().map(|member| &member.data_type));
+            }
+        }
+    }
+
+    // Defines used by the value of another used define are used as well
+    let mut next: usize = 0;
+    while next < used_defines.len() {
+        let used_name: &str = used_defines[next];
+
+        for file in files {
+            for define_definition in file.definitions.defines.iter().filter(|definition| definition.name == used_name) {
+                for name in define_references(define_definition) {
+                    if !used_defines.contains(&name) {
+                        used_defines.push(name);
+                    }
+                }
+            }
+        }
+        next += 1;
+    }
+
+    for file in files {
+        let definitions = &file.definitions;
+
+        let unused_types = (definitions.bitfields.iter().map(|definition| &definition.name))
+            .chain(definitions.enums.iter().map(|definition| &definition.name))
+            .chain(definitions.structs.iter().map(|definition| &definition.name))
+            .filter(|name| !used_types.contains(&name.as_str()));
+
+        let unused_defines = definitions.defines.iter().map(|definition| &definition.name).filter(|name| !used_defines.contains(&name.as_str()));
+
+        for name in unused_types.chain(unused_defines) {
+            let warning = Warning::UnusedDefinition { file: file.include_path(), name: name.clone() };
+            warning!("{0}", warning);
+            warnings.push(warning);
+        }
+    }
+}
+
+/// Names of the defines giving the sizes of an array and of every array nested inside it
+fn collect_array_defines<'a>(array: &'a Array, used_defines: &mut Vec<&'a str>) {
+    if let ArraySize::UserDefinition(definition) = &array.element_count {
+        if !used_defines.contains(&definition.name.as_str()) {
+            used_defines.push(&definition.name);
+        }
+    }
+
+    if let ArrayType::Array(inner_array) = &array.data_type {
+        collect_array_defines(inner_array, used_defines);
+    }
+}
+
+/// Names of the defines referred to by the value of a define, or by its redefinition
+fn define_references(definition: &DefineDefinition) -> Vec<&str> {
+    let mut names: Vec<&str> = Vec::new();
+
+    for value in std::iter::once(&definition.value).chain(definition.redefinition.as_ref().map(|redefinition| &redefinition.value)) {
+        match value {
+            DefineValue::Alias(name) => names.push(name),
+            DefineValue::Expression(expression) => collect_expression_references(expression, &mut names),
+            _ => ()
+        }
+    }
+
+    names
+}
+
+fn collect_expression_references<'a>(expression: &'a DefineExpression, names: &mut Vec<&'a str>) {
+    match expression {
+        DefineExpression::Literal(_) => (),
+        DefineExpression::Identifier(name) => names.push(name),
+        DefineExpression::Binary(left, _, right) => {
+            collect_expression_references(left, names);
+            collect_expression_references(right, names);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::validate_parsed_files;
+    use crate::{
+        parse_str,
+        scanner::{NumeralSystem, NumericLiteral},
+        test_utils::{parse_files, parse_files_with_options, parse_files_with_warnings},
+        types::{BitSize, DefineValue, Primitive, ValueType},
+        ParserOptions,
+        RuneParserError,
+        Warning
+    };
+
+    const OBSOLETE_SOURCE: &str = "message Reading {\n    @obsolete_after(4)\n    legacy: u16 = 1;\n    value: u32 = 2;\n}\n";
+
+    #[test]
+    fn obsolete_field_warns_past_its_version() {
+        let mut warnings: Vec<Warning> = Vec::new();
+        parse_files_with_warnings(&[("reading.rune", &format!("version 5;\n\n{0}", OBSOLETE_SOURCE))], &mut warnings).unwrap();
+
+        let obsolete: Vec<&Warning> = warnings.iter().filter(|warning| matches!(warning, Warning::ObsoleteField { .. })).collect();
+        assert_eq!(obsolete.len(), 1);
+        assert!(matches!(obsolete[0], Warning::ObsoleteField { field, obsolete_after: 4, version: 5, .. } if field == "legacy"));
+    }
+
+    #[test]
+    fn obsolete_field_is_quiet_up_to_its_version() {
+        let mut warnings: Vec<Warning> = Vec::new();
+        parse_files_with_warnings(&[("reading.rune", &format!("version 4;\n\n{0}", OBSOLETE_SOURCE))], &mut warnings).unwrap();
+
+        assert!(!warnings.iter().any(|warning| matches!(warning, Warning::ObsoleteField { .. })));
+    }
+
+    fn repeated_reservations(source: &str) -> Vec<(String, String)> {
+        let mut warnings: Vec<Warning> = Vec::new();
+        parse_files_with_warnings(&[("reserved.rune", source)], &mut warnings).unwrap();
+
+        warnings
+            .into_iter()
+            .filter_map(|warning| match warning {
+                Warning::RepeatedReservation { value, earlier, .. } => Some((value, earlier)),
+                _ => None
+            })
+            .collect()
+    }
+
+    #[test]
+    fn same_value_in_another_numeral_system_warns() {
+        let warnings = repeated_reservations("enum Level: u8 {\n    Low = 0;\n    reserve 254, 0xFE;\n}\n");
+        assert_eq!(warnings, vec![(String::from("0xFE"), String::from("254"))]);
+    }
+
+    #[test]
+    fn repeated_reservations_across_statements_warn() {
+        let warnings = repeated_reservations("message Packet {\n    a: u8 = 1;\n    reserve 3..6;\n    reserve 5..7;\n    reserve 9;\n    reserve 9;\n}\n");
+        assert_eq!(warnings, vec![(String::from("5"), String::from("5")), (String::from("9"), String::from("9"))]);
+    }
+
+    #[test]
+    fn distinct_reservations_are_quiet() {
+        assert!(repeated_reservations("bitfield Flags: u8 {\n    a: u1 = 0;\n    reserve 1..3;\n    reserve 4;\n}\n").is_empty());
+    }
+
+    fn deprecation_warnings(source: &str) -> Vec<Warning> {
+        let mut warnings: Vec<Warning> = Vec::new();
+        parse_files_with_warnings(&[("deprecated.rune", source)], &mut warnings).unwrap();
+
+        warnings.retain(|warning| matches!(warning, Warning::LongDeprecatedRange { .. } | Warning::DeprecatedNextToUsed { .. }));
+        warnings
+    }
+
+    #[test]
+    fn long_deprecated_range_warns() {
+        let warnings = deprecation_warnings("message Packet {\n    a: u8 = 1;\n    deprecate 10..20;\n}\n");
+
+        assert_eq!(warnings.len(), 1);
+        assert!(matches!(&warnings[0], Warning::LongDeprecatedRange { file, definition, first: 10, last: 19 } if file == "deprecated" && definition == "Packet"));
+    }
+
+    #[test]
+    fn deprecation_next_to_a_used_index_warns() {
+        let warnings = deprecation_warnings("bitfield Flags: u8 {\n    a: u2 = 0;\n    deprecate 2;\n}\n\nmessage Packet {\n    a: u8 = 1;\n    deprecate 2..4;\n}\n");
+
+        assert_eq!(warnings.len(), 2);
+        assert!(matches!(&warnings[0], Warning::DeprecatedNextToUsed { definition, deprecated: 2, used: 1, .. } if definition == "Flags"));
+        assert!(matches!(&warnings[1], Warning::DeprecatedNextToUsed { definition, deprecated: 2, used: 1, .. } if definition == "Packet"));
+    }
+
+    #[test]
+    fn isolated_deprecation_is_quiet() {
+        assert!(deprecation_warnings("message Packet {\n    a: u8 = 1;\n    deprecate 5..7;\n}\n").is_empty());
+    }
+
+    #[test]
+    fn empty_arrays_are_rejected() {
+        for member in ["data: [u8; 0]", "matrix: [[u8; 0]; 4]"] {
+            let result = parse_str(&format!("struct Buffer {{\n    {0} = 0;\n}}\n", member), "buffer.rune");
+            assert!(matches!(result, Err(RuneParserError::InvalidArraySize)), "{0} was accepted", member);
+        }
+    }
+
+    #[test]
+    fn message_arrays_must_fit_a_length_prefix() {
+        // 2^29 elements of 8 bytes are 4 GiB, one byte more than a 32 bit length can hold
+        let result = parse_str("message Blob {\n    data: [u64; 0x20000000] = 1;\n}\n", "blob.rune");
+        assert!(matches!(result, Err(RuneParserError::InvalidArraySize)));
+
+        assert!(parse_str("message Blob {\n    data: [u64; 0x1000000] = 1;\n}\n", "blob.rune").is_ok());
+    }
+
+    #[test]
+    fn empty_extension_arrays_are_rejected() {
+        let base: (&str, &str) = ("base.rune", "message Packet {\n    a: u8 = 1;\n}\n\nstruct Point {\n    x: u8 = 0;\n}\n");
+        let merged = ParserOptions {
+            append_extensions: true,
+            silent:            true,
+            warn_unused:       false,
+            array_size_type:   None
+        };
+        let separate = ParserOptions { append_extensions: false, ..merged.clone() };
+
+        for extension in ["extend message Packet {\n    b: [u8; N] = 2;\n}\n", "extend struct Point {\n    y: [u8; N] = 1;\n}\n"] {
+            let files = [base, ("extra.rune", &format!("include \"base\";\n\ndefine N 0;\n\n{0}", extension))];
+
+            for options in [&merged, &separate] {
+                let result = parse_files_with_options(&files, options, &mut Vec::new());
+                assert!(matches!(result, Err(RuneParserError::InvalidArraySize)), "{0} was accepted", extension);
+            }
+        }
+    }
+
+    #[test]
+    fn unresolved_array_size_is_rejected() {
+        assert!(matches!(parse_str("struct Buffer {\n    data: [u8; MISSING] = 0;\n}\n", "buffer.rune"), Err(RuneParserError::InvalidArraySize)));
+        assert!(matches!(parse_files(&[("buffer.rune", "message Buffer {\n    data: [u8; MISSING] = 1;\n}\n")]), Err(RuneParserError::InvalidArraySize)));
+
+        // Defines declared in the same string are resolved
+        assert!(parse_str("define SIZE 4;\n\nstruct Buffer {\n    data: [u8; SIZE] = 0;\n}\n", "buffer.rune").is_ok());
+    }
+
+    fn with_size_type(source: &str, size_type: Primitive) -> Result<(), RuneParserError> {
+        let options = ParserOptions {
+            append_extensions: true,
+            silent:            true,
+            warn_unused:       false,
+            array_size_type:   Some(size_type)
+        };
+        parse_files_with_options(&[("sizes.rune", source)], &options, &mut Vec::new()).map(|_| ())
+    }
+
+    #[test]
+    fn arrays_must_fit_the_size_type() {
+        assert!(with_size_type("message Blob {\n    data: [u8; 255] = 1;\n}\n", Primitive::U8).is_ok());
+        assert!(matches!(with_size_type("message Blob {\n    data: [u8; 256] = 1;\n}\n", Primitive::U8), Err(RuneParserError::InvalidArraySize)));
+        assert!(with_size_type("message Blob {\n    data: [u8; 256] = 1;\n}\n", Primitive::U16).is_ok());
+
+        // Struct arrays and inner dimensions count their elements with the same type
+        let grid: &str = "struct Grid {\n    cells: [[u8; 300]; 2] = 0;\n}\n";
+        assert!(matches!(with_size_type(grid, Primitive::U8), Err(RuneParserError::InvalidArraySize)));
+        assert!(with_size_type(grid, Primitive::U16).is_ok());
+        assert!(parse_files(&[("grid.rune", grid)]).is_ok());
+    }
+
+    #[test]
+    fn size_type_must_be_unsigned() {
+        assert!(matches!(with_size_type("message Blob {\n    data: [u8; 4] = 1;\n}\n", Primitive::I16), Err(RuneParserError::InvalidTypeUse)));
+    }
+
+    fn bitfield(members: &str) -> Result<crate::types::Definitions, RuneParserError> {
+        parse_str(&format!("bitfield Flags: u8 {{\n{0}\n}}\n", members), "flags.rune")
+    }
+
+    #[test]
+    fn overlapping_bitfield_members_collide() {
+        assert!(matches!(bitfield("    a: u4 = 0;\n    b: u2 = 2;"), Err(RuneParserError::IndexCollision)));
+    }
+
+    #[test]
+    fn adjacent_bitfield_members_do_not_collide() {
+        assert!(bitfield("    a: u4 = 0;\n    b: u2 = 4;").is_ok());
+    }
+
+    #[test]
+    fn bitfield_member_over_reserved_bits_is_rejected() {
+        assert!(matches!(bitfield("    a: u4 = 0;\n    reserve 3;"), Err(RuneParserError::UseOfReservedIndex)));
+        assert!(bitfield("    a: u4 = 0;\n    reserve 4..8;").is_ok());
+    }
+
+    #[test]
+    fn c_keyword_as_member_name_is_rejected() {
+        assert!(matches!(parse_str("struct Device {\n    int: u8 = 0;\n}\n", "device.rune"), Err(RuneParserError::ReservedIdentifier)));
+        assert!(matches!(parse_str("message Loop {\n    while: u8 = 1;\n}\n", "loop.rune"), Err(RuneParserError::ReservedIdentifier)));
+    }
+
+    #[test]
+    fn wide_integer_field_is_parsed() {
+        let definitions = parse_str("message Ledger {\n    total: u128 = 1 default 18446744073709551615;\n}\n", "ledger.rune").unwrap();
+        let field = &definitions.find_message("Ledger").unwrap().fields[0];
+
+        assert!(matches!(field.data_type.value_type(), Some(ValueType::Primitive(Primitive::U128))));
+    }
+
+    #[test]
+    fn wide_integers_accept_every_64_bit_value() {
+        assert!(Primitive::U128.validate_value(&NumericLiteral::PositiveInteger(u64::MAX, NumeralSystem::Decimal)));
+        assert!(!Primitive::U128.validate_value(&NumericLiteral::NegativeInteger(-1, NumeralSystem::Decimal)));
+
+        assert!(Primitive::I128.validate_value(&NumericLiteral::PositiveInteger(u64::MAX, NumeralSystem::Decimal)));
+        assert!(Primitive::I128.validate_value(&NumericLiteral::NegativeInteger(i64::MIN, NumeralSystem::Decimal)));
+    }
+
+    #[test]
+    fn half_float_enum_accepts_its_largest_value() {
+        assert!(parse_str("enum Scale: f16 {\n    Max = 65504.0;\n    Min = -65504.0;\n}\n", "scale.rune").is_ok());
+
+        for value in ["65505.0", "70000.0"] {
+            let result = parse_str(&format!("enum Scale: f16 {{\n    Max = {0};\n}}\n", value), "scale.rune");
+            assert!(result.is_err(), "{0} should not fit in an f16", value);
+        }
+    }
+
+    #[test]
+    fn integer_ranges_include_their_maximum() {
+        assert!(Primitive::U8.validate_value(&NumericLiteral::PositiveInteger(255, NumeralSystem::Decimal)));
+        assert!(!Primitive::U8.validate_value(&NumericLiteral::PositiveInteger(256, NumeralSystem::Decimal)));
+        assert!(Primitive::I8.validate_value(&NumericLiteral::NegativeInteger(-128, NumeralSystem::Decimal)));
+        assert!(Primitive::U16.validate_value(&NumericLiteral::PositiveInteger(u16::MAX as u64, NumeralSystem::Hexadecimal)));
+        assert!(Primitive::U32.validate_value(&NumericLiteral::PositiveInteger(u32::MAX as u64, NumeralSystem::Hexadecimal)));
+        assert!(Primitive::I64.validate_value(&NumericLiteral::PositiveInteger(i64::MAX as u64, NumeralSystem::Decimal)));
+        assert!(!Primitive::I64.validate_value(&NumericLiteral::PositiveInteger(i64::MAX as u64 + 1, NumeralSystem::Decimal)));
+
+        assert!(parse_str("enum Level: u8 {\n    Max = 255;\n}\n", "level.rune").is_ok());
+        assert!(parse_str("enum Level: u8 {\n    Max = 256;\n}\n", "level.rune").is_err());
+    }
+
+    #[test]
+    fn literal_beyond_64_bits_is_not_truncated() {
+        let result = parse_str("message Ledger {\n    total: u128 = 1 default 18446744073709551616;\n}\n", "ledger.rune");
+        assert!(matches!(result, Err(RuneParserError::InvalidToken)));
+    }
+
+    #[test]
+    fn zero_width_bitfield_member_is_rejected() {
+        // The parser rejects u0 and i0, so only definitions built in code can have zero width members
+        let mut files = parse_files(&[("flags.rune", "bitfield Flags: u8 {\n    a: i3 = 0;\n}\n")]).unwrap();
+        files[0].definitions.bitfields[0].members[0].size = BitSize::Signed(0);
+
+        assert!(matches!(validate_parsed_files(&files, &mut Vec::new()), Err(RuneParserError::InvalidBitfieldMemberSize)));
+    }
+
+    #[test]
+    fn single_bit_signed_member_is_accepted() {
+        let mut warnings: Vec<Warning> = Vec::new();
+        parse_files_with_warnings(&[("flags.rune", "bitfield Flags: u8 {\n    a: i1 = 0;\n    b: u1 = 1;\n}\n")], &mut warnings).unwrap();
+
+        let single_bits: Vec<&Warning> = warnings.iter().filter(|warning| matches!(warning, Warning::SingleBitSigned { .. })).collect();
+        assert_eq!(single_bits.len(), 1);
+        assert!(matches!(single_bits[0], Warning::SingleBitSigned { file, bitfield, member } if file == "flags" && bitfield == "Flags" && member == "a"));
+    }
+
+    #[test]
+    fn bitfield_member_past_the_backing_type_is_rejected() {
+        assert!(matches!(bitfield("    a: u4 = 6;"), Err(RuneParserError::InvalidBitfieldMemberSize)));
+        assert!(bitfield("    a: u4 = 4;").is_ok());
+    }
+
+    fn named(member: &str) -> Result<crate::types::Definitions, RuneParserError> {
+        parse_str(&format!("struct Named {{\n    {0};\n}}\n", member), "named.rune")
+    }
+
+    #[test]
+    fn char_array_takes_a_text_default() {
+        let plain = named("name: [char; 8] = 0").unwrap();
+        assert!(plain.find_struct("Named").unwrap().members[0].default.is_none());
+
+        let greeting = named("name: [char; 8] = 0 default \"hi\"").unwrap();
+        assert!(matches!(&greeting.find_struct("Named").unwrap().members[0].default, Some(DefineValue::StringLiteral(text)) if text == "hi"));
+    }
+
+    #[test]
+    fn text_default_must_fit_the_char_array() {
+        assert!(named("name: [char; 8] = 0 default \"12345678\"").is_ok());
+        assert!(matches!(named("name: [char; 8] = 0 default \"123456789\""), Err(RuneParserError::InvalidArraySize)));
+
+        // C strings keep the last byte for the terminating NUL
+        assert!(matches!(named("@cstring\n    name: [char; 8] = 0 default \"12345678\""), Err(RuneParserError::InvalidArraySize)));
+    }
+
+    #[test]
+    fn text_default_must_be_ascii() {
+        assert!(matches!(named("name: [char; 8] = 0 default \"h\u{e9}\""), Err(RuneParserError::InvalidSyntax)));
+    }
+
+    fn unused_definitions(files: &[(&str, &str)]) -> Vec<String> {
+        let options = ParserOptions {
+            append_extensions: true,
+            silent:            true,
+            warn_unused:       true,
+            array_size_type:   None
+        };
+        let mut warnings: Vec<Warning> = Vec::new();
+        parse_files_with_options(files, &options, &mut warnings).unwrap();
+
+        warnings
+            .into_iter()
+            .filter_map(|warning| match warning {
+                Warning::UnusedDefinition { name, .. } => Some(name),
+                _ => None
+            })
+            .collect()
+    }
+
+    #[test]
+    fn only_orphan_struct_is_reported_as_unused() {
+        let source = "struct Used {\n    a: u8 = 0;\n}\n\nstruct Orphan {\n    a: u8 = 0;\n}\n\nmessage Packet {\n    used: Used = 1;\n}\n";
+        assert_eq!(unused_definitions(&[("packet.rune", source)]), ["Orphan"]);
+    }
+
+    #[test]
+    fn types_used_by_extensions_are_not_reported() {
+        let files = [
+            ("base.rune", "struct Point {\n    x: u8 = 0;\n}\n\nmessage Packet {\n    a: u8 = 1;\n}\n"),
+            ("extra.rune", "include \"base\";\n\nextend message Packet {\n    at: Point = 2;\n}\n")
+        ];
+        assert!(unused_definitions(&files).is_empty());
+    }
+
+    #[test]
+    fn unused_definitions_are_only_reported_when_asked() {
+        let mut warnings: Vec<Warning> = Vec::new();
+        parse_files_with_warnings(&[("orphan.rune", "struct Orphan {\n    a: u8 = 0;\n}\n")], &mut warnings).unwrap();
+
+        assert!(!warnings.iter().any(|warning| matches!(warning, Warning::UnusedDefinition { .. })));
+    }
+}